@@ -0,0 +1,55 @@
+// Compares resolving every tile of the mystic_mountain map, as the renderer does every frame,
+// via searching the tilesets and via the precomputed gid table.
+//
+// cargo bench --bench tile_lookup
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use oml_game::system::filesystem_disk::FilesystemDisk;
+use oml_game::system::System;
+use rar_rs::rar::Map;
+
+const FRAMES: u32 = 200;
+
+fn time_frames(map: &Map, lookup: &dyn Fn(&Map, u32) -> bool) -> (Duration, usize) {
+	let mut found = 0;
+	let start = Instant::now();
+	for _ in 0..FRAMES {
+		for l in map.layers() {
+			for c in l.chunks() {
+				let tm = c.tile_map();
+				for y in 0..tm.height() {
+					for x in 0..tm.width() {
+						let tid = tm.get_xy(x, y);
+						if tid > 0 && lookup(map, black_box(tid)) {
+							found += 1;
+						}
+					}
+				}
+			}
+		}
+	}
+	(start.elapsed() / FRAMES, found / FRAMES as usize)
+}
+
+fn main() -> anyhow::Result<()> {
+	let datadir = format!("{}/../data/base", env!("CARGO_MANIFEST_DIR"));
+	let mut system = System::new();
+	system.set_default_filesystem(Box::new(FilesystemDisk::new(&datadir)));
+
+	let mut map = Map::new();
+	map.load(&mut system, "mystic_mountain")?;
+	map.load_all_tilesets(&mut system)?;
+
+	let (linear, tiles) = time_frames(&map, &|m, tid| m.find_tile_image(tid).is_some());
+	let (table, _) = time_frames(&map, &|m, tid| m.get_tile_image(tid).is_some());
+
+	println!(
+		"mystic_mountain: {} tiles per frame, {} frames",
+		tiles, FRAMES
+	);
+	println!("  tileset search: {:>10.2?} per frame", linear);
+	println!("  gid table:      {:>10.2?} per frame", table);
+	Ok(())
+}
@@ -0,0 +1,13 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.8" tiledversion="1.9.0" name="blocks" tilewidth="64" tileheight="64" tilecount="2" columns="0">
+ <grid orientation="orthogonal" width="1" height="1"/>
+ <tile id="0">
+  <image width="64" height="64" source="../blocks/tile_default_block.png"/>
+ </tile>
+ <tile id="1">
+  <properties>
+   <property name="hazard" type="bool" value="true"/>
+  </properties>
+  <image width="64" height="64" source="../blocks/tile_spikes.png"/>
+ </tile>
+</tileset>
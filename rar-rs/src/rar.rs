@@ -22,17 +22,25 @@ pub mod effect_ids;
 pub mod entities;
 mod entity_update_context;
 pub mod font_ids;
+pub mod layer_config;
 pub mod layer_ids;
 pub use entity_update_context::EntityUpdateContext;
+pub mod input_actions;
 mod player_input_context;
-pub use player_input_context::PlayerInputContext;
+pub use player_input_context::{PlayerInputContext, PlayerInputSample, PlayerInputTracker};
 
 mod camera;
+mod camera_effects;
+mod collision;
+mod debug_draw;
 
 mod map;
 pub use map::Map;
+mod map_streamer;
+pub use map_streamer::MapStreamer;
+mod tiled_xml;
 mod tileset;
-pub use tileset::Tileset;
+pub use tileset::{TileImage, Tileset};
 mod world;
 pub use world::World;
 mod world_list;
@@ -2,6 +2,7 @@ use oml_game::math::Vector2;
 use oml_game::window::window_update_context::WindowUpdateContext;
 use tracing::*;
 
+use crate::rar::input_actions::InputActionState;
 use crate::rar::AudioMessage;
 use crate::ui::UiUpdateContext;
 
@@ -13,6 +14,7 @@ pub struct AppUpdateContext {
 	sound_tx:          Option<std::sync::mpsc::Sender<AudioMessage>>,
 	is_music_playing:  bool,
 	is_sound_enabled:  bool,
+	input_state:       InputActionState,
 	ui_update_context: Option<Box<dyn UiUpdateContext>>,
 }
 
@@ -25,6 +27,7 @@ impl AppUpdateContext {
 			sound_tx:          None,
 			is_music_playing:  false,
 			is_sound_enabled:  true,
+			input_state:       InputActionState::default(),
 			ui_update_context: None,
 		}
 	}
@@ -82,6 +85,15 @@ impl AppUpdateContext {
 		self.is_sound_enabled
 	}
 
+	pub fn with_input_state(mut self, input_state: InputActionState) -> Self {
+		self.input_state = input_state;
+		self
+	}
+
+	pub fn input_state(&self) -> &InputActionState {
+		&self.input_state
+	}
+
 	pub fn with_ui_update_context(mut self, ui_update_context: Box<dyn UiUpdateContext>) -> Self {
 		self.ui_update_context = Some(ui_update_context);
 		self
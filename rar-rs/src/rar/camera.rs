@@ -3,6 +3,8 @@ use oml_game::math::Rectangle;
 use oml_game::math::Vector2;
 use tracing::*;
 
+use crate::rar::camera_effects::{CameraEffect, CameraEffects, CameraEffectsState};
+use crate::rar::collision;
 use crate::rar::entities::{EntityId, EntityManager, Player};
 
 #[derive(Debug, Default)]
@@ -17,40 +19,231 @@ pub enum CameraMode {
 	FollowPlayerEntityId {
 		id: EntityId,
 	},
+	// keeps all living players in frame, zooming out if needed
+	FollowPlayerGroup {
+		ids: Vec<EntityId>,
+	},
 	#[default]
 	None,
 }
 
+/// Anything the camera can follow.
+pub trait CameraTarget {
+	fn camera_target_pos(&self) -> Vector2;
+	fn camera_target_velocity(&self) -> Vector2;
+	fn camera_target_is_grounded(&self) -> bool;
+	/// size of the area that should stay in frame
+	fn camera_target_extent(&self) -> Vector2 {
+		Vector2::zero()
+	}
+}
+
+impl CameraTarget for Player {
+	fn camera_target_pos(&self) -> Vector2 {
+		*self.pos()
+	}
+	fn camera_target_velocity(&self) -> Vector2 {
+		*self.speed()
+	}
+	fn camera_target_is_grounded(&self) -> bool {
+		self.is_grounded()
+	}
+}
+
+/// Combines multiple targets, e.g. for local co-op.
+#[derive(Debug)]
+pub struct CameraTargetGroup {
+	pos:      Vector2,
+	velocity: Vector2,
+	grounded: bool,
+	extent:   Vector2,
+}
+
+impl CameraTargetGroup {
+	pub fn new(targets: &[&dyn CameraTarget]) -> Option<Self> {
+		let first = targets.first()?.camera_target_pos();
+		let mut min = first;
+		let mut max = first;
+		let mut velocity = Vector2::zero();
+		for t in targets.iter() {
+			let p = t.camera_target_pos();
+			min = Vector2::new(min.x.min(p.x), min.y.min(p.y));
+			max = Vector2::new(max.x.max(p.x), max.y.max(p.y));
+			velocity = velocity.add(&t.camera_target_velocity());
+		}
+		Some(Self {
+			pos:      min.add(&max).scaled(0.5),
+			velocity: velocity.scaled(1.0 / targets.len() as f32),
+			// only snap once everybody landed
+			grounded: targets.iter().all(|t| t.camera_target_is_grounded()),
+			extent:   max.sub(&min),
+		})
+	}
+}
+
+impl CameraTarget for CameraTargetGroup {
+	fn camera_target_pos(&self) -> Vector2 {
+		self.pos
+	}
+	fn camera_target_velocity(&self) -> Vector2 {
+		self.velocity
+	}
+	fn camera_target_is_grounded(&self) -> bool {
+		self.grounded
+	}
+	fn camera_target_extent(&self) -> Vector2 {
+		self.extent
+	}
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum CameraFollowMode {
+	#[default]
+	Follow,
+	// only follow horizontally, and stay vertically centered on the zone
+	Horizontal,
+	// stay centered on the zone
+	Locked,
+}
+
+impl From<&str> for CameraFollowMode {
+	fn from(s: &str) -> Self {
+		match s {
+			"follow" => CameraFollowMode::Follow,
+			"horizontal" => CameraFollowMode::Horizontal,
+			"locked" => CameraFollowMode::Locked,
+			o => {
+				warn!("Unknown camera follow mode >{}<, using follow", o);
+				CameraFollowMode::Follow
+			},
+		}
+	}
+}
+
+/// An area overriding the camera settings while the target is inside it.
+#[derive(Debug, Clone)]
+pub struct CameraZone {
+	pub name:        String,
+	pub rect:        Rectangle,
+	pub zoom:        Option<f32>,
+	pub bounds:      Option<Rectangle>,
+	pub follow_mode: Option<CameraFollowMode>,
+	/// constrain the camera to this path
+	pub rail:        Option<Vec<Vector2>>,
+	pub blend_time:  f32,
+}
+
+impl CameraZone {
+	pub fn new(name: &str, rect: &Rectangle) -> Self {
+		Self {
+			name:        name.to_string(),
+			rect:        *rect,
+			zoom:        None,
+			bounds:      None,
+			follow_mode: None,
+			rail:        None,
+			blend_time:  1.0,
+		}
+	}
+}
+
+// blends between the settings from before and after entering/leaving a zone
+#[derive(Debug, Default)]
+struct CameraBlend {
+	from_zoom:   f32,
+	from_bounds: Option<Rectangle>,
+	time:        f32,
+	duration:    f32,
+}
+
+impl CameraBlend {
+	fn factor(&self) -> f32 {
+		if self.duration <= 0.0 {
+			1.0
+		} else {
+			(self.time / self.duration).min(1.0)
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct CameraConfig {
+	/// size of the window around the focus in which the target can move without moving the camera
+	pub dead_zone:         Vector2,
+	/// how many seconds of the target's velocity we look ahead
+	pub look_ahead_time:   f32,
+	pub max_look_ahead:    f32,
+	/// only re-centre vertically when the target lands, unless it leaves the dead zone
+	pub platform_snapping: bool,
+	pub follow_speed:      f32,
+	/// space kept around the target extent when zooming out automatically
+	pub extent_margin:     Vector2,
+	/// never zoom out further than this automatically
+	pub min_auto_zoom:     f32,
+}
+
+impl Default for CameraConfig {
+	fn default() -> Self {
+		Self {
+			dead_zone:         Vector2::new(256.0, 192.0),
+			look_ahead_time:   0.5,
+			max_look_ahead:    256.0,
+			platform_snapping: true,
+			follow_speed:      1.1,
+			extent_margin:     Vector2::new(512.0, 384.0),
+			min_auto_zoom:     0.5,
+		}
+	}
+}
+
 #[derive(Debug, Getters)]
 pub struct Camera {
-	pos:                 Vector2,
-	target_pos:          Vector2,
-	mode:                CameraMode,
-	state:               CameraState,
-	punch_factor:        f32,
-	target_punch_factor: f32,
-	frame_size:          Vector2,
-	zoom:                f32,
+	pos:          Vector2,
+	target_pos:   Vector2,
+	focus:        Vector2,
+	mode:         CameraMode,
+	state:        CameraState,
+	config:       CameraConfig,
+	bounds:       Option<Rectangle>,
+	world_bounds: Option<Rectangle>,
+	zones:        Vec<CameraZone>,
+	active_zone:  Option<usize>,
+	#[getter(skip)]
+	blend:        CameraBlend,
+	#[getter(skip)]
+	effects:      CameraEffects,
+	frame_size:   Vector2,
+	zoom:         f32,
+	auto_zoom:    f32,
 }
 
 impl Default for Camera {
 	fn default() -> Self {
 		Self {
-			pos:                 Vector2::default(),
-			target_pos:          Vector2::default(),
-			mode:                CameraMode::default(),
-			state:               CameraState::default(),
-			punch_factor:        1.0,
-			target_punch_factor: 1.0,
-			frame_size:          Vector2::default(),
-			zoom:                1.0,
+			pos:          Vector2::default(),
+			target_pos:   Vector2::default(),
+			focus:        Vector2::default(),
+			mode:         CameraMode::default(),
+			state:        CameraState::default(),
+			config:       CameraConfig::default(),
+			bounds:       None,
+			world_bounds: None,
+			zones:        Vec::new(),
+			active_zone:  None,
+			blend:        CameraBlend::default(),
+			effects:      CameraEffects::default(),
+			frame_size:   Vector2::default(),
+			zoom:         1.0,
+			auto_zoom:    1.0,
 			//			..Default::default()
 		}
 	}
 }
 impl Camera {
+	/// Moves the camera without any smoothing.
 	pub fn set_pos(&mut self, pos: &Vector2) {
 		self.pos = *pos;
+		self.focus = *pos;
 	}
 	pub fn set_target_pos(&mut self, target_pos: &Vector2) {
 		self.target_pos = *target_pos;
@@ -60,31 +253,100 @@ impl Camera {
 		self.frame_size = *frame_size;
 	}
 
+	pub fn set_config(&mut self, config: &CameraConfig) {
+		self.config = config.clone();
+	}
+
+	/// Keep the frame inside the given area, e.g. the union of all map bounds.
+	pub fn set_bounds(&mut self, bounds: Option<&Rectangle>) {
+		self.world_bounds = bounds.cloned();
+		self.bounds = bounds.cloned();
+	}
+
+	pub fn add_zone(&mut self, zone: CameraZone) {
+		self.zones.push(zone);
+	}
+
+	pub fn clear_zones(&mut self) {
+		self.zones.clear();
+		self.active_zone = None;
+	}
+
+	pub fn active_zone(&self) -> Option<&CameraZone> {
+		self.active_zone.and_then(|i| self.zones.get(i))
+	}
+
+	/// The visible area in world space, including zoom and shake.
 	pub fn frame(&self) -> Rectangle {
 		let f = Rectangle::default()
-			.with_size(&self.frame_size)
-			.with_center(&self.pos);
+			.with_size(&self.frame_size.scaled(1.0 / self.scale()))
+			.with_center(&self.pos.add(&self.effects.state().offset));
 
 		f
 	}
 	pub fn offset(&self) -> Vector2 {
-		self.pos.scaled_vector2(&Vector2::new(-1.0, -1.0))
+		self.pos
+			.add(&self.effects.state().offset)
+			.scaled_vector2(&Vector2::new(-1.0, -1.0))
+	}
+	/// Where a world position ends up on screen, zoomed around the centre of the frame.
+	pub fn world_to_screen(&self, pos: &Vector2) -> Vector2 {
+		pos.add(&self.offset()).scaled(self.scale())
+	}
+	/// Where a world rectangle ends up on screen, see `world_to_screen`.
+	pub fn world_rect_to_screen(&self, rect: &Rectangle) -> Rectangle {
+		Rectangle::default()
+			.with_size(&rect.size().scaled(self.scale()))
+			.with_center(&self.world_to_screen(rect.center()))
 	}
 
+	/// Screen units per world unit, everything rendered relative to the camera is scaled by this.
 	pub fn scale(&self) -> f32 {
-		self.punch_factor * self.zoom
+		let state = self.effects.state();
+		self.zoom * self.auto_zoom * state.zoom * state.punch
+	}
+
+	/// Height of each letterbox bar as a fraction of the frame height.
+	pub fn letterbox(&self) -> f32 {
+		self.effects.state().letterbox
+	}
+
+	pub fn effects_state(&self) -> &CameraEffectsState {
+		self.effects.state()
+	}
+
+	pub fn add_effect(&mut self, effect: CameraEffect) {
+		self.effects.add(effect);
+	}
+
+	pub fn clear_effects(&mut self) {
+		self.effects.clear();
+	}
+
+	pub fn shake(&mut self, trauma: f32) {
+		self.add_effect(CameraEffect::Shake { trauma });
 	}
 	pub fn follow_player_entity_id(&mut self, id: EntityId) {
 		self.mode = CameraMode::FollowPlayerEntityId { id }
 	}
+	pub fn follow_player_group(&mut self, ids: &[EntityId]) {
+		self.mode = CameraMode::FollowPlayerGroup { ids: ids.to_vec() }
+	}
 
 	pub fn punch(&mut self, punch_factor: f32) {
-		self.punch_factor = punch_factor;
+		self.add_effect(CameraEffect::Punch {
+			factor:   punch_factor,
+			duration: 0.5,
+		});
 	}
 
 	pub fn change_zoom(&mut self, delta: f32) {
-		self.zoom += delta;
-		debug!("zoom: {}", self.zoom);
+		let zoom = self.effects.state().zoom + delta;
+		self.add_effect(CameraEffect::ZoomTo {
+			zoom,
+			duration: 0.25,
+		});
+		debug!("zoom: {}", zoom);
 	}
 	pub fn thaw(&mut self) {
 		self.state = CameraState::Active;
@@ -94,33 +356,457 @@ impl Camera {
 	}
 
 	pub fn update(&mut self, time_step: f64, entity_manager: &EntityManager) {
-		match self.state {
-			CameraState::Frozen => {
-				return;
-			},
-			_ => {},
-		};
-
 		match self.mode {
 			CameraMode::FollowPlayerEntityId { id } => {
 				if let Some(p) = entity_manager.get_as::<Player>(id) {
-					//println!("{:?}", p.pos());
-					self.set_target_pos(p.pos());
+					self.update_with_target(time_step, Some(p));
 				} else {
 					panic!("Can not follow player {}", id);
 				}
 			},
+			CameraMode::FollowPlayerGroup { ref ids } => {
+				let players: Vec<&dyn CameraTarget> = ids
+					.iter()
+					.filter_map(|id| entity_manager.get_as::<Player>(*id))
+					.filter(|p| p.is_alive())
+					.map(|p| p as &dyn CameraTarget)
+					.collect();
+				let group = CameraTargetGroup::new(&players);
+				self.update_with_target(time_step, group.as_ref().map(|g| g as &dyn CameraTarget));
+			},
+			_ => self.update_with_target(time_step, None),
+		}
+	}
+
+	pub fn update_with_target(&mut self, time_step: f64, target: Option<&dyn CameraTarget>) {
+		match self.state {
+			CameraState::Frozen => {
+				return;
+			},
 			_ => {},
+		};
+
+		// effects keep running when there is no target, e.g. during cutscenes
+		self.effects.update(time_step as f32);
+
+		if let Some(target) = target {
+			self.update_active_zone(&target.camera_target_pos());
+			self.update_focus(target);
+			let look_ahead = self.look_ahead(target);
+			let target_pos = self.focus.add(&look_ahead);
+			let target_pos = self.apply_zone(&target_pos);
+			self.set_target_pos(&target_pos);
 		}
+		self.update_blend(time_step as f32);
+		self.target_pos = self.clamp_to_bounds(&self.target_pos);
 
-		let ls = 1.1 * time_step as f32;
+		let ls = self.config.follow_speed * time_step as f32;
+
+		let auto_zoom = target.map_or(1.0, |t| self.fit_extent(&t.camera_target_extent()));
+		self.auto_zoom = lerp(self.auto_zoom, auto_zoom, ls.min(1.0));
 
-		self.punch_factor = lerp(self.punch_factor, self.target_punch_factor, ls);
 		self.pos.x = lerp(self.pos.x, self.target_pos.x, ls);
 		self.pos.y = lerp(self.pos.y, self.target_pos.y, ls);
+		self.pos = self.clamp_to_bounds(&self.pos);
+	}
+
+	fn update_active_zone(&mut self, pos: &Vector2) {
+		// the last matching zone wins, so nested zones can be placed after their parents
+		let zone = self
+			.zones
+			.iter()
+			.rposition(|z| collision::rectangle_contains(&z.rect, pos));
+		if zone != self.active_zone {
+			debug!("Camera zone {:?} -> {:?}", self.active_zone, zone);
+			// entering uses the new zone's blend time, leaving the old one's
+			let blend_time = zone
+				.or(self.active_zone)
+				.and_then(|i| self.zones.get(i))
+				.map_or(0.0, |z| z.blend_time);
+			self.blend = CameraBlend {
+				from_zoom:   self.zoom,
+				from_bounds: self.bounds,
+				time:        0.0,
+				duration:    blend_time,
+			};
+			self.active_zone = zone;
+		}
+	}
+
+	fn update_blend(&mut self, time_step: f32) {
+		self.blend.time += time_step;
+		let t = self.blend.factor();
+
+		let (zoom, bounds) = match self.active_zone() {
+			Some(zone) => (zone.zoom.unwrap_or(1.0), zone.bounds.or(self.world_bounds)),
+			None => (1.0, self.world_bounds),
+		};
+		self.zoom = lerp(self.blend.from_zoom, zoom, t);
+		self.bounds = match (self.blend.from_bounds, bounds) {
+			(Some(from), Some(to)) => Some(lerp_rectangle(&from, &to, t)),
+			(_, to) => to,
+		};
+	}
+
+	fn apply_zone(&self, target_pos: &Vector2) -> Vector2 {
+		let zone = match self.active_zone() {
+			Some(zone) => zone,
+			None => return *target_pos,
+		};
+		let center = zone.rect.center();
+		let pos = match zone.follow_mode.unwrap_or_default() {
+			CameraFollowMode::Follow => *target_pos,
+			CameraFollowMode::Horizontal => Vector2::new(target_pos.x, center.y),
+			CameraFollowMode::Locked => *center,
+		};
+		match &zone.rail {
+			Some(rail) => closest_point_on_polyline(rail, &pos),
+			None => pos,
+		}
+	}
+
+	// the zoom needed to keep the extent (plus margin) in frame
+	fn fit_extent(&self, extent: &Vector2) -> f32 {
+		if self.frame_size.x <= 0.0 || self.frame_size.y <= 0.0 {
+			return 1.0;
+		}
+		let size = extent.add(&self.config.extent_margin);
+		let zoom = (self.frame_size.x / size.x).min(self.frame_size.y / size.y);
+		zoom.clamp(self.config.min_auto_zoom.min(1.0), 1.0)
+	}
+
+	fn update_focus(&mut self, target: &dyn CameraTarget) {
+		let pos = target.camera_target_pos();
+		let half_dead_zone = self.config.dead_zone.scaled(0.5);
+
+		self.focus.x = follow_dead_zone(self.focus.x, pos.x, half_dead_zone.x);
+		if self.config.platform_snapping && target.camera_target_is_grounded() {
+			self.focus.y = pos.y;
+		} else {
+			self.focus.y = follow_dead_zone(self.focus.y, pos.y, half_dead_zone.y);
+		}
+	}
+
+	fn look_ahead(&self, target: &dyn CameraTarget) -> Vector2 {
+		let max = self.config.max_look_ahead;
+		let x = target.camera_target_velocity().x * self.config.look_ahead_time;
+		Vector2::new(x.clamp(-max, max), 0.0)
+	}
+
+	fn clamp_to_bounds(&self, pos: &Vector2) -> Vector2 {
+		match &self.bounds {
+			Some(bounds) => {
+				let center = bounds.center();
+				let frame_size = self.frame_size.scaled(1.0 / self.scale());
+				let half_slack = bounds.size().sub(&frame_size).scaled(0.5);
+				Vector2::new(
+					clamp_axis(pos.x, center.x, half_slack.x),
+					clamp_axis(pos.y, center.y, half_slack.y),
+				)
+			},
+			None => *pos,
+		}
 	}
 }
 
 fn lerp(v0: f32, v1: f32, t: f32) -> f32 {
 	(1.0 - t) * v0 + t * v1
 }
+
+fn lerp_rectangle(from: &Rectangle, to: &Rectangle, t: f32) -> Rectangle {
+	let center = from.center().scaled(1.0 - t).add(&to.center().scaled(t));
+	let size = from.size().scaled(1.0 - t).add(&to.size().scaled(t));
+	Rectangle::default().with_size(&size).with_center(&center)
+}
+
+fn closest_point_on_polyline(points: &[Vector2], pos: &Vector2) -> Vector2 {
+	let mut closest = match points.first() {
+		Some(p) => *p,
+		None => return *pos,
+	};
+	let mut closest_distance = closest.sub(pos).length();
+	for (a, b) in points.iter().zip(points.iter().skip(1)) {
+		let ab = b.sub(a);
+		let l2 = ab.x * ab.x + ab.y * ab.y;
+		let t = if l2 > 0.0 {
+			let ap = pos.sub(a);
+			((ap.x * ab.x + ap.y * ab.y) / l2).clamp(0.0, 1.0)
+		} else {
+			0.0
+		};
+		let p = a.add(&ab.scaled(t));
+		let d = p.sub(pos).length();
+		if d < closest_distance {
+			closest = p;
+			closest_distance = d;
+		}
+	}
+	closest
+}
+
+// move focus just enough to keep target within +/- half_size
+fn follow_dead_zone(focus: f32, target: f32, half_size: f32) -> f32 {
+	if target > focus + half_size {
+		target - half_size
+	} else if target < focus - half_size {
+		target + half_size
+	} else {
+		focus
+	}
+}
+
+// a frame bigger than the bounds just stays centered
+fn clamp_axis(v: f32, center: f32, half_slack: f32) -> f32 {
+	if half_slack <= 0.0 {
+		center
+	} else {
+		v.clamp(center - half_slack, center + half_slack)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[derive(Default)]
+	struct MockTarget {
+		pos:      Vector2,
+		velocity: Vector2,
+		grounded: bool,
+	}
+
+	impl CameraTarget for MockTarget {
+		fn camera_target_pos(&self) -> Vector2 {
+			self.pos
+		}
+		fn camera_target_velocity(&self) -> Vector2 {
+			self.velocity
+		}
+		fn camera_target_is_grounded(&self) -> bool {
+			self.grounded
+		}
+	}
+
+	fn camera() -> Camera {
+		let mut camera = Camera::default();
+		camera.set_frame_size(&Vector2::new(1024.0, 512.0));
+		camera.set_config(&CameraConfig {
+			dead_zone:         Vector2::new(200.0, 100.0),
+			look_ahead_time:   0.5,
+			max_look_ahead:    100.0,
+			platform_snapping: true,
+			follow_speed:      1.0,
+			extent_margin:     Vector2::new(48.0, 0.0),
+			min_auto_zoom:     0.25,
+		});
+		camera
+	}
+
+	#[test]
+	fn dead_zone_works() {
+		let mut camera = camera();
+		let mut target = MockTarget::default();
+
+		target.pos = Vector2::new(90.0, 0.0);
+		camera.update_with_target(0.0, Some(&target));
+		assert_eq!(camera.target_pos().x, 0.0);
+
+		target.pos = Vector2::new(150.0, 0.0);
+		camera.update_with_target(0.0, Some(&target));
+		assert_eq!(camera.target_pos().x, 50.0);
+
+		target.pos = Vector2::new(-100.0, 0.0);
+		camera.update_with_target(0.0, Some(&target));
+		assert_eq!(camera.target_pos().x, 0.0);
+	}
+
+	#[test]
+	fn look_ahead_works() {
+		let mut camera = camera();
+		let mut target = MockTarget::default();
+
+		target.velocity = Vector2::new(100.0, 0.0);
+		camera.update_with_target(0.0, Some(&target));
+		assert_eq!(camera.target_pos().x, 50.0);
+
+		// clamped to max_look_ahead
+		target.velocity = Vector2::new(-1000.0, 0.0);
+		camera.update_with_target(0.0, Some(&target));
+		assert_eq!(camera.target_pos().x, -100.0);
+	}
+
+	#[test]
+	fn platform_snapping_works() {
+		let mut camera = camera();
+		let mut target = MockTarget::default();
+
+		// jumping within the dead zone doesn't move the camera
+		target.pos = Vector2::new(0.0, 40.0);
+		camera.update_with_target(0.0, Some(&target));
+		assert_eq!(camera.target_pos().y, 0.0);
+
+		// landing does
+		target.grounded = true;
+		camera.update_with_target(0.0, Some(&target));
+		assert_eq!(camera.target_pos().y, 40.0);
+
+		// leaving the dead zone while in the air does too
+		target.grounded = false;
+		target.pos = Vector2::new(0.0, -100.0);
+		camera.update_with_target(0.0, Some(&target));
+		assert_eq!(camera.target_pos().y, -50.0);
+	}
+
+	#[test]
+	fn horizontal_zone_with_rail_works() {
+		let mut camera = camera();
+		let rect = Rectangle::default()
+			.with_size(&Vector2::new(1000.0, 1000.0))
+			.with_center(&Vector2::new(2000.0, 0.0));
+		let mut zone = CameraZone::new("zone", &rect);
+		zone.follow_mode = Some(CameraFollowMode::Horizontal);
+		zone.zoom = Some(2.0);
+		zone.blend_time = 2.0;
+		zone.rail = Some(vec![
+			Vector2::new(1500.0, 100.0),
+			Vector2::new(2500.0, 100.0),
+		]);
+		camera.add_zone(zone);
+		let mut target = MockTarget::default();
+
+		target.pos = Vector2::new(1900.0, 300.0);
+		target.grounded = true;
+		camera.update_with_target(1.0, Some(&target));
+		assert!(camera.active_zone().is_some());
+		// horizontal follow snaps to the zone center, then onto the rail
+		assert_eq!(camera.target_pos().y, 100.0);
+		// half way through the blend
+		assert_eq!(*camera.zoom(), 1.5);
+
+		target.pos = Vector2::new(0.0, 0.0);
+		camera.update_with_target(0.0, Some(&target));
+		assert!(camera.active_zone().is_none());
+	}
+
+	#[test]
+	fn locked_zone_holds_the_camera() {
+		let mut camera = camera();
+		let rect = Rectangle::default()
+			.with_size(&Vector2::new(1000.0, 1000.0))
+			.with_center(&Vector2::new(2000.0, 0.0));
+		let mut zone = CameraZone::new("zone", &rect);
+		zone.follow_mode = Some(CameraFollowMode::Locked);
+		camera.add_zone(zone);
+		let mut target = MockTarget::default();
+
+		// wherever the target moves, or looks, within the zone the camera stays on its center
+		target.grounded = true;
+		for (x, y) in [(1600.0, -400.0), (2400.0, 300.0), (2000.0, 0.0)] {
+			target.pos = Vector2::new(x, y);
+			target.velocity = Vector2::new(1000.0, 0.0);
+			camera.update_with_target(1.0, Some(&target));
+			assert!(camera.active_zone().is_some());
+			assert_eq!(
+				(camera.target_pos().x, camera.target_pos().y),
+				(2000.0, 0.0)
+			);
+			assert_eq!((camera.pos().x, camera.pos().y), (2000.0, 0.0));
+		}
+
+		// leaving the zone lets the camera follow again
+		target.pos = Vector2::new(0.0, 0.0);
+		target.velocity = Vector2::zero();
+		camera.update_with_target(1.0, Some(&target));
+		assert!(camera.active_zone().is_none());
+		assert!(camera.target_pos().x < 2000.0);
+	}
+
+	#[test]
+	fn closest_point_on_polyline_works() {
+		let points = [
+			Vector2::new(0.0, 0.0),
+			Vector2::new(100.0, 0.0),
+			Vector2::new(100.0, 100.0),
+		];
+		let p = closest_point_on_polyline(&points, &Vector2::new(50.0, 20.0));
+		assert_eq!((p.x, p.y), (50.0, 0.0));
+		let p = closest_point_on_polyline(&points, &Vector2::new(150.0, 70.0));
+		assert_eq!((p.x, p.y), (100.0, 70.0));
+		let p = closest_point_on_polyline(&points, &Vector2::new(-50.0, -50.0));
+		assert_eq!((p.x, p.y), (0.0, 0.0));
+	}
+
+	#[test]
+	fn group_target_zooms_out() {
+		let mut camera = camera();
+		let a = MockTarget {
+			pos:      Vector2::new(-1000.0, 0.0),
+			velocity: Vector2::zero(),
+			grounded: true,
+		};
+		let b = MockTarget {
+			pos:      Vector2::new(1000.0, 100.0),
+			velocity: Vector2::zero(),
+			grounded: false,
+		};
+		let group = CameraTargetGroup::new(&[&a, &b]).unwrap();
+		assert!(!group.camera_target_is_grounded());
+
+		camera.update_with_target(1.0, Some(&group));
+		assert_eq!(camera.target_pos().x, 0.0);
+		// 2000 + 48 wide needs to fit into 1024
+		assert_eq!(camera.scale(), 0.5);
+		assert_eq!(camera.frame().size().x, 2048.0);
+
+		assert!(CameraTargetGroup::new(&[]).is_none());
+	}
+
+	#[test]
+	fn effects_affect_frame() {
+		let mut camera = camera();
+		let target = MockTarget::default();
+
+		camera.add_effect(CameraEffect::ZoomTo {
+			zoom:     2.0,
+			duration: 0.0,
+		});
+		camera.shake(1.0);
+		camera.update_with_target(1.0 / 60.0, Some(&target));
+		assert_eq!(camera.scale(), 2.0);
+		let frame = camera.frame();
+		assert_eq!((frame.size().x, frame.size().y), (512.0, 256.0));
+		let offset = camera.effects_state().offset;
+		assert!(offset.x != 0.0);
+		assert_eq!(frame.center().x, offset.x);
+		assert_eq!(camera.offset().x, -offset.x);
+
+		// shake is gone after a second
+		for _ in 0..70 {
+			camera.update_with_target(1.0 / 60.0, Some(&target));
+		}
+		assert_eq!(camera.offset().x, 0.0);
+		// still zoomed in
+		assert_eq!(camera.world_to_screen(&Vector2::new(100.0, 0.0)).x, 200.0);
+	}
+
+	#[test]
+	fn clamping_to_bounds_works() {
+		let mut camera = camera();
+		let bounds = Rectangle::default()
+			.with_size(&Vector2::new(2048.0, 256.0))
+			.with_center(&Vector2::zero());
+		camera.set_bounds(Some(&bounds));
+		let mut target = MockTarget::default();
+
+		target.pos = Vector2::new(5000.0, 0.0);
+		camera.update_with_target(1.0, Some(&target));
+		assert_eq!(camera.target_pos().x, 512.0);
+		assert_eq!(camera.pos().x, 512.0);
+
+		// bounds smaller than the frame keep the camera centered
+		target.grounded = true;
+		target.pos = Vector2::new(0.0, 300.0);
+		camera.update_with_target(1.0, Some(&target));
+		assert_eq!(camera.target_pos().y, 0.0);
+	}
+}
@@ -0,0 +1,258 @@
+use oml_game::math::Vector2;
+
+// trauma lost per second
+const TRAUMA_DECAY: f32 = 1.0;
+// frequencies for the shake "noise", incommensurable so the pattern doesn't repeat too obviously
+const SHAKE_FREQUENCIES: [f32; 2] = [31.0, 47.0];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CameraEffect {
+	/// adds trauma, the shake strength is trauma squared, trauma decays over time
+	Shake { trauma: f32 },
+	/// zooms to `zoom` over `duration` seconds, replacing any previous zoom
+	ZoomTo { zoom: f32, duration: f32 },
+	/// scales by `factor`, and settles back to 1.0 over `duration` seconds
+	Punch { factor: f32, duration: f32 },
+	/// blends the bars to `amount` (fraction of the frame height per bar) over `duration` seconds
+	Letterbox { amount: f32, duration: f32 },
+}
+
+/// The combined result of all active effects.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CameraEffectsState {
+	pub offset:    Vector2,
+	pub zoom:      f32,
+	pub punch:     f32,
+	pub letterbox: f32,
+	pub trauma:    f32,
+}
+
+impl Default for CameraEffectsState {
+	fn default() -> Self {
+		Self {
+			offset:    Vector2::zero(),
+			zoom:      1.0,
+			punch:     1.0,
+			letterbox: 0.0,
+			trauma:    0.0,
+		}
+	}
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Transition {
+	from:     f32,
+	to:       f32,
+	time:     f32,
+	duration: f32,
+}
+
+impl Transition {
+	fn settled(value: f32) -> Self {
+		Self {
+			from:     value,
+			to:       value,
+			time:     0.0,
+			duration: 0.0,
+		}
+	}
+
+	fn value(&self) -> f32 {
+		if self.duration <= 0.0 || self.time >= self.duration {
+			self.to
+		} else {
+			let t = self.time / self.duration;
+			// smoothstep
+			let t = t * t * (3.0 - 2.0 * t);
+			self.from + (self.to - self.from) * t
+		}
+	}
+
+	fn retarget(&mut self, to: f32, duration: f32) {
+		*self = Self {
+			from: self.value(),
+			to,
+			time: 0.0,
+			duration,
+		};
+	}
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Punch {
+	factor:   f32,
+	time:     f32,
+	duration: f32,
+}
+
+/// Effects are applied in the order they were added, and only depend on the time steps given,
+/// so the result is fully deterministic.
+#[derive(Debug)]
+pub struct CameraEffects {
+	max_shake_offset: Vector2,
+	trauma:           f32,
+	shake_time:       f32,
+	zoom:             Transition,
+	letterbox:        Transition,
+	punches:          Vec<Punch>,
+	state:            CameraEffectsState,
+}
+
+impl Default for CameraEffects {
+	fn default() -> Self {
+		Self {
+			max_shake_offset: Vector2::new(48.0, 32.0),
+			trauma:           0.0,
+			shake_time:       0.0,
+			zoom:             Transition::settled(1.0),
+			letterbox:        Transition::settled(0.0),
+			punches:          Vec::new(),
+			state:            CameraEffectsState::default(),
+		}
+	}
+}
+
+impl CameraEffects {
+	pub fn add(&mut self, effect: CameraEffect) {
+		match effect {
+			CameraEffect::Shake { trauma } => {
+				self.trauma = (self.trauma + trauma).clamp(0.0, 1.0);
+			},
+			CameraEffect::ZoomTo { zoom, duration } => {
+				self.zoom.retarget(zoom, duration);
+			},
+			CameraEffect::Punch { factor, duration } => {
+				self.punches.push(Punch {
+					factor,
+					time: 0.0,
+					duration,
+				});
+			},
+			CameraEffect::Letterbox { amount, duration } => {
+				self.letterbox.retarget(amount, duration);
+			},
+		}
+	}
+
+	pub fn clear(&mut self) {
+		*self = Self {
+			max_shake_offset: self.max_shake_offset,
+			..Default::default()
+		};
+	}
+
+	pub fn state(&self) -> &CameraEffectsState {
+		&self.state
+	}
+
+	pub fn update(&mut self, time_step: f32) {
+		self.zoom.time += time_step;
+		self.letterbox.time += time_step;
+
+		for p in self.punches.iter_mut() {
+			p.time += time_step;
+		}
+		self.punches.retain(|p| p.time < p.duration);
+		let punch = self.punches.iter().fold(1.0, |acc, p| {
+			let t = 1.0 - p.time / p.duration;
+			acc * (1.0 + (p.factor - 1.0) * t)
+		});
+
+		let offset = if self.trauma > 0.0 {
+			self.shake_time += time_step;
+			let shake = self.trauma * self.trauma;
+			let t = self.shake_time;
+			Vector2::new(
+				self.max_shake_offset.x * shake * (t * SHAKE_FREQUENCIES[0]).sin(),
+				self.max_shake_offset.y * shake * (t * SHAKE_FREQUENCIES[1]).sin(),
+			)
+		} else {
+			self.shake_time = 0.0;
+			Vector2::zero()
+		};
+		self.trauma = (self.trauma - TRAUMA_DECAY * time_step).max(0.0);
+
+		self.state = CameraEffectsState {
+			offset,
+			zoom: self.zoom.value(),
+			punch,
+			letterbox: self.letterbox.value(),
+			trauma: self.trauma,
+		};
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn shake_decays_and_is_deterministic() {
+		let run = || {
+			let mut effects = CameraEffects::default();
+			effects.add(CameraEffect::Shake { trauma: 0.5 });
+			effects.add(CameraEffect::Shake { trauma: 0.75 });
+			let mut offsets = Vec::new();
+			for _ in 0..120 {
+				effects.update(1.0 / 60.0);
+				offsets.push(effects.state().offset);
+			}
+			(offsets, *effects.state())
+		};
+		let (a, state) = run();
+		let (b, _) = run();
+		assert_eq!(a.len(), b.len());
+		assert!(a
+			.iter()
+			.zip(b.iter())
+			.all(|(a, b)| a.x == b.x && a.y == b.y));
+		assert!(a.iter().any(|o| o.x != 0.0));
+		// trauma is clamped to 1.0, and fully decayed after a second
+		assert_eq!(state.trauma, 0.0);
+		assert_eq!(state.offset.x, 0.0);
+	}
+
+	#[test]
+	fn zoom_to_and_letterbox_blend() {
+		let mut effects = CameraEffects::default();
+		effects.add(CameraEffect::ZoomTo {
+			zoom:     2.0,
+			duration: 1.0,
+		});
+		effects.add(CameraEffect::Letterbox {
+			amount:   0.1,
+			duration: 0.0,
+		});
+		effects.update(0.5);
+		assert_eq!(effects.state().zoom, 1.5);
+		assert_eq!(effects.state().letterbox, 0.1);
+		effects.update(0.5);
+		assert_eq!(effects.state().zoom, 2.0);
+
+		// retargeting starts from the current value
+		effects.add(CameraEffect::ZoomTo {
+			zoom:     1.0,
+			duration: 2.0,
+		});
+		effects.update(1.0);
+		assert_eq!(effects.state().zoom, 1.5);
+	}
+
+	#[test]
+	fn punches_compose() {
+		let mut effects = CameraEffects::default();
+		effects.add(CameraEffect::Punch {
+			factor:   2.0,
+			duration: 1.0,
+		});
+		effects.add(CameraEffect::Punch {
+			factor:   3.0,
+			duration: 2.0,
+		});
+		effects.update(0.5);
+		// (1 + 1 * 0.5) * (1 + 2 * 0.75)
+		assert_eq!(effects.state().punch, 3.75);
+		effects.update(1.5);
+		assert_eq!(effects.state().punch, 1.0);
+	}
+}
@@ -0,0 +1,142 @@
+use oml_game::math::{Cardinals, Rectangle, Vector2};
+use oml_game::renderer::Color;
+
+use crate::rar::debug_draw;
+use crate::rar::entities::EntityId;
+use crate::rar::map::ObjectData;
+use crate::rar::EntityUpdateContext;
+use crate::rar::World;
+
+// Note: we only rely on center & size here, so we don't care which way up top & bottom are
+
+pub fn rectangle_min(rect: &Rectangle) -> Vector2 {
+	rect.center().sub(&rect.size().scaled(0.5))
+}
+
+pub fn rectangle_max(rect: &Rectangle) -> Vector2 {
+	rect.center().add(&rect.size().scaled(0.5))
+}
+
+pub fn rectangles_overlap(a: &Rectangle, b: &Rectangle) -> bool {
+	let a_min = rectangle_min(a);
+	let a_max = rectangle_max(a);
+	let b_min = rectangle_min(b);
+	let b_max = rectangle_max(b);
+
+	a_min.x < b_max.x && a_max.x > b_min.x && a_min.y < b_max.y && a_max.y > b_min.y
+}
+
+pub fn rectangle_contains(rect: &Rectangle, pos: &Vector2) -> bool {
+	let min = rectangle_min(rect);
+	let max = rectangle_max(rect);
+
+	pos.x >= min.x && pos.x <= max.x && pos.y >= min.y && pos.y <= max.y
+}
+
+pub type TileCollision = (f32, Cardinals, Rectangle);
+
+/// A collider owned by an entity, e.g. a moving platform.
+#[derive(Debug, Clone)]
+pub struct DynamicCollider {
+	pub entity_id: EntityId,
+	pub rect:      Rectangle,
+	// how far the collider moved during the current fixed update
+	pub movement:  Vector2,
+}
+
+/// Sweeps a box of `size` from `start` to `end` against the "Collider" layer,
+/// and returns the *first* collision, if any.
+pub fn find_first_tile_collision(
+	world: &World,
+	start: &Vector2,
+	end: &Vector2,
+	size: &Vector2,
+	debug: bool,
+) -> Option<TileCollision> {
+	let colliders = world.list_objects_in_layer("Collider");
+
+	let l = start.sub(end).length();
+	let r = Rectangle::default().with_size(size).with_center(end);
+	let pc = r.calculate_bounding_circle();
+	let pc = pc.with_radius(pc.radius() + l);
+
+	let mut first_collision: Option<TileCollision> = None;
+	for c in colliders {
+		if let ObjectData::Rectangle {
+			rect,
+			bounding_circle,
+		} = c.data()
+		{
+			// if we have a bounding circle use that for a quick/cheap early out
+			if let Some(bounding_circle) = bounding_circle {
+				let overlaps = pc.overlaps(&bounding_circle);
+				if debug {
+					let color = if overlaps {
+						Color::red()
+					} else {
+						Color::blue()
+					};
+					debug_draw::debug_draw_add_circle(
+						bounding_circle.center(),
+						bounding_circle.radius(),
+						5.0,
+						&color,
+					);
+				}
+				if !overlaps {
+					continue;
+				}
+			}
+
+			if let Some(col) = rect.would_collide(start, end, &r) {
+				if first_collision.map_or(true, |fc| col.0 < fc.0) {
+					first_collision = Some((col.0, col.1, rect.clone()));
+				}
+			}
+		}
+	}
+
+	first_collision
+}
+
+/// Like `find_first_tile_collision`, but also includes the dynamic colliders,
+/// and returns the id of the entity we collided with, if it wasn't a tile.
+pub fn find_first_collision(
+	euc: &EntityUpdateContext,
+	start: &Vector2,
+	end: &Vector2,
+	size: &Vector2,
+	debug: bool,
+) -> Option<(TileCollision, Option<EntityId>)> {
+	let mut first_collision =
+		find_first_tile_collision(euc.world(), start, end, size, debug).map(|c| (c, None));
+
+	let r = Rectangle::default().with_size(size).with_center(end);
+	for dc in euc.dynamic_colliders().iter() {
+		if let Some(col) = dc.rect.would_collide(start, end, &r) {
+			if first_collision.map_or(true, |fc| col.0 < fc.0 .0) {
+				first_collision = Some(((col.0, col.1, dc.rect), Some(dc.entity_id)));
+			}
+		}
+	}
+
+	first_collision
+}
+
+/// The center of the box at the point of the collision returned by `find_first_tile_collision`.
+pub fn tile_collision_pos(start: &Vector2, end: &Vector2, collision: &TileCollision) -> Vector2 {
+	// :HACK: would_collide reports the time for twice the distance
+	let p = collision.0 * 0.5;
+	let full = end.sub(start).scaled(p);
+	start.add(&full)
+}
+
+pub fn is_solid_at(world: &World, pos: &Vector2) -> bool {
+	world
+		.list_objects_in_layer("Collider")
+		.iter()
+		.any(|c| match c.data() {
+			ObjectData::Rectangle { rect, .. } => rectangle_contains(rect, pos),
+			_ => false,
+		})
+}
@@ -1,5 +1,10 @@
 use std::sync::RwLock;
 
+use oml_game::window::WindowUpdateContext;
+use tracing::*;
+
+use crate::rar::input_actions::{InputActionSet, InputBinding, InputConfig};
+
 #[derive(Debug, Default)]
 pub struct AudioData {
 	pub is_sound_enabled: bool,
@@ -7,15 +12,30 @@ pub struct AudioData {
 	pub backend_type:     String,
 }
 
+#[derive(Debug, Default)]
+pub struct InputData {
+	pub config:    InputConfig,
+	/// the action waiting for a new binding in the settings
+	pub rebinding: Option<(InputActionSet, String)>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct LevelResult {
+	pub time:   f64,
+	pub deaths: u32,
+}
+
 #[derive(Debug, Default)]
 pub struct GameData {
-	pub is_paused: bool,
+	pub is_paused:    bool,
+	pub level_result: Option<LevelResult>,
 }
 
 #[derive(Debug)]
 pub struct RarData {
 	pub audio: RwLock<AudioData>,
 	pub game:  RwLock<GameData>,
+	pub input: RwLock<InputData>,
 }
 
 impl RarData {
@@ -23,6 +43,39 @@ impl RarData {
 		Self {
 			audio: RwLock::new(AudioData::default()),
 			game:  RwLock::new(GameData::default()),
+			input: RwLock::new(InputData::default()),
+		}
+	}
+
+	/// Handles the `input/rebind/<action>` buttons, returns false for any other button.
+	pub fn start_rebinding_from_button(&self, button_name: &str, set: InputActionSet) -> bool {
+		let action = match button_name.strip_prefix("input/rebind/") {
+			Some(action) => action,
+			None => return false,
+		};
+		if let Ok(mut input) = self.input.write() {
+			input.rebinding = Some((set, action.to_string()));
+		}
+		true
+	}
+
+	/// Replaces the bindings of the action waiting for one with the first key pressed.
+	pub fn update_rebinding(&self, wuc: &WindowUpdateContext) {
+		let mut input = match self.input.write() {
+			Ok(input) => input,
+			Err(_) => return,
+		};
+		let (set, action) = match &input.rebinding {
+			Some(r) => r.clone(),
+			None => return,
+		};
+		if let Some(binding) = InputBinding::find_pressed(wuc) {
+			debug!("Rebinding {:?} {} to {}", set, &action, binding);
+			if let Some(map) = input.config.action_map_mut(set) {
+				map.set_bindings(&action, vec![binding]);
+			}
+			// :TODO: persist to the savegame filesystem
+			input.rebinding = None;
 		}
 	}
 
@@ -0,0 +1,62 @@
+use std::sync::RwLock;
+
+use oml_game::math::Rectangle;
+use oml_game::math::Vector2;
+use oml_game::renderer::debug_renderer;
+use oml_game::renderer::Color;
+use once_cell::sync::Lazy;
+
+use crate::rar::camera::Camera;
+
+/// Camera transform applied to world space debug shapes before they reach the debug renderer.
+#[derive(Debug, Clone)]
+struct DebugDrawTransform {
+	offset: Vector2,
+	scale:  f32,
+}
+
+impl Default for DebugDrawTransform {
+	fn default() -> Self {
+		Self {
+			offset: Vector2::zero(),
+			scale:  1.0,
+		}
+	}
+}
+
+impl DebugDrawTransform {
+	fn pos(&self, pos: &Vector2) -> Vector2 {
+		pos.add(&self.offset).scaled(self.scale)
+	}
+}
+
+static DEBUGDRAWTRANSFORM: Lazy<RwLock<DebugDrawTransform>> =
+	Lazy::new(|| RwLock::new(DebugDrawTransform::default()));
+
+fn transform() -> DebugDrawTransform {
+	DEBUGDRAWTRANSFORM.read().unwrap().clone()
+}
+
+pub fn debug_draw_set_camera(camera: &Camera) {
+	let mut t = DEBUGDRAWTRANSFORM.write().unwrap();
+	t.offset = camera.offset();
+	t.scale = camera.scale();
+}
+
+pub fn debug_draw_add_circle(pos: &Vector2, radius: f32, width: f32, color: &Color) {
+	let t = transform();
+	debug_renderer::debug_renderer_add_circle(&t.pos(pos), radius * t.scale, width, color);
+}
+
+pub fn debug_draw_add_rectangle(rect: &Rectangle, width: f32, color: &Color) {
+	let t = transform();
+	let rect = Rectangle::default()
+		.with_size(&rect.size().scaled(t.scale))
+		.with_center(&t.pos(rect.center()));
+	debug_renderer::debug_renderer_add_rectangle(&rect, width, color);
+}
+
+pub fn debug_draw_add_line(start: &Vector2, end: &Vector2, width: f32, color: &Color) {
+	let t = transform();
+	debug_renderer::debug_renderer_add_line(&t.pos(start), &t.pos(end), width, color);
+}
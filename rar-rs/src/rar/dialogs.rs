@@ -12,3 +12,6 @@ pub use ingame_pause_dialog::IngamePauseDialog;
 
 mod quit_app_dialog;
 pub use quit_app_dialog::QuitAppDialog;
+
+mod level_complete_dialog;
+pub use level_complete_dialog::LevelCompleteDialog;
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use oml_game::system::Data;
+use oml_game::system::System;
+use tracing::*;
+
+use crate::rar::data::{LevelResult, RarData};
+use crate::ui::UiEventResponse;
+use crate::ui::*;
+
+#[derive(Debug)]
+pub struct LevelCompleteDialog {
+	data:         Option<Arc<dyn Data>>,
+	container:    Option<UiElementContainer>,
+	level_result: Option<LevelResult>,
+}
+
+impl LevelCompleteDialog {
+	pub fn new(system: &mut System, ui_element_factory: &UiElementFactory) -> Self {
+		let container = UiElementContainer::from_config_asset(
+			system,
+			ui_element_factory,
+			"level_complete_dialog",
+		);
+		Self {
+			data: system.data().as_ref().map(|data| Arc::clone(data)),
+			container,
+			level_result: None,
+		}
+	}
+
+	fn format_time(time: f64) -> String {
+		let minutes = (time / 60.0).floor();
+		let seconds = time - minutes * 60.0;
+		format!("{:02.0}:{:05.2}", minutes, seconds)
+	}
+
+	fn update_labels(container_data: &mut UiElementContainerData, level_result: &LevelResult) {
+		let texts = [
+			(
+				"time",
+				format!("Time: {}", Self::format_time(level_result.time)),
+			),
+			("deaths", format!("Deaths: {}", level_result.deaths)),
+		];
+		for (tag, text) in texts.iter() {
+			container_data.find_child_by_tag_as_mut_element_then::<UiLabel>(tag, &|l| {
+				l.set_text(text);
+			});
+		}
+	}
+}
+
+impl UiElement for LevelCompleteDialog {
+	fn as_any(&self) -> &dyn std::any::Any {
+		self
+	}
+	fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+		self
+	}
+
+	fn setup_within_container(&mut self, container_data: &mut UiElementContainerData) {
+		if let Some(container) = self.container.take() {
+			container_data.add_child_element_container(container);
+		} else {
+			panic!("No container for LevelCompleteDialog");
+		};
+	}
+	fn update(&mut self, container: &mut UiElementContainerData, _time_step: f64) {
+		if let Some(data) = &self.data {
+			match data.as_any().downcast_ref::<RarData>() {
+				Some(data) => {
+					let level_result = data
+						.game
+						.read()
+						.map(|game| game.level_result.clone())
+						.unwrap();
+					// only touch the labels when the result actually changes
+					if let Some(lr) = &level_result {
+						if self.level_result.is_none() {
+							Self::update_labels(container, lr);
+						}
+					}
+					self.level_result = level_result;
+				},
+				None => {
+					warn!("level complete dialog data is not RarData");
+					return;
+				},
+			}
+		}
+	}
+	fn handle_ui_event_response(
+		&mut self,
+		_container_data: &mut UiElementContainerData,
+		response: Box<dyn UiEventResponse>,
+	) -> Option<Box<dyn UiEventResponse>> {
+		match response
+			.as_any()
+			.downcast_ref::<UiEventResponseButtonClicked>()
+		{
+			Some(bce) => match bce.button_name.as_str() {
+				"back" => {
+					debug!("back to world selection clicked");
+					return Some(Box::new(UiEventResponseGenericMessage::new(
+						"level_complete/back",
+					)));
+				},
+				"restart" => {
+					debug!("restart clicked");
+					return Some(Box::new(UiEventResponseGenericMessage::new(
+						"level_complete/restart",
+					)));
+				},
+				_ => {},
+			},
+			None => {},
+		};
+		Some(response)
+	}
+}
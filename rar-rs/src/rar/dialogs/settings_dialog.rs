@@ -5,17 +5,23 @@ use oml_game::system::Data;
 use oml_game::system::System;
 use tracing::*;
 
-use crate::rar::data::RarData;
+use crate::rar::data::{InputData, RarData};
 use crate::rar::font_ids::FontId;
+use crate::rar::input_actions::InputActionSet;
 use crate::ui::*;
 
 #[derive(Debug)]
 pub struct SettingsDialog {
 	base_data_build_number: String,
 	data:                   Option<Arc<dyn Data>>,
+	// the actions that can be rebound
+	input_actions:          Vec<String>,
 }
 
 impl SettingsDialog {
+	// :TODO: allow rebinding for the other players too
+	pub const REBINDABLE_SET: InputActionSet = InputActionSet::Player(0);
+
 	pub fn new(system: &mut System) -> Self {
 		let base_data_build_number_path = "base_build_number.txt";
 		let mut f = system
@@ -26,12 +32,48 @@ impl SettingsDialog {
 		} else {
 			"[file not found]".to_string()
 		};
+		let data = system.data().as_ref().map(|data| Arc::clone(data));
+		let mut input_actions = Vec::new();
+		if let Some(data) = &data {
+			if let Some(data) = data.as_any().downcast_ref::<RarData>() {
+				if let Ok(input) = data.input.read() {
+					if let Some(map) = input.config.action_map(Self::REBINDABLE_SET) {
+						input_actions = map.iter().map(|(a, _)| a.clone()).collect();
+					}
+				}
+			}
+		}
 		Self {
 			base_data_build_number,
-			data: system.data().as_ref().map(|data| Arc::clone(data)),
+			data,
+			input_actions,
 			//..Default::default()
 		}
 	}
+
+	fn create_input_rows(&self) -> Vec<UiElementContainer> {
+		let h = 32.0;
+		let label_size = Vector2::new(256.0, h);
+		let value_size = Vector2::new(256.0, h);
+
+		self.input_actions
+			.iter()
+			.flat_map(|action| {
+				[
+					UiLabel::new(&label_size, action)
+						.with_font_id(FontId::Mono as u8)
+						.containerize(),
+					UiLabel::new(&value_size, "")
+						.with_font_id(FontId::Mono as u8)
+						.containerize()
+						.with_tag(&format!("input/{}", action)),
+					UiButton::new("ui-button_settings", &Vector2::new(h, h))
+						.containerize()
+						.with_name(&format!("input/rebind/{}", action)),
+				]
+			})
+			.collect()
+	}
 	fn create_info_labels(&self) -> Vec<UiElementContainer> {
 		let h = 16.0;
 		let label_size = Vector2::new(256.0 + 64.0 + 32.0, h);
@@ -144,10 +186,40 @@ impl SettingsDialog {
 					]
 					.into(),
 				),
+			UiGridBox::default()
+				.with_padding(16.0)
+				.with_column_count(3)
+				.containerize()
+				.with_name("Input Bindings")
+				.with_child_element_containers(self.create_input_rows()),
 		]
 		.into()
 	}
 
+	fn update_input(&self, container_data: &mut UiElementContainerData, input: &InputData) {
+		let map = input.config.action_map(Self::REBINDABLE_SET);
+		for action in self.input_actions.iter() {
+			let text = match &input.rebinding {
+				Some((set, a)) if *set == Self::REBINDABLE_SET && a == action => {
+					"Press a key...".to_string()
+				},
+				_ => map.map_or(String::new(), |m| {
+					m.bindings(action)
+						.iter()
+						.map(|b| b.to_string())
+						.collect::<Vec<String>>()
+						.join(", ")
+				}),
+			};
+			container_data.find_child_by_tag_as_mut_element_then::<UiLabel>(
+				&format!("input/{}", action),
+				&|l| {
+					l.set_text(&text);
+				},
+			);
+		}
+	}
+
 	fn update_music(
 		&self,
 		_uielement: &dyn UiElement,
@@ -230,6 +302,10 @@ impl UiElement for SettingsDialog {
 						self.update_music(uielement, container, audio.is_music_enabled);
 						Ok(())
 					});
+					data.input.read().and_then(|input| {
+						self.update_input(container, &input);
+						Ok(())
+					});
 				},
 				None => {},
 			}
@@ -4,6 +4,7 @@ pub use entity::EntityData;
 pub use entity::EntityState;
 mod entity_configuration;
 pub use entity_configuration::AnimatedTextureConfiguration;
+pub use entity_configuration::BackgroundPlaneConfiguration;
 pub use entity_configuration::EntityConfiguration;
 pub use entity_configuration::EntityConfigurationManager;
 //mod entity_ids;
@@ -19,17 +20,30 @@ mod background;
 	pub use background::Background as Background;
 mod coin;
 	pub use coin::Coin as Coin;
-mod obstacle;
-	pub use obstacle::Obstacle as Obstacle;
 mod fish;
 	pub use fish::Fish as Fish;
 */
 mod player;
 pub use player::Player;
 
+mod enemy;
+pub use enemy::{Enemy, EnemyBehaviour};
+
 mod background;
 pub use background::Background;
 
+mod obstacle;
+pub use obstacle::Obstacle;
+
+mod checkpoint;
+pub use checkpoint::Checkpoint;
+
+mod goal;
+pub use goal::Goal;
+
+mod platform;
+pub use platform::Platform;
+
 mod entity_manager;
 pub use entity_manager::EntityId;
 pub use entity_manager::EntityManager;
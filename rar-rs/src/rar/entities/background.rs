@@ -3,13 +3,16 @@ use oml_game::renderer::Renderer;
 
 use crate::rar::camera::Camera;
 use crate::rar::effect_ids::EffectId;
-use crate::rar::entities::{Entity, EntityConfiguration, EntityData, EntityType};
+use crate::rar::entities::{
+	BackgroundPlaneConfiguration, Entity, EntityConfiguration, EntityData, EntityType,
+};
 use crate::rar::layer_ids::LayerId;
 use crate::rar::EntityUpdateContext;
 
 #[derive(Debug)]
 pub struct Background {
-	name: String,
+	name:   String,
+	planes: Vec<BackgroundPlaneConfiguration>,
 
 	entity_data: EntityData,
 }
@@ -18,6 +21,7 @@ impl Background {
 	pub fn new() -> Self {
 		Self {
 			name:        "background".to_string(),
+			planes:      Vec::new(),
 			entity_data: EntityData::default(),
 		}
 	}
@@ -38,23 +42,42 @@ impl Entity for Background {
 		self
 	}
 
-	fn setup(&mut self, _ec: &EntityConfiguration) {}
+	fn setup(&mut self, ec: &EntityConfiguration) {
+		self.planes = ec.planes().to_vec();
+		if self.planes.is_empty() {
+			// what we had before the planes were configurable
+			self.planes.push(BackgroundPlaneConfiguration::new(
+				"bg-title",
+				&[0.0, 0.0],
+				&[1024.0, 1024.0],
+			));
+		}
+	}
 
 	fn teardown(&mut self) {}
 
 	fn update(&mut self, _euc: &mut EntityUpdateContext) {}
 
-	fn render(&mut self, renderer: &mut Renderer, _camera: &Camera) {
-		renderer.use_texture("bg-title");
+	fn render(&mut self, renderer: &mut Renderer, camera: &Camera) {
 		renderer.use_layer(LayerId::Background as u8);
 		renderer.use_effect(EffectId::Background as u16);
 
-		let a = renderer.aspect_ratio();
-		let mtx = Matrix32::scaling_xy(1.0 * a, 1.0);
-		//mtx.pos.x = - self.pos.x / 1024.0;
-		renderer.set_tex_matrix(&mtx);
+		let frame = camera.frame();
+		let frame_size = frame.size();
+		let camera_pos = camera.offset().scaled(-1.0);
+		for p in self.planes.iter() {
+			let [px, py] = *p.parallax();
+			let [w, h] = *p.size();
+			renderer.use_texture(p.texture());
 
-		renderer.render_textured_fullscreen_quad();
+			let mut mtx = Matrix32::scaling_xy(frame_size.x / w, frame_size.y / h);
+			// the texture repeats, and its v goes down while the world goes up
+			mtx.pos.x = camera_pos.x * px / w;
+			mtx.pos.y = -camera_pos.y * py / h;
+			renderer.set_tex_matrix(&mtx);
+
+			renderer.render_textured_fullscreen_quad();
+		}
 
 		renderer.set_tex_matrix(&Matrix32::identity());
 	}
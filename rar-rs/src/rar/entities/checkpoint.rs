@@ -0,0 +1,125 @@
+use oml_game::math::{Rectangle, Vector2};
+use oml_game::renderer::{Color, Renderer};
+
+use crate::rar::camera::Camera;
+use crate::rar::effect_ids::EffectId;
+use crate::rar::entities::{Entity, EntityConfiguration, EntityData, EntityType};
+use crate::rar::layer_ids::LayerId;
+use crate::rar::EntityUpdateContext;
+
+const ACTIVATION_DURATION: f64 = 0.75;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckpointState {
+	Inactive,
+	Activating,
+	Active,
+}
+
+#[derive(Debug)]
+pub struct Checkpoint {
+	name:            String,
+	rect:            Rectangle,
+	spawn_pos:       Vector2,
+	state:           CheckpointState,
+	activation_time: f64,
+	entity_data:     EntityData,
+}
+
+impl Checkpoint {
+	pub fn new(name: &str, rect: &Rectangle, spawn_pos: &Vector2) -> Self {
+		Self {
+			name:            name.to_string(),
+			rect:            rect.clone(),
+			spawn_pos:       *spawn_pos,
+			state:           CheckpointState::Inactive,
+			activation_time: 0.0,
+			entity_data:     EntityData::default(),
+		}
+	}
+
+	pub fn rect(&self) -> &Rectangle {
+		&self.rect
+	}
+
+	pub fn spawn_pos(&self) -> &Vector2 {
+		&self.spawn_pos
+	}
+
+	pub fn state(&self) -> CheckpointState {
+		self.state
+	}
+
+	pub fn is_active(&self) -> bool {
+		self.state != CheckpointState::Inactive
+	}
+
+	pub fn activate(&mut self) {
+		if self.state == CheckpointState::Inactive {
+			self.state = CheckpointState::Activating;
+			self.activation_time = 0.0;
+		}
+	}
+
+	pub fn deactivate(&mut self) {
+		self.state = CheckpointState::Inactive;
+	}
+
+	fn activation_progress(&self) -> f32 {
+		match self.state {
+			CheckpointState::Inactive => 0.0,
+			CheckpointState::Activating => (self.activation_time / ACTIVATION_DURATION) as f32,
+			CheckpointState::Active => 1.0,
+		}
+	}
+}
+
+impl Entity for Checkpoint {
+	fn data(&self) -> &EntityData {
+		&self.entity_data
+	}
+	fn as_any(&self) -> &dyn std::any::Any {
+		self
+	}
+	fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+		self
+	}
+
+	fn setup(&mut self, _ec: &EntityConfiguration) {}
+
+	fn teardown(&mut self) {}
+
+	fn update(&mut self, euc: &mut EntityUpdateContext) {
+		if self.state == CheckpointState::Activating {
+			self.activation_time += euc.time_step();
+			if self.activation_time >= ACTIVATION_DURATION {
+				self.state = CheckpointState::Active;
+			}
+		}
+	}
+
+	fn render(&mut self, renderer: &mut Renderer, camera: &Camera) {
+		// :TODO: use an animated texture once we have art for checkpoints
+		let p = self.activation_progress();
+		// a little bounce while activating
+		let punch = 1.0 + 0.25 * (p * std::f32::consts::PI).sin();
+		let color = Color::from_rgba(0.5 - 0.3 * p, 0.5 + 0.4 * p, 0.5 - 0.3 * p, 0.8);
+
+		renderer.use_layer(LayerId::Props as u8);
+		renderer.use_effect(EffectId::Colored as u16);
+		renderer.set_color(&color);
+
+		let pos = camera.world_to_screen(self.rect.center());
+		let size = self.rect.size().scaled(punch * camera.scale());
+		renderer.render_quad(&pos, &size);
+		renderer.set_color(&Color::white());
+	}
+
+	fn name(&self) -> &str {
+		&self.name
+	}
+
+	fn entity_type(&self) -> EntityType {
+		EntityType::Decoration
+	}
+}
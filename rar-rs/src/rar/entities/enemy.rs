@@ -0,0 +1,397 @@
+use std::collections::HashMap;
+
+use oml_game::math::{Cardinals, Rectangle, Vector2};
+use oml_game::renderer::{AnimatedTexture, Color, Renderer};
+use tracing::*;
+
+use crate::rar::camera::Camera;
+use crate::rar::collision;
+use crate::rar::effect_ids::EffectId;
+use crate::rar::entities::{Entity, EntityConfiguration, EntityData, EntityState, EntityType};
+use crate::rar::layer_ids::LayerId;
+use crate::rar::map;
+use crate::rar::EntityUpdateContext;
+
+const FPS: f32 = 25.0;
+const GRAVITY: f32 = 1500.0;
+const MAX_FALL_SPEED: f32 = 1000.0;
+// how far below the world bounds we can fall before we die
+const WORLD_BOUNDS_MARGIN: f32 = 256.0;
+
+// numeric parameters that can be set via entity config, and overridden via map object properties,
+// `stompable` is a bool, both in the entity config, and as map object property
+const PARAMETERS: &[&str] = &[
+	"speed",
+	"chase_speed",
+	"chase_radius",
+	"patrol_distance",
+	"width",
+	"height",
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EnemyBehaviour {
+	Patrol,
+	WalkUntilLedge,
+	Chase,
+}
+
+impl From<&str> for EnemyBehaviour {
+	fn from(s: &str) -> Self {
+		match s {
+			"patrol" => EnemyBehaviour::Patrol,
+			"walk_until_ledge" => EnemyBehaviour::WalkUntilLedge,
+			"chase" => EnemyBehaviour::Chase,
+			o => {
+				warn!("Unknown enemy behaviour >{}<, using walk_until_ledge", o);
+				EnemyBehaviour::WalkUntilLedge
+			},
+		}
+	}
+}
+
+impl From<EnemyBehaviour> for &str {
+	fn from(eb: EnemyBehaviour) -> Self {
+		match eb {
+			EnemyBehaviour::Patrol => "patrol",
+			EnemyBehaviour::WalkUntilLedge => "walk_until_ledge",
+			EnemyBehaviour::Chase => "chase",
+		}
+	}
+}
+
+#[derive(Debug)]
+pub struct Enemy {
+	name:              String,
+	spawn_pos:         Vector2,
+	pos:               Vector2,
+	old_pos:           Vector2,
+	size:              Vector2,
+	collision_size:    Vector2,
+	speed:             Vector2,
+	direction:         f32,
+	grounded:          bool,
+	behaviour:         EnemyBehaviour,
+	walk_speed:        f32,
+	chase_speed:       f32,
+	chase_radius:      f32,
+	patrol_distance:   f32,
+	patrol_range:      Option<(f32, f32)>,
+	stompable:         bool,
+	entity_data:       EntityData,
+	animated_textures: HashMap<String, AnimatedTexture>,
+}
+
+impl Enemy {
+	pub fn new(spawn_pos: &Vector2) -> Self {
+		Self {
+			name:              "enemy".to_string(),
+			spawn_pos:         *spawn_pos,
+			pos:               *spawn_pos,
+			old_pos:           *spawn_pos,
+			size:              Vector2::new(128.0, 128.0),
+			collision_size:    Vector2::new(64.0, 96.0),
+			speed:             Vector2::zero(),
+			direction:         -1.0,
+			grounded:          false,
+			behaviour:         EnemyBehaviour::WalkUntilLedge,
+			walk_speed:        80.0,
+			chase_speed:       120.0,
+			chase_radius:      512.0,
+			patrol_distance:   256.0,
+			patrol_range:      None,
+			stompable:         true,
+			entity_data:       EntityData::default(),
+			animated_textures: HashMap::new(),
+		}
+	}
+
+	/// Patrol between the left and right edge of the given rectangle instead of
+	/// `patrol_distance` around the spawn position.
+	pub fn with_patrol_rect(mut self, rect: &Rectangle) -> Self {
+		let min = collision::rectangle_min(rect);
+		let max = collision::rectangle_max(rect);
+		self.patrol_range = Some((min.x, max.x));
+		self
+	}
+
+	pub fn set_behaviour(&mut self, behaviour: EnemyBehaviour) {
+		self.behaviour = behaviour;
+	}
+
+	pub fn behaviour(&self) -> EnemyBehaviour {
+		self.behaviour
+	}
+
+	pub fn pos(&self) -> &Vector2 {
+		&self.pos
+	}
+
+	pub fn is_stompable(&self) -> bool {
+		self.stompable
+	}
+
+	pub fn bounding_rect(&self) -> Rectangle {
+		Rectangle::default()
+			.with_size(&self.collision_size)
+			.with_center(&self.pos)
+	}
+
+	pub fn apply_object_properties(&mut self, object: &map::Object) {
+		if let Some(behaviour) = object.property("behaviour") {
+			self.set_behaviour(behaviour.into());
+		}
+		for name in PARAMETERS.iter() {
+			if let Some(value) = object.property_as_f32(name) {
+				self.set_parameter(name, value);
+			}
+		}
+		if let Some(stompable) = object.property_as_bool("stompable") {
+			self.stompable = stompable;
+		}
+	}
+
+	fn set_parameter(&mut self, name: &str, value: f32) {
+		match name {
+			"speed" => self.walk_speed = value,
+			"chase_speed" => self.chase_speed = value,
+			"chase_radius" => self.chase_radius = value,
+			"patrol_distance" => self.patrol_distance = value,
+			"width" => self.collision_size.x = value,
+			"height" => self.collision_size.y = value,
+			o => {
+				warn!("Unknown enemy parameter >{}<", o);
+			},
+		}
+	}
+
+	fn setup_from_configuration(&mut self, ec: &EntityConfiguration) {
+		if let Some(behaviour) = ec.behaviour() {
+			self.set_behaviour(behaviour.into());
+		}
+		for name in PARAMETERS.iter() {
+			if let Some(value) = ec.parameter(name) {
+				self.set_parameter(name, value);
+			}
+		}
+		if let Some(stompable) = ec.stompable() {
+			self.stompable = stompable;
+		}
+
+		// :TODO: support more than one state once we have art for it
+		for (_sk, sv) in ec.states_iter().filter(|(k, _)| *k == "walking") {
+			self.size = Vector2::new(sv.size()[0], sv.size()[1]);
+			for (_dk, dv) in sv.directions_iter() {
+				let mut animated_texture = AnimatedTexture::new();
+				animated_texture.setup(dv.template(), sv.first_frame(), sv.last_frame(), FPS);
+				self.animated_textures
+					.insert(dv.name().to_string(), animated_texture);
+			}
+		}
+	}
+
+	fn direction_name(&self) -> &str {
+		if self.direction < 0.0 {
+			"left"
+		} else {
+			"right"
+		}
+	}
+
+	fn is_ledge_ahead(&self, euc: &EntityUpdateContext) -> bool {
+		if !self.grounded {
+			return false;
+		}
+		let half_size = self.collision_size.scaled(0.5);
+		let probe = self.pos.add(&Vector2::new(
+			self.direction * (half_size.x + 4.0),
+			-(half_size.y + 8.0),
+		));
+		!collision::is_solid_at(euc.world(), &probe)
+	}
+
+	fn update_patrol(&mut self, _euc: &mut EntityUpdateContext) {
+		let (from, to) = self.patrol_range.unwrap_or((
+			self.spawn_pos.x - self.patrol_distance,
+			self.spawn_pos.x + self.patrol_distance,
+		));
+		if self.pos.x <= from {
+			self.direction = 1.0;
+		} else if self.pos.x >= to {
+			self.direction = -1.0;
+		}
+		self.speed.x = self.direction * self.walk_speed;
+	}
+
+	fn update_walk_until_ledge(&mut self, euc: &mut EntityUpdateContext) {
+		if self.is_ledge_ahead(euc) {
+			self.direction = -self.direction;
+		}
+		self.speed.x = self.direction * self.walk_speed;
+	}
+
+	fn update_chase(&mut self, euc: &mut EntityUpdateContext) {
+		let target = euc
+			.nearest_player_position(&self.pos)
+			.filter(|p| p.sub(&self.pos).length() <= self.chase_radius)
+			.copied();
+
+		self.speed.x = 0.0;
+		if let Some(target) = target {
+			let dx = target.x - self.pos.x;
+			if dx.abs() > 8.0 {
+				self.direction = dx.signum();
+				// never chase off a ledge
+				if !self.is_ledge_ahead(euc) {
+					self.speed.x = self.direction * self.chase_speed;
+				}
+			}
+		}
+	}
+
+	fn handle_collision(&mut self, euc: &EntityUpdateContext) {
+		let start = self.old_pos;
+		let end = self.pos;
+		// :TODO: ride platforms like the player does
+		let col = collision::find_first_collision(euc, &start, &end, &self.collision_size, false);
+
+		self.grounded = false;
+		if let Some((col, _)) = col {
+			let actual = collision::tile_collision_pos(&start, &end, &col);
+			match col.1 {
+				Cardinals::Bottom => {
+					self.pos = actual;
+					self.pos.y += 1.0;
+					self.speed.y = 0.0;
+					self.grounded = true;
+				},
+				Cardinals::Top => {
+					self.pos = actual;
+					self.speed.y = 0.0;
+				},
+				Cardinals::Left => {
+					self.pos = actual;
+					self.pos.x += 1.0;
+					self.direction = 1.0;
+				},
+				Cardinals::Right => {
+					self.pos = actual;
+					self.pos.x -= 1.0;
+					self.direction = -1.0;
+				},
+			}
+		}
+	}
+}
+
+impl Entity for Enemy {
+	fn data(&self) -> &EntityData {
+		&self.entity_data
+	}
+	fn as_any(&self) -> &dyn std::any::Any {
+		self
+	}
+	fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+		self
+	}
+
+	fn setup(&mut self, ec: &EntityConfiguration) {
+		self.setup_from_configuration(ec);
+	}
+
+	fn teardown(&mut self) {}
+
+	fn update(&mut self, euc: &mut EntityUpdateContext) {
+		let d = self.direction_name().to_string();
+		if let Some(animated_texture) = self.animated_textures.get_mut(&d) {
+			animated_texture.update(euc.time_step());
+		}
+
+		match self.entity_data.state {
+			EntityState::Alive => {},
+			EntityState::Dieing { .. } => {
+				self.entity_data.update(euc.time_step());
+				return;
+			},
+			EntityState::Dead => return,
+		}
+
+		match self.behaviour {
+			EnemyBehaviour::Patrol => self.update_patrol(euc),
+			EnemyBehaviour::WalkUntilLedge => self.update_walk_until_ledge(euc),
+			EnemyBehaviour::Chase => self.update_chase(euc),
+		}
+
+		if let Some(debug_renderer) = &*euc.debug_renderer {
+			let mut debug_renderer = debug_renderer.borrow_mut();
+			let color = Color::from_rgba(0.9, 0.3, 0.3, 0.8);
+			debug_renderer.add_rectangle(&self.bounding_rect(), 3.0, &color);
+			if self.behaviour == EnemyBehaviour::Chase {
+				debug_renderer.add_circle(&self.pos, self.chase_radius, 1.0, &color);
+			}
+		}
+	}
+
+	fn fixed_update(&mut self, euc: &EntityUpdateContext) {
+		self.old_pos = self.pos;
+
+		if !self.is_alive() {
+			return;
+		}
+
+		let time_step = euc.time_step() as f32;
+		self.speed.y = (self.speed.y - GRAVITY * time_step).max(-MAX_FALL_SPEED);
+		self.pos = self.pos.add(&self.speed.scaled(time_step));
+
+		self.handle_collision(euc);
+
+		if let Some(bounds) = euc.world().bounds() {
+			let min_y = collision::rectangle_min(&bounds).y - WORLD_BOUNDS_MARGIN;
+			if self.pos.y < min_y {
+				debug!("Enemy fell out of world {} < {}", self.pos.y, min_y);
+				self.entity_data.state = EntityState::Dead;
+			}
+		}
+	}
+
+	fn render(&mut self, renderer: &mut Renderer, camera: &Camera) {
+		if self.is_dead() {
+			return;
+		}
+
+		renderer.use_layer(LayerId::Player as u8);
+		renderer.use_effect(EffectId::ColoredTextured as u16);
+		// :HACK: tint until we have enemy art
+		renderer.set_color(&Color::from_rgba(1.0, 0.4, 0.4, 1.0));
+
+		if let Some(animated_texture) = self.animated_textures.get(self.direction_name()) {
+			animated_texture.r#use(renderer);
+		}
+
+		// squash when stomped
+		let squash = 1.0 - self.entity_data.deadness() as f32;
+		let size = Vector2::new(self.size.x, self.size.y * squash);
+		let pos = self
+			.pos
+			.add(&Vector2::new(0.0, -0.5 * (self.size.y - size.y)));
+		let pos = camera.world_to_screen(&pos);
+		let size = size.scaled(camera.scale());
+
+		renderer.render_textured_quad(&pos, &size);
+	}
+
+	fn name(&self) -> &str {
+		&self.name
+	}
+
+	fn entity_type(&self) -> EntityType {
+		EntityType::Enemy
+	}
+
+	fn kill(&mut self) {
+		if self.is_alive() {
+			self.speed = Vector2::zero();
+			self.entity_data.state = EntityState::Dieing { time: 0.0 };
+		}
+	}
+}
@@ -121,11 +121,44 @@ impl EntityConfigurationState {
 	}
 }
 
+/// One plane of a parallax background.
+#[derive(Debug, Clone)]
+pub struct BackgroundPlaneConfiguration {
+	texture:  String,
+	parallax: [f32; 2],
+	size:     [f32; 2],
+}
+
+impl BackgroundPlaneConfiguration {
+	pub fn new(texture: &str, parallax: &[f32; 2], size: &[f32; 2]) -> Self {
+		Self {
+			texture:  texture.to_string(),
+			parallax: *parallax,
+			size:     *size,
+		}
+	}
+	pub fn texture(&self) -> &str {
+		&self.texture
+	}
+	/// 0.0 stays fixed on screen, 1.0 moves with the world
+	pub fn parallax(&self) -> &[f32; 2] {
+		&self.parallax
+	}
+	/// The area in world units one repeat of the texture covers.
+	pub fn size(&self) -> &[f32; 2] {
+		&self.size
+	}
+}
+
 #[derive(Debug)]
 pub struct EntityConfiguration {
-	name:   String,
+	name:       String,
 	//	entity_type: String,
-	states: HashMap<String, EntityConfigurationState>,
+	states:     HashMap<String, EntityConfigurationState>,
+	behaviour:  Option<String>,
+	stompable:  Option<bool>,
+	parameters: HashMap<String, f32>,
+	planes:     Vec<BackgroundPlaneConfiguration>,
 	//	pub entity_id: EntityId,
 	//	pub entity_type: EntityType,
 	//	pub animated_texture_configuration: AnimatedTextureConfiguration,
@@ -139,9 +172,13 @@ impl EntityConfiguration {
 		//		animated_texture_configuration: AnimatedTextureConfiguration,
 	) -> Self {
 		Self {
-			name:   name.to_string(),
+			name:       name.to_string(),
 			//			entity_type: entity_type.to_string(),
-			states: HashMap::new(),
+			states:     HashMap::new(),
+			behaviour:  None,
+			stompable:  None,
+			parameters: HashMap::new(),
+			planes:     Vec::new(),
 			//			entity_id: EntityId::NONE,
 			//			entity_type: EntityType::None,
 			//			size,
@@ -158,6 +195,40 @@ impl EntityConfiguration {
 	) -> std::collections::hash_map::Iter<String, EntityConfigurationState> {
 		self.states.iter()
 	}
+
+	pub fn set_behaviour(&mut self, behaviour: &str) {
+		self.behaviour = Some(behaviour.to_string());
+	}
+
+	pub fn behaviour(&self) -> Option<&str> {
+		self.behaviour.as_deref()
+	}
+
+	pub fn set_stompable(&mut self, stompable: bool) {
+		self.stompable = Some(stompable);
+	}
+
+	/// Only for enemies, None keeps their default.
+	pub fn stompable(&self) -> Option<bool> {
+		self.stompable
+	}
+
+	pub fn set_parameter(&mut self, name: &str, value: f32) {
+		self.parameters.insert(name.to_string(), value);
+	}
+
+	pub fn parameter(&self, name: &str) -> Option<f32> {
+		self.parameters.get(name).copied()
+	}
+
+	pub fn add_plane(&mut self, plane: BackgroundPlaneConfiguration) {
+		self.planes.push(plane);
+	}
+
+	/// Back to front.
+	pub fn planes(&self) -> &[BackgroundPlaneConfiguration] {
+		&self.planes
+	}
 }
 
 // :TEMP: until I know where this will be going
@@ -196,12 +267,35 @@ impl Default for EntityConfigurationYamlState {
 	}
 }
 
+fn default_plane_size() -> [f32; 2] {
+	[1024.0, 1024.0]
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+struct EntityConfigurationYamlPlane {
+	texture:  String,
+	#[serde(default)]
+	parallax: [f32; 2],
+	#[serde(default = "default_plane_size")]
+	size:     [f32; 2],
+}
+
 #[derive(Debug, PartialEq, Deserialize, Serialize)]
 struct EntiyConfigurationYamlEntity {
 	name:        String,
 	#[serde(rename = "type")]
 	entity_type: String,
+	#[serde(default)]
 	states:      HashMap<String, EntityConfigurationYamlState>,
+	#[serde(default)]
+	behaviour:   Option<String>,
+	#[serde(default)]
+	stompable:   Option<bool>,
+	#[serde(default)]
+	parameters:  HashMap<String, f32>,
+	/// only for backgrounds
+	#[serde(default)]
+	planes:      Vec<EntityConfigurationYamlPlane>,
 }
 impl Default for EntiyConfigurationYamlEntity {
 	fn default() -> Self {
@@ -209,6 +303,10 @@ impl Default for EntiyConfigurationYamlEntity {
 			name:        "[name]".to_string(),
 			entity_type: "[type]".to_string(),
 			states:      HashMap::new(),
+			behaviour:   None,
+			stompable:   None,
+			parameters:  HashMap::new(),
+			planes:      Vec::new(),
 		}
 	}
 }
@@ -277,6 +375,22 @@ impl EntityConfigurationManager {
 			}
 			ec.add_state(s);
 		}
+		if let Some(behaviour) = &ecye.behaviour {
+			ec.set_behaviour(behaviour);
+		}
+		if let Some(stompable) = ecye.stompable {
+			ec.set_stompable(stompable);
+		}
+		for (k, v) in ecye.parameters {
+			ec.set_parameter(&k, v);
+		}
+		for p in ecye.planes {
+			ec.add_plane(BackgroundPlaneConfiguration::new(
+				&p.texture,
+				&p.parallax,
+				&p.size,
+			));
+		}
 
 		self.add_config(ec);
 		Ok(())
@@ -41,6 +41,12 @@ impl EntityManager {
 		id
 	}
 
+	pub fn remove(&mut self, id: EntityId) {
+		if let Some(mut e) = self.entities.remove(&id) {
+			e.teardown();
+		}
+	}
+
 	pub fn get(&self, id: EntityId) -> Option<&Box<dyn Entity>> {
 		self.entities.get(&id)
 	}
@@ -76,6 +82,10 @@ impl EntityManager {
 		}
 	}
 
+	pub fn iter(&self) -> std::collections::hash_map::Values<'_, u32, Box<(dyn Entity + 'static)>> {
+		self.entities.values()
+	}
+
 	pub fn iter_mut(
 		&mut self,
 	) -> std::collections::hash_map::ValuesMut<'_, u32, Box<(dyn Entity + 'static)>> {
@@ -2,7 +2,9 @@
 pub enum EntityType {
 	None,
 	Player,
+	Enemy,
 	Pickup,
 	Obstacle,
+	Platform,
 	Decoration,
 }
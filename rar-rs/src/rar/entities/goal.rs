@@ -0,0 +1,74 @@
+use oml_game::math::Rectangle;
+use oml_game::renderer::{Color, Renderer};
+
+use crate::rar::camera::Camera;
+use crate::rar::effect_ids::EffectId;
+use crate::rar::entities::{Entity, EntityConfiguration, EntityData, EntityType};
+use crate::rar::layer_ids::LayerId;
+use crate::rar::EntityUpdateContext;
+
+#[derive(Debug)]
+pub struct Goal {
+	name:        String,
+	rect:        Rectangle,
+	time:        f64,
+	entity_data: EntityData,
+}
+
+impl Goal {
+	pub fn new(name: &str, rect: &Rectangle) -> Self {
+		Self {
+			name:        name.to_string(),
+			rect:        rect.clone(),
+			time:        0.0,
+			entity_data: EntityData::default(),
+		}
+	}
+
+	pub fn rect(&self) -> &Rectangle {
+		&self.rect
+	}
+}
+
+impl Entity for Goal {
+	fn data(&self) -> &EntityData {
+		&self.entity_data
+	}
+	fn as_any(&self) -> &dyn std::any::Any {
+		self
+	}
+	fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+		self
+	}
+
+	fn setup(&mut self, _ec: &EntityConfiguration) {}
+
+	fn teardown(&mut self) {}
+
+	fn update(&mut self, euc: &mut EntityUpdateContext) {
+		self.time += euc.time_step();
+	}
+
+	fn render(&mut self, renderer: &mut Renderer, camera: &Camera) {
+		// :TODO: use a flag, or a door once we have art for goals
+		let glow = 0.5 + 0.5 * (self.time * 3.0).sin() as f32;
+		let color = Color::from_rgba(0.9, 0.7 + 0.2 * glow, 0.2, 0.6 + 0.2 * glow);
+
+		renderer.use_layer(LayerId::Props as u8);
+		renderer.use_effect(EffectId::Colored as u16);
+		renderer.set_color(&color);
+
+		let pos = camera.world_to_screen(self.rect.center());
+		let size = self.rect.size().scaled(camera.scale());
+		renderer.render_quad(&pos, &size);
+		renderer.set_color(&Color::white());
+	}
+
+	fn name(&self) -> &str {
+		&self.name
+	}
+
+	fn entity_type(&self) -> EntityType {
+		EntityType::Decoration
+	}
+}
@@ -0,0 +1,62 @@
+use oml_game::math::Rectangle;
+use oml_game::renderer::{Color, Renderer};
+
+use crate::rar::camera::Camera;
+use crate::rar::entities::{Entity, EntityConfiguration, EntityData, EntityType};
+use crate::rar::EntityUpdateContext;
+
+#[derive(Debug)]
+pub struct Obstacle {
+	name:        String,
+	rect:        Rectangle,
+	entity_data: EntityData,
+}
+
+impl Obstacle {
+	pub fn new(rect: &Rectangle) -> Self {
+		Self {
+			name:        "obstacle".to_string(),
+			rect:        rect.clone(),
+			entity_data: EntityData::default(),
+		}
+	}
+
+	pub fn rect(&self) -> &Rectangle {
+		&self.rect
+	}
+}
+
+impl Entity for Obstacle {
+	fn data(&self) -> &EntityData {
+		&self.entity_data
+	}
+	fn as_any(&self) -> &dyn std::any::Any {
+		self
+	}
+	fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+		self
+	}
+
+	fn setup(&mut self, _ec: &EntityConfiguration) {}
+
+	fn teardown(&mut self) {}
+
+	fn update(&mut self, euc: &mut EntityUpdateContext) {
+		if let Some(debug_renderer) = &*euc.debug_renderer {
+			let mut debug_renderer = debug_renderer.borrow_mut();
+			debug_renderer.add_rectangle(&self.rect, 5.0, &Color::red());
+		}
+	}
+
+	fn render(&mut self, _renderer: &mut Renderer, _camera: &Camera) {
+		// :TODO: obstacles are invisible until we have art for them
+	}
+
+	fn name(&self) -> &str {
+		&self.name
+	}
+
+	fn entity_type(&self) -> EntityType {
+		EntityType::Obstacle
+	}
+}
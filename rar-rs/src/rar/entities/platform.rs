@@ -0,0 +1,155 @@
+use oml_game::math::{Rectangle, Vector2};
+use oml_game::renderer::{Color, Renderer};
+
+use crate::rar::camera::Camera;
+use crate::rar::effect_ids::EffectId;
+use crate::rar::entities::{Entity, EntityConfiguration, EntityData, EntityType};
+use crate::rar::layer_ids::LayerId;
+use crate::rar::EntityUpdateContext;
+
+/// A kinematic platform moving along a path with constant speed.
+#[derive(Debug)]
+pub struct Platform {
+	name:        String,
+	size:        Vector2,
+	pos:         Vector2,
+	path:        Vec<Vector2>,
+	looping:     bool,
+	speed:       f32,
+	target:      usize,
+	forward:     bool,
+	movement:    Vector2,
+	entity_data: EntityData,
+}
+
+impl Platform {
+	pub fn new(name: &str, size: &Vector2, path: Vec<Vector2>) -> Self {
+		let pos = path.first().copied().unwrap_or(Vector2::zero());
+		Self {
+			name: name.to_string(),
+			size: *size,
+			pos,
+			path,
+			looping: false,
+			speed: 100.0,
+			target: 1,
+			forward: true,
+			movement: Vector2::zero(),
+			entity_data: EntityData::default(),
+		}
+	}
+
+	pub fn with_speed(mut self, speed: f32) -> Self {
+		self.speed = speed;
+		self
+	}
+
+	/// Move from the last point back to the first, instead of reversing.
+	pub fn with_looping(mut self, looping: bool) -> Self {
+		self.looping = looping;
+		self
+	}
+
+	pub fn rect(&self) -> Rectangle {
+		Rectangle::default()
+			.with_size(&self.size)
+			.with_center(&self.pos)
+	}
+
+	pub fn movement(&self) -> &Vector2 {
+		&self.movement
+	}
+
+	fn next_target(&mut self) {
+		let last = self.path.len() - 1;
+		if self.forward {
+			if self.target < last {
+				self.target += 1;
+			} else if self.looping {
+				self.target = 0;
+			} else {
+				self.forward = false;
+				self.target = last - 1;
+			}
+		} else if self.target > 0 {
+			self.target -= 1;
+		} else {
+			self.forward = true;
+			self.target = 1;
+		}
+	}
+}
+
+impl Entity for Platform {
+	fn data(&self) -> &EntityData {
+		&self.entity_data
+	}
+	fn as_any(&self) -> &dyn std::any::Any {
+		self
+	}
+	fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+		self
+	}
+
+	fn setup(&mut self, _ec: &EntityConfiguration) {}
+
+	fn teardown(&mut self) {}
+
+	fn update(&mut self, euc: &mut EntityUpdateContext) {
+		if let Some(debug_renderer) = &*euc.debug_renderer {
+			let mut debug_renderer = debug_renderer.borrow_mut();
+			let color = Color::from_rgba(0.6, 0.4, 0.9, 0.8);
+			debug_renderer.add_rectangle(&self.rect(), 3.0, &color);
+			for (a, b) in self.path.iter().zip(self.path.iter().skip(1)) {
+				debug_renderer.add_line(a, b, 1.0, &color);
+			}
+		}
+	}
+
+	fn fixed_update(&mut self, euc: &EntityUpdateContext) {
+		let old_pos = self.pos;
+		if self.path.len() < 2 {
+			self.movement = Vector2::zero();
+			return;
+		}
+
+		let mut distance = self.speed * euc.time_step() as f32;
+		// guard against degenerate paths, e.g. duplicated points
+		let mut steps = self.path.len() * 2;
+		while distance > 0.0 && steps > 0 {
+			let target = self.path[self.target];
+			let delta = target.sub(&self.pos);
+			let l = delta.length();
+			if l > distance {
+				self.pos = self.pos.add(&delta.scaled(distance / l));
+				distance = 0.0;
+			} else {
+				self.pos = target;
+				distance -= l;
+				self.next_target();
+			}
+			steps -= 1;
+		}
+
+		self.movement = self.pos.sub(&old_pos);
+	}
+
+	fn render(&mut self, renderer: &mut Renderer, camera: &Camera) {
+		// :TODO: use tiles once we have art for platforms
+		renderer.use_layer(LayerId::Props as u8);
+		renderer.use_effect(EffectId::Colored as u16);
+		renderer.set_color(&Color::from_rgba(0.45, 0.35, 0.25, 1.0));
+
+		let pos = camera.world_to_screen(&self.pos);
+		let size = self.size.scaled(camera.scale());
+		renderer.render_quad(&pos, &size);
+	}
+
+	fn name(&self) -> &str {
+		&self.name
+	}
+
+	fn entity_type(&self) -> EntityType {
+		EntityType::Platform
+	}
+}
@@ -4,21 +4,33 @@ use std::collections::VecDeque;
 use std::convert::From;
 
 use oml_game::math::{Cardinals, Rectangle, Vector2};
-use oml_game::renderer::debug_renderer;
 use oml_game::renderer::{AnimatedTexture, Color, Renderer};
 use tracing::*;
 
 use crate::rar::camera::Camera;
+use crate::rar::camera_effects::CameraEffect;
+use crate::rar::collision;
+use crate::rar::debug_draw;
 use crate::rar::effect_ids::EffectId;
 use crate::rar::entities::Entity;
 use crate::rar::entities::EntityConfiguration;
 use crate::rar::entities::EntityData;
+use crate::rar::entities::EntityId;
+use crate::rar::entities::EntityState as EntityDataState;
 use crate::rar::entities::EntityType;
 use crate::rar::layer_ids::LayerId;
 use crate::rar::map::ObjectData;
 use crate::rar::EntityUpdateContext;
 
 const FPS: f32 = 25.0;
+// horizontal speed at full input
+const RUN_SPEED: f32 = 100.0;
+// how far below the world bounds we can fall before we die
+const WORLD_BOUNDS_MARGIN: f32 = 256.0;
+// landing faster than this shakes the camera, harder the faster we were falling
+const LANDING_SHAKE_SPEED: f32 = 600.0;
+const LANDING_SHAKE_TRAUMA_PER_SPEED: f32 = 1.0 / 1000.0;
+const LANDING_SHAKE_MAX_TRAUMA: f32 = 0.6;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum PlayerState {
@@ -131,8 +143,8 @@ pub struct Player {
 	speed:               Vector2,
 	movement:            Vector2,
 	grounded:            bool,
+	riding:              Option<EntityId>,
 	hit_max_jump:        bool,
-	time_since_dying:    f32,
 	input_context_index: u8,
 	entity_data:         EntityData,
 
@@ -154,8 +166,8 @@ impl Player {
 			speed:               Vector2::zero(),
 			movement:            Vector2::zero(),
 			grounded:            false,
+			riding:              None,
 			hit_max_jump:        false,
-			time_since_dying:    f32::MAX,
 			input_context_index: 0xff,
 			entity_data:         EntityData::default(),
 
@@ -182,6 +194,14 @@ impl Player {
 		self.state == PlayerState::Dead
 	}
 
+	pub fn speed(&self) -> &Vector2 {
+		&self.speed
+	}
+
+	pub fn is_grounded(&self) -> bool {
+		self.grounded
+	}
+
 	pub fn movement(&self) -> &Vector2 {
 		&self.movement
 	}
@@ -211,7 +231,13 @@ impl Player {
 				}
 			},
 			PlayerState::Dying => {
-				self.time_since_dying = 0.0;
+				self.speed = Vector2::zero();
+				self.entity_data.state = EntityDataState::Dieing { time: 0.0 };
+				self.state = state; // :HACK: so we get the correct state direction below
+				if let Some(state_direction) = self.get_state_direction_mut() {
+					state_direction.animated_texture.set_autoloop(false);
+					state_direction.animated_texture.set_current_frame(0);
+				}
 			},
 			_ => {},
 		}
@@ -221,6 +247,10 @@ impl Player {
 	pub fn respawn(&mut self) {
 		match self.state {
 			PlayerState::Dead => {
+				self.entity_data.state = EntityDataState::Alive;
+				self.speed = Vector2::zero();
+				self.grounded = false;
+				self.riding = None;
 				self.goto_state(PlayerState::WaitForStart);
 			},
 			_ => {},
@@ -233,9 +263,19 @@ impl Player {
 		}
 	}
 
+	// e.g. after stomping an enemy
+	pub fn bounce(&mut self) {
+		if self.is_alive() {
+			self.speed.y = 200.0;
+			self.state = PlayerState::Jumping;
+			self.grounded = false;
+			self.hit_max_jump = true;
+		}
+	}
+
 	fn update_waiting_for_start(&mut self, euc: &mut EntityUpdateContext) {
 		if let Some(pic) = euc.player_input_context(self.input_context_index) {
-			if pic.is_left_pressed || pic.is_right_pressed {
+			if pic.left.is_down || pic.right.is_down {
 				self.goto_state(PlayerState::Idle); // :TODO: start logic
 			}
 		}
@@ -243,23 +283,23 @@ impl Player {
 
 	fn update_idle(&mut self, euc: &mut EntityUpdateContext) {
 		if let Some(pic) = euc.player_input_context(self.input_context_index) {
-			if pic.is_left_pressed {
-				self.speed.x = -100.0;
+			if pic.left.is_down {
+				self.speed.x = RUN_SPEED * pic.horizontal;
 				self.direction = PlayerDirection::Left;
 				self.state = PlayerState::Running;
-			} else if pic.is_right_pressed {
-				self.speed.x = 100.0;
+			} else if pic.right.is_down {
+				self.speed.x = RUN_SPEED * pic.horizontal;
 				self.direction = PlayerDirection::Right;
 				self.state = PlayerState::Running;
 			} else {
 				self.speed.x = 0.0;
 			}
 			// :HACK:
-			if pic.is_up_pressed {
+			if pic.up.is_down {
 				// jetpack mode
 				self.speed.y = 100.0;
 				self.state = PlayerState::Running;
-			} else if pic.is_down_pressed {
+			} else if pic.down.is_down {
 				self.speed.y = -100.0;
 				self.state = PlayerState::Running;
 			} else {
@@ -267,7 +307,7 @@ impl Player {
 				//				self.speed.y -= 5.0;
 			};
 
-			if pic.is_jump_pressed {
+			if pic.jump.was_pressed {
 				tracing::debug!("Idle -> Jumping");
 				//if self.speed.y >= -100.0 && self.speed.y < 200.0 {
 				// :TODO: make time step dependend
@@ -281,17 +321,17 @@ impl Player {
 	}
 	fn update_running(&mut self, euc: &mut EntityUpdateContext) {
 		if let Some(pic) = euc.player_input_context(self.input_context_index) {
-			if pic.is_left_pressed {
-				self.speed.x = -100.0;
+			if pic.left.is_down {
+				self.speed.x = RUN_SPEED * pic.horizontal;
 				self.direction = PlayerDirection::Left;
-			} else if pic.is_right_pressed {
-				self.speed.x = 100.0;
+			} else if pic.right.is_down {
+				self.speed.x = RUN_SPEED * pic.horizontal;
 				self.direction = PlayerDirection::Right;
 			} else {
 				self.speed.x = 0.0;
 				self.state = PlayerState::Idle;
 			}
-			if pic.is_jump_pressed {
+			if pic.jump.was_pressed {
 				tracing::debug!("Running -> Jumping");
 				self.speed.y += 50.0;
 				self.state = PlayerState::Jumping;
@@ -304,7 +344,11 @@ impl Player {
 	fn update_jumping(&mut self, euc: &mut EntityUpdateContext) {
 		tracing::debug!("Jumping {}", self.hit_max_jump);
 		if let Some(pic) = euc.player_input_context(self.input_context_index) {
-			if !self.hit_max_jump && pic.is_jump_pressed {
+			// letting go of jump cuts the jump short
+			if pic.jump.was_released {
+				self.hit_max_jump = true;
+			}
+			if !self.hit_max_jump && pic.jump.is_down {
 				if self.speed.y < 300.0 {
 					self.speed.y += 50.0;
 				} else {
@@ -332,6 +376,15 @@ impl Player {
 		}
 	}
 
+	fn update_dying(&mut self, euc: &mut EntityUpdateContext) {
+		self.entity_data.update(euc.time_step());
+		if let EntityDataState::Dead = self.entity_data.state {
+			self.goto_state(PlayerState::Dead);
+			// :TODO: decide if we want to wait for input before respawning
+			self.respawn();
+		}
+	}
+
 	fn update_backflip(&mut self, _euc: &mut EntityUpdateContext) {
 		if let Some(state_direction) = self.get_state_direction_mut() {
 			// println!("{:#?}", &state_direction );
@@ -341,12 +394,6 @@ impl Player {
 		}
 	}
 	fn debug_colliders(&mut self, euc: &EntityUpdateContext) {
-		let world = euc.world();
-		//debug!("World {:?}", world);
-		//list_objects_in_layer_for_class
-		//let colliders = world.list_objects_in_layer_for_class( "Collider", "Collider" );
-		let colliders = world.list_objects_in_layer("Collider");
-
 		let start = &self.old_pos;
 		let end = self.pos.clone();
 		let l = start.sub(&end).length();
@@ -357,137 +404,47 @@ impl Player {
 		//debug!("l: {}", l);
 		let er = pc.radius() + l * 1.0;
 		let pc = pc.with_radius(er);
-		debug_renderer::debug_renderer_add_circle(pc.center(), pc.radius(), 5.0, &Color::white());
-		debug_renderer::debug_renderer_add_rectangle(&r, 5.0, &Color::white());
-
-		let mut collisions: Vec<(f32, Cardinals, Rectangle)> = Vec::new();
-
-		let mut collision_cardinal: Option<Cardinals> = None;
-		for c in colliders {
-			match c.data() {
-				ObjectData::Rectangle {
-					rect,
-					bounding_circle,
-				} => {
-					let rect = rect.clone();
-					//rect.offset(&offset);
-
-					// if we have a bounding circle use that for a quick/cheap early out
-					if let Some(bounding_circle) = bounding_circle {
-						if pc.overlaps(&bounding_circle) {
-							debug_renderer::debug_renderer_add_circle(
-								bounding_circle.center(),
-								bounding_circle.radius(),
-								5.0,
-								&Color::red(),
-							);
-						} else {
-							debug_renderer::debug_renderer_add_circle(
-								bounding_circle.center(),
-								bounding_circle.radius(),
-								5.0,
-								&Color::blue(),
-							);
-							continue;
-						}
-					}
+		debug_draw::debug_draw_add_circle(pc.center(), pc.radius(), 5.0, &Color::white());
+		debug_draw::debug_draw_add_rectangle(&r, 5.0, &Color::white());
 
-					// rectangles that we _could_ collide with
-					// debug_renderer::debug_renderer_add_rectangle(&rect, 5.0, &Color::red());
-
-					if let Some(col) = rect.would_collide(&start, &end, &r) {
-						collisions.push((col.0, col.1, rect.clone()));
-						/*
-						debug!("Collision: {:?}", &col);
-						let p = col.0 * 0.5;
-						let full = end.sub(&start).scaled(p);
-						let actual = start.add(&full);
-						let r = r.clone().with_center(&actual);
-						let l = match col.1 {
-							Cardinals::Bottom => {
-								self.pos = *r.center();
-								self.pos.y += 1.0;
-								self.speed.y = 0.0;
-								let x0 = r.left();
-								let x1 = r.right();
-								let y = r.bottom();
-								Some((Vector2::new(x0, y), Vector2::new(x1, y)))
-							},
-							Cardinals::Top => {
-								let x0 = r.left();
-								let x1 = r.right();
-								let y = r.top();
-								Some((Vector2::new(x0, y), Vector2::new(x1, y)))
-							},
-							Cardinals::Left => {
-								self.pos = *r.center();
-								self.pos.x += 1.0;
-								self.speed.x = 0.0;
-								let x = r.left();
-								let y0 = r.bottom();
-								let y1 = r.top();
-								Some((Vector2::new(x, y0), Vector2::new(x, y1)))
-							},
-							Cardinals::Right => {
-								let x = r.right();
-								let y0 = r.bottom();
-								let y1 = r.top();
-								Some((Vector2::new(x, y0), Vector2::new(x, y1)))
-							},
-						};
-
-						if let Some(l) = l {
-							debug!("{:?}", &l);
-							self.last_collision_line.set(Some((l.0, l.1, Color::red())));
-							collision_cardinal = Some(col.1);
-						}
-						*/
-					}
+		let collision_cardinal: Option<Cardinals> = None;
+		let first_collision = collision::find_first_collision(euc, start, &end, r.size(), true);
+		let collided_with = first_collision.and_then(|(_, id)| id);
+		let first_collision = first_collision.map(|(col, _)| col);
 
-					//debug!("{:?}", &rect);
-				},
-				o => {
-					debug!("Collider: {:?}", &o);
-				},
-			}
-			//break;
-		}
-
-		if !collisions.is_empty() {
-			tracing::debug!("Collisions {:?}", collisions);
+		if let Some(col) = &first_collision {
+			tracing::debug!("Collision {:?}", col);
 			tracing::debug!("{:?} -> {:?} [Speed: {:?}]", start, end, self.speed);
 		}
-		oml_game::DefaultTelemetry::trace::<f32>("collision.#", collisions.len() as f32 * 20.0);
+		oml_game::DefaultTelemetry::trace::<f32>(
+			"collision.#",
+			first_collision.map_or(0.0, |_| 20.0),
+		);
 
-		// find *first* collision
-		let mut first_collision = (f32::MAX, Cardinals::Top, Rectangle::default());
-		for col in collisions.iter() {
-			if col.0 < first_collision.0 {
-				first_collision = *col;
-			}
-		}
-		if first_collision.0 < f32::MAX {
-			self.last_collision = first_collision;
+		if let Some(col) = first_collision {
+			self.last_collision = col;
 		}
 		if self.last_collision.0 < f32::MAX {
-			debug_renderer::debug_renderer_add_rectangle(
-				&self.last_collision.2,
-				9.0,
-				&Color::blue(),
-			);
+			debug_draw::debug_draw_add_rectangle(&self.last_collision.2, 9.0, &Color::blue());
 		}
 
 		// handle _first_ collison
-		if first_collision.0 < f32::MAX {
-			let col = first_collision;
-			let p = col.0 * 0.5;
-			let full = end.sub(&start).scaled(p);
-			let actual = start.add(&full);
+		if let Some(col) = first_collision {
+			let actual = collision::tile_collision_pos(start, &end, &col);
 			let r = r.clone().with_center(&actual);
 
 			let l = match col.1 {
 				Cardinals::Bottom => {
+					let landing_speed = -self.speed.y;
+					if landing_speed > LANDING_SHAKE_SPEED {
+						let trauma =
+							(landing_speed - LANDING_SHAKE_SPEED) * LANDING_SHAKE_TRAUMA_PER_SPEED;
+						euc.add_camera_effect(CameraEffect::Shake {
+							trauma: trauma.min(LANDING_SHAKE_MAX_TRAUMA),
+						});
+					}
 					self.grounded = true;
+					self.riding = collided_with;
 					//col_order.push( 'B' );
 					self.pos = *r.center();
 					self.pos.y += 1.0;
@@ -529,7 +486,7 @@ impl Player {
 		for col in collisions.iter() {
 			debug!("Collision: {:?}", &col);
 			// rectangles that we *did* collide with
-			debug_renderer::debug_renderer_add_rectangle(&col.2, 5.0, &Color::red());
+			debug_draw::debug_draw_add_rectangle(&col.2, 5.0, &Color::red());
 			let p = col.0 * 0.5;
 			let full = end.sub(&start).scaled(p);
 			let actual = start.add(&full);
@@ -588,17 +545,81 @@ impl Player {
 		}
 
 		if let Some(l) = self.last_collision_line.get() {
-			debug_renderer::debug_renderer_add_line(
-				&l.0,
-				&l.1,
-				3.0,
-				&Color::from_rgba(0.8, 0.6, 0.4, 1.0),
-			);
+			debug_draw::debug_draw_add_line(&l.0, &l.1, 3.0, &Color::from_rgba(0.8, 0.6, 0.4, 1.0));
 		}
 
 		//		debug!("Colliders {:?}", &colliders);
 	}
 
+	// move along with the platform we are standing on
+	fn ride_platform(&mut self, euc: &EntityUpdateContext) {
+		if let Some(id) = self.riding {
+			match euc.dynamic_collider(id) {
+				Some(dc) => {
+					self.pos = self.pos.add(&dc.movement);
+					// the carried distance is not part of our own movement
+					self.old_pos = self.pos;
+				},
+				None => self.riding = None,
+			}
+		}
+	}
+
+	fn check_still_riding(&mut self, euc: &EntityUpdateContext) {
+		if let Some(id) = self.riding {
+			let r = self.bounding_rect();
+			let dc = euc.dynamic_collider(id);
+			let on_top = dc.map_or(false, |dc| {
+				let p_min = collision::rectangle_min(&r);
+				let p_max = collision::rectangle_max(&r);
+				let dc_min = collision::rectangle_min(&dc.rect);
+				let dc_max = collision::rectangle_max(&dc.rect);
+				p_min.x < dc_max.x && p_max.x > dc_min.x && (p_min.y - dc_max.y).abs() < 4.0
+			});
+			if !on_top {
+				self.riding = None;
+				// keep the platform's momentum, e.g. jumping off a rising platform goes higher
+				if let Some(dc) = dc {
+					if euc.time_step() > 0.0 {
+						let velocity = dc.movement.scaled(1.0 / euc.time_step() as f32);
+						self.speed = self.speed.add(&velocity);
+					}
+				}
+			}
+		}
+	}
+
+	fn check_hazards(&mut self, euc: &EntityUpdateContext) {
+		let world = euc.world();
+		let r = self.bounding_rect();
+
+		let hazards = world.list_objects_in_layer("Hazard");
+		let kills = world.list_objects_in_layer_for_class("Player", "PlayerKill");
+		for h in hazards.iter().chain(kills.iter()) {
+			if let ObjectData::Rectangle { rect, .. } = h.data() {
+				if collision::rectangles_overlap(rect, &r) {
+					tracing::debug!("Killed by hazard {:?}", &rect);
+					self.kill();
+					return;
+				}
+			}
+		}
+
+		if let Some(bounds) = world.bounds() {
+			let min_y = collision::rectangle_min(&bounds).y - WORLD_BOUNDS_MARGIN;
+			if self.pos.y < min_y {
+				tracing::debug!("Fell out of world {} < {}", self.pos.y, min_y);
+				self.kill();
+			}
+		}
+	}
+
+	pub fn bounding_rect(&self) -> Rectangle {
+		Rectangle::default()
+			.with_size(&Vector2::new(12.0, 120.0))
+			.with_center(&self.pos)
+	}
+
 	pub fn set_spawn_pos(&mut self, spawn_pos: &Vector2) {
 		self.spawn_pos = *spawn_pos;
 	}
@@ -704,7 +725,7 @@ impl Entity for Player {
 			PlayerState::Jumping => self.update_jumping(euc),
 			PlayerState::Falling => self.update_falling(euc),
 			PlayerState::Backflip => self.update_backflip(euc),
-			PlayerState::Dying => self.goto_state(PlayerState::Dead),
+			PlayerState::Dying => self.update_dying(euc),
 			_ => {},
 		}
 
@@ -730,11 +751,19 @@ impl Entity for Player {
 
 		self.old_pos = self.pos;
 
+		if !self.is_alive() {
+			return;
+		}
+
+		self.ride_platform(euc);
+
 		self.movement.x = self.speed.x * euc.time_step() as f32;
 		self.movement.y = self.speed.y * euc.time_step() as f32;
 		self.pos = self.pos.add(&self.movement);
 
 		self.debug_colliders(euc);
+		self.check_still_riding(euc);
+		self.check_hazards(euc);
 		/*
 		debug!(
 			"player delta pos y {} ({})",
@@ -754,14 +783,17 @@ impl Entity for Player {
 
 		renderer.use_layer(LayerId::Player as u8);
 		renderer.use_effect(EffectId::Textured as u16);
+		// entities rendered before might have left a tint
+		renderer.set_color(&Color::white());
 
 		if let Some(state_direction) = self.get_state_direction() {
 			state_direction.animated_texture.r#use(renderer);
 		}
 
-		let pos = self.pos.add(&camera.offset());
+		let pos = camera.world_to_screen(&self.pos);
+		let size = self.size.scaled(camera.scale());
 
-		renderer.render_textured_quad(&pos, &self.size);
+		renderer.render_textured_quad(&pos, &size);
 	}
 
 	fn name(&self) -> &str {
@@ -772,3 +804,44 @@ impl Entity for Player {
 		EntityType::Player
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::rar::collision::DynamicCollider;
+
+	fn platform_at(center: &Vector2, movement: &Vector2) -> DynamicCollider {
+		DynamicCollider {
+			entity_id: 7,
+			rect:      Rectangle::default()
+				.with_size(&Vector2::new(192.0, 32.0))
+				.with_center(center),
+			movement:  *movement,
+		}
+	}
+
+	#[test]
+	fn leaving_a_platform_keeps_its_momentum() {
+		let mut player = Player::new();
+		// standing on top of the platform below
+		player.pos = Vector2::new(0.0, 16.0 + 60.0);
+		player.riding = Some(7);
+		let movement = Vector2::new(10.0, 20.0);
+
+		let euc = EntityUpdateContext::new()
+			.set_time_step(0.5)
+			.with_dynamic_colliders(vec![platform_at(&Vector2::zero(), &movement)]);
+		player.check_still_riding(&euc);
+		assert_eq!(player.riding, Some(7));
+		assert_eq!((player.speed.x, player.speed.y), (0.0, 0.0));
+
+		// e.g. after jumping off
+		player.speed = Vector2::new(0.0, 50.0);
+		let euc = EntityUpdateContext::new()
+			.set_time_step(0.5)
+			.with_dynamic_colliders(vec![platform_at(&Vector2::new(0.0, -100.0), &movement)]);
+		player.check_still_riding(&euc);
+		assert_eq!(player.riding, None);
+		assert_eq!((player.speed.x, player.speed.y), (20.0, 90.0));
+	}
+}
@@ -1,10 +1,13 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
-//use oml_game::math::Vector2;
+use oml_game::math::Vector2;
 //use crate::fiiish::game::GameState;
 use oml_game::renderer::debug_renderer::DebugRenderer;
 
+use crate::rar::camera_effects::{CameraEffect, CameraEffectsState};
+use crate::rar::collision::DynamicCollider;
+use crate::rar::entities::EntityId;
 use crate::rar::PlayerInputContext;
 use crate::rar::World;
 
@@ -12,6 +15,11 @@ use crate::rar::World;
 pub struct EntityUpdateContext<'a> {
 	time_step:             f64,
 	player_input_contexts: Vec<PlayerInputContext>,
+	player_positions:      Vec<Vector2>,
+	dynamic_colliders:     Vec<DynamicCollider>,
+	camera_effects_state:  CameraEffectsState,
+	// requests from entities, applied to the camera after the update
+	camera_effects:        RefCell<Vec<CameraEffect>>,
 	world:                 &'a World,
 	fixed_update_count:    u32,
 	//	world_movement: Vector2,
@@ -27,6 +35,10 @@ impl<'a> EntityUpdateContext<'a> {
 		Self {
 			time_step:             0.0,
 			player_input_contexts: Vec::new(),
+			player_positions:      Vec::new(),
+			dynamic_colliders:     Vec::new(),
+			camera_effects_state:  CameraEffectsState::default(),
+			camera_effects:        RefCell::new(Vec::new()),
 			world:                 &EMPTY_WORLD,
 			fixed_update_count:    0,
 			//			world_movement: Vector2::zero(),
@@ -48,6 +60,52 @@ impl<'a> EntityUpdateContext<'a> {
 		self.player_input_contexts.push(player_input_context);
 	}
 
+	pub fn add_player_position(&mut self, pos: &Vector2) {
+		self.player_positions.push(*pos);
+	}
+
+	pub fn nearest_player_position(&self, pos: &Vector2) -> Option<&Vector2> {
+		self.player_positions.iter().min_by(|a, b| {
+			let da = a.sub(pos).length();
+			let db = b.sub(pos).length();
+			da.partial_cmp(&db).unwrap_or(std::cmp::Ordering::Equal)
+		})
+	}
+
+	pub fn with_dynamic_colliders(mut self, dynamic_colliders: Vec<DynamicCollider>) -> Self {
+		self.dynamic_colliders = dynamic_colliders;
+		self
+	}
+
+	pub fn dynamic_colliders(&self) -> &Vec<DynamicCollider> {
+		&self.dynamic_colliders
+	}
+
+	pub fn dynamic_collider(&self, entity_id: EntityId) -> Option<&DynamicCollider> {
+		self.dynamic_colliders
+			.iter()
+			.find(|dc| dc.entity_id == entity_id)
+	}
+
+	pub fn with_camera_effects_state(mut self, camera_effects_state: &CameraEffectsState) -> Self {
+		self.camera_effects_state = *camera_effects_state;
+		self
+	}
+
+	/// The camera effects as of the start of this update.
+	pub fn camera_effects_state(&self) -> &CameraEffectsState {
+		&self.camera_effects_state
+	}
+
+	/// Can be called from `fixed_update` too, effects are applied in the order they were added.
+	pub fn add_camera_effect(&self, effect: CameraEffect) {
+		self.camera_effects.borrow_mut().push(effect);
+	}
+
+	pub fn take_camera_effects(&self) -> Vec<CameraEffect> {
+		self.camera_effects.take()
+	}
+
 	pub fn time_step(&self) -> f64 {
 		self.time_step
 	}
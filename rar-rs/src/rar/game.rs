@@ -1,7 +1,9 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
 use std::sync::Arc;
 
+use oml_game::math::Rectangle;
 use oml_game::math::Vector2;
-use oml_game::renderer::debug_renderer;
 use oml_game::renderer::debug_renderer::DebugRenderer;
 use oml_game::renderer::Color;
 use oml_game::renderer::Renderer;
@@ -10,20 +12,35 @@ use oml_game::system::System;
 use tracing::*;
 
 use crate::rar::camera::Camera;
+use crate::rar::camera::CameraZone;
+use crate::rar::collision;
+use crate::rar::collision::DynamicCollider;
+use crate::rar::data::LevelResult;
 use crate::rar::data::RarData;
+use crate::rar::debug_draw;
 use crate::rar::effect_ids::EffectId;
 use crate::rar::entities::entity::Entity;
 use crate::rar::entities::Background;
+use crate::rar::entities::Checkpoint;
+use crate::rar::entities::Enemy;
 use crate::rar::entities::EntityConfigurationManager;
 use crate::rar::entities::EntityId;
 use crate::rar::entities::EntityManager;
+use crate::rar::entities::EntityType;
+use crate::rar::entities::Goal;
+use crate::rar::entities::Obstacle;
+use crate::rar::entities::Platform;
 use crate::rar::entities::Player;
 use crate::rar::game_state::GameStateResponse;
+use crate::rar::input_actions::InputActionSet;
+use crate::rar::layer_config::WorldLayerConfig;
 use crate::rar::layer_ids::LayerId;
 use crate::rar::map;
 use crate::rar::AppUpdateContext;
 use crate::rar::EntityUpdateContext;
-use crate::rar::PlayerInputContext;
+use crate::rar::MapStreamer;
+use crate::rar::PlayerInputSample;
+use crate::rar::PlayerInputTracker;
 use crate::rar::World;
 use crate::rar::WorldRenderer;
 
@@ -32,12 +49,31 @@ pub struct Game {
 	entity_configuration_manager: EntityConfigurationManager,
 	entity_manager: EntityManager,
 	world: World,
+	map_streamer: MapStreamer,
 	world_renderer: WorldRenderer,
 	camera: Camera,
 	fixed_camera: Camera,
 	use_fixed_camera: bool,
 	total_time: f64,
-	player_id: EntityId,
+	player_ids: Vec<EntityId>,
+	// indexed like player_ids
+	were_players_alive: Vec<bool>,
+	// one per input context, they need to persist to detect presses and releases
+	input_trackers: Vec<PlayerInputTracker>,
+	// from the on screen controls, merged with the input actions
+	touch_samples: Vec<PlayerInputSample>,
+	checkpoint_ids: Vec<EntityId>,
+	active_checkpoint_id: Option<EntityId>,
+	// the entities spawned by each loaded map, keyed like the world's maps
+	spawned_maps: HashMap<usize, Vec<EntityId>>,
+	// maps that added their camera zones already, zones stay when a map streams out
+	zoned_maps: HashSet<usize>,
+	goal_ids: Vec<EntityId>,
+	enemy_ids: Vec<EntityId>,
+	platform_ids: Vec<EntityId>,
+	level_time: f64,
+	death_count: u32,
+	level_result: Option<LevelResult>,
 	world_name: String,
 	fixed_update_count: u32,
 	is_paused: bool,
@@ -60,14 +96,39 @@ impl Game {
 		self.is_paused
 	}
 
+	/// Returns false if there is no such player, or control.
+	pub fn apply_touch_input(
+		&mut self,
+		player_index: usize,
+		control: &str,
+		axis: &Vector2,
+		is_down: bool,
+	) -> bool {
+		match self.touch_samples.get_mut(player_index) {
+			Some(sample) => sample.apply_virtual_input(control, axis, is_down),
+			None => false,
+		}
+	}
+
+	pub fn level_result(&self) -> Option<&LevelResult> {
+		self.level_result.as_ref()
+	}
+
 	pub fn setup(&mut self, system: &mut System) -> anyhow::Result<()> {
 		self.is_paused = false;
+		self.level_time = 0.0;
+		self.death_count = 0;
+		self.level_result = None;
 
 		self.entity_configuration_manager
 			.load(system, "todo_filename");
 
 		self.entity_configuration_manager
 			.load_yaml(system, "player.entity_config.yaml")?;
+		self.entity_configuration_manager
+			.load_yaml(system, "enemy.entity_config.yaml")?;
+		self.entity_configuration_manager
+			.load_yaml(system, "background.entity_config.yaml")?;
 
 		self.entity_manager.setup();
 
@@ -79,48 +140,90 @@ impl Game {
 		// load world
 		debug!("Loading world {}", &self.world_name);
 		self.world.load(system, &self.world_name)?;
-		debug!("Loading all maps...");
-		self.world.load_all_maps(system)?;
-		debug!("Loading all tilesets...");
-		self.world.load_all_tilesets(system)?;
+		let layer_config =
+			WorldLayerConfig::from_config_asset(system, &self.world_name).unwrap_or_default();
+		// drops anything still in flight for the previous world
+		self.map_streamer = layer_config.streaming.map_streamer();
 
+		// registered before loading, so streamed in maps get them too
 		debug!("Generating colliders...");
+		let collider_layers = layer_config.collider_layers();
 		self.world
-			.generate_collider_layers("Collider", &["Tile Layer 1", "terrain"].to_vec())?;
-		//			.generate_collider_layers("Collider", &["Tile Layer", "terrain"].to_vec())?;
-		//			.generate_collider_layers("Collider", &["Tile Layer"].to_vec())?;
-		//		self.world
-		//			.generate_collider_layers("Collider", &["terrain"].to_vec())?;
-		// self.world.generate_collider_layers( "Collider", &[ "Tile Layer" ].to_vec() )?; // force error for testing
+			.generate_collider_layers("Collider", &collider_layers)?;
+		self.world
+			.generate_hazard_layers("Hazard", &collider_layers)?;
+
+		if self.world.is_streamed() {
+			// the first map is where the level starts
+			debug!("Loading maps near the start...");
+			if let Some(start) = self.world.maps().first().map(|wm| wm.bounds()) {
+				self.world.load_maps_near(&self.map_streamer, &start)?;
+			}
+		} else {
+			debug!("Loading all maps...");
+			self.world.load_all_maps()?;
+		}
+
+		self.platform_ids.clear();
+		self.enemy_ids.clear();
+		self.checkpoint_ids.clear();
+		self.active_checkpoint_id = None;
+		self.goal_ids.clear();
+		self.spawned_maps.clear();
+		self.zoned_maps.clear();
+		self.camera.clear_zones();
+		self.camera.clear_effects();
+		let loaded_maps = self.world.loaded_map_indices();
+		self.spawn_map_entities(&loaded_maps);
+		// streamed worlds might have their goal in a map that isn't loaded yet
+		if self.goal_ids.is_empty() && !self.world.is_streamed() {
+			warn!(
+				"World {} has no goal, so it can never be completed",
+				&self.world_name
+			);
+		}
 
 		let player_spawns = self
 			.world
 			.list_objects_in_layer_for_class("Player", "PlayerSpawn");
+		self.player_ids.clear();
+		self.were_players_alive.clear();
+		// every player needs their own input context
+		let max_players = player_spawns.len().min(self.input_context_count());
+		self.input_trackers = vec![PlayerInputTracker::default(); max_players];
+		self.touch_samples = vec![PlayerInputSample::default(); max_players];
 		for ps in player_spawns.iter() {
-			dbg!(&ps);
-		}
-
-		for ps in player_spawns.iter() {
-			dbg!(&ps);
 			match ps.data() {
 				map::ObjectData::Point { pos } => {
+					let index = self.player_ids.len();
+					if index >= max_players {
+						warn!(
+							"Ignoring player spawn {}, only {} input contexts available",
+							index, max_players
+						);
+						continue;
+					}
 					// add player ... at spawn position
 					let mut player = Player::new();
 					player.setup(self.entity_configuration_manager.get_config("player"));
-					player.set_input_context_index(0);
+					player.set_input_context_index(index as u8);
 					player.set_spawn_pos(&pos.add(&Vector2::new(64.0 + 32.0, 64.0)));
 					player.respawn();
 					let player_id = self.entity_manager.add(Box::new(player));
 
-					self.camera.follow_player_entity_id(player_id);
-					self.player_id = player_id;
-					break; // just one for now ;)
+					self.player_ids.push(player_id);
+					self.were_players_alive.push(true);
 				},
 				o => {
 					println!("Ignoring invalid object type for Player Spawn {:?}", &o);
 				},
 			}
 		}
+		match self.player_ids.as_slice() {
+			[] => warn!("World {} has no player spawn", &self.world_name),
+			[id] => self.camera.follow_player_entity_id(*id),
+			ids => self.camera.follow_player_group(ids),
+		}
 
 		let camera_starts = self
 			.world
@@ -143,35 +246,246 @@ impl Game {
 			}
 		}
 
+		// don't show the void beyond the edges of the world
+		self.camera.set_bounds(self.world.bounds().as_ref());
+
 		// :HACK:
 		//self.camera.freeze();
 
 		self.world_renderer.setup()?;
-		self.world_renderer.enable_layer(
-			"Tile Layer 1",
-			LayerId::TileMap1 as u8,
-			EffectId::Textured as u16,
-		);
-		self.world_renderer.enable_layer(
-			"terrain",
-			LayerId::TileMap1 as u8,
-			EffectId::Textured as u16,
-		);
-		/*
-		self.world_renderer.enable_layer(
-			"Tile Layer 2",
-			LayerId::TileMap2 as u8,
-			EffectId::TexturedDesaturated as u16,
-		);
-		*/
+		self.world_renderer.configure_layers(&layer_config);
 
 		Ok(())
 	}
 
-	pub fn teardown(&mut self) {}
+	// spawns the entities of the given maps, unless they are already there
+	fn spawn_map_entities(&mut self, map_indices: &[usize]) {
+		for &i in map_indices {
+			if !self.spawned_maps.contains_key(&i) {
+				let ids = self.spawn_entities_in_map(i);
+				self.spawned_maps.insert(i, ids);
+			}
+		}
+	}
+
+	// removes the entities of maps that streamed out, they spawn again when streamed back in
+	fn despawn_map_entities(&mut self, map_indices: &[usize]) {
+		for i in map_indices {
+			let ids = match self.spawned_maps.remove(i) {
+				Some(ids) => ids,
+				None => continue,
+			};
+			for id in ids.iter() {
+				self.entity_manager.remove(*id);
+			}
+			self.platform_ids.retain(|id| !ids.contains(id));
+			self.enemy_ids.retain(|id| !ids.contains(id));
+			self.checkpoint_ids.retain(|id| !ids.contains(id));
+			self.goal_ids.retain(|id| !ids.contains(id));
+			// players keep respawning at the checkpoint, it just isn't shown as active anymore
+			if let Some(id) = self.active_checkpoint_id {
+				if ids.contains(&id) {
+					self.active_checkpoint_id = None;
+				}
+			}
+		}
+	}
+
+	fn stream_maps(&mut self, frame: &Rectangle) {
+		let (loaded, unloaded) = self.world.update_streaming(&mut self.map_streamer, frame);
+		self.despawn_map_entities(&unloaded);
+		self.spawn_map_entities(&loaded);
+	}
+
+	// spawns the entities, and camera zones, of one map, and returns the ids of the entities
+	fn spawn_entities_in_map(&mut self, map_index: usize) -> Vec<EntityId> {
+		let mut ids = Vec::new();
+		let map_indices = [map_index];
+		let map_indices = map_indices.as_slice();
+
+		let obstacles =
+			self.world
+				.list_objects_in_maps_layer_for_class(map_indices, "Obstacles", "Obstacle");
+		for o in obstacles.iter() {
+			match o.data() {
+				map::ObjectData::Rectangle { rect, .. } => {
+					let obstacle = Obstacle::new(rect);
+					ids.push(self.entity_manager.add(Box::new(obstacle)));
+				},
+				o => {
+					println!("Ignoring invalid object type for Obstacle {:?}", &o);
+				},
+			}
+		}
+
+		let platforms =
+			self.world
+				.list_objects_in_maps_layer_for_class(map_indices, "Platforms", "Platform");
+		for po in platforms.iter() {
+			let speed = po.property_as_f32("speed").unwrap_or(100.0);
+			let looping = po.property_as_bool("loop").unwrap_or(false);
+			let (size, path) = match po.data() {
+				map::ObjectData::Rectangle { rect, .. } => {
+					// move between the rectangle and an optional offset
+					let offset = Vector2::new(
+						po.property_as_f32("move_x").unwrap_or(0.0),
+						po.property_as_f32("move_y").unwrap_or(0.0),
+					);
+					let start = *rect.center();
+					(*rect.size(), vec![start, start.add(&offset)])
+				},
+				map::ObjectData::Polyline { points } => {
+					let size = Vector2::new(
+						po.property_as_f32("width").unwrap_or(192.0),
+						po.property_as_f32("height").unwrap_or(32.0),
+					);
+					(size, points.clone())
+				},
+				o => {
+					println!("Ignoring invalid object type for Platform {:?}", &o);
+					continue;
+				},
+			};
+			let platform = Platform::new(po.name(), &size, path)
+				.with_speed(speed)
+				.with_looping(looping);
+			let id = self.entity_manager.add(Box::new(platform));
+			self.platform_ids.push(id);
+			ids.push(id);
+		}
+
+		let enemies =
+			self.world
+				.list_objects_in_maps_layer_for_class(map_indices, "Enemies", "Enemy");
+		for e in enemies.iter() {
+			// rectangles define the patrol range, and spawn at their bottom center
+			let (spawn_pos, patrol_rect) = match e.data() {
+				map::ObjectData::Rectangle { rect, .. } => {
+					let bottom = collision::rectangle_min(rect).y;
+					(Vector2::new(rect.center().x, bottom + 64.0), Some(rect))
+				},
+				map::ObjectData::Point { pos } => (pos.add(&Vector2::new(0.0, 64.0)), None),
+				o => {
+					println!("Ignoring invalid object type for Enemy {:?}", &o);
+					continue;
+				},
+			};
+			let mut enemy = Enemy::new(&spawn_pos);
+			if let Some(patrol_rect) = patrol_rect {
+				enemy = enemy.with_patrol_rect(patrol_rect);
+			}
+			let config = e.property("config").unwrap_or("enemy");
+			enemy.setup(self.entity_configuration_manager.get_config(config));
+			enemy.apply_object_properties(e);
+			let id = self.entity_manager.add(Box::new(enemy));
+			self.enemy_ids.push(id);
+			ids.push(id);
+		}
+
+		let checkpoints =
+			self.world
+				.list_objects_in_maps_layer_for_class(map_indices, "Player", "Checkpoint");
+		for cp in checkpoints.iter() {
+			// :HACK: spawn positions use the same offset as the PlayerSpawn in setup
+			let (rect, anchor) = match cp.data() {
+				map::ObjectData::Rectangle { rect, .. } => {
+					let bottom = collision::rectangle_min(rect).y;
+					(rect.clone(), Vector2::new(rect.center().x, bottom))
+				},
+				map::ObjectData::Point { pos } => {
+					let rect = Rectangle::default()
+						.with_size(&Vector2::new(64.0, 128.0))
+						.with_center(&pos.add(&Vector2::new(0.0, 64.0)));
+					(rect, *pos)
+				},
+				o => {
+					println!("Ignoring invalid object type for Checkpoint {:?}", &o);
+					continue;
+				},
+			};
+			let spawn_pos = anchor.add(&Vector2::new(64.0 + 32.0, 64.0));
+			let checkpoint = Checkpoint::new(cp.name(), &rect, &spawn_pos);
+			let id = self.entity_manager.add(Box::new(checkpoint));
+			self.checkpoint_ids.push(id);
+			ids.push(id);
+		}
+
+		let goals = self
+			.world
+			.list_objects_in_maps_layer_for_class(map_indices, "Player", "Goal");
+		for g in goals.iter() {
+			let rect = match g.data() {
+				map::ObjectData::Rectangle { rect, .. } => rect.clone(),
+				map::ObjectData::Point { pos } => Rectangle::default()
+					.with_size(&Vector2::new(128.0, 256.0))
+					.with_center(&pos.add(&Vector2::new(0.0, 128.0))),
+				o => {
+					println!("Ignoring invalid object type for Goal {:?}", &o);
+					continue;
+				},
+			};
+			let goal = Goal::new(g.name(), &rect);
+			let id = self.entity_manager.add(Box::new(goal));
+			self.goal_ids.push(id);
+			ids.push(id);
+		}
+
+		if !self.zoned_maps.insert(map_index) {
+			return ids;
+		}
+		let camera_rails = self
+			.world
+			.list_objects_in_layer_for_class("CameraControl", "CameraRail");
+		let camera_zones = self.world.list_objects_in_maps_layer_for_class(
+			map_indices,
+			"CameraControl",
+			"CameraZone",
+		);
+		for cz in camera_zones.iter() {
+			let rect = match cz.data() {
+				map::ObjectData::Rectangle { rect, .. } => rect,
+				o => {
+					println!("Ignoring invalid object type for Camera Zone {:?}", &o);
+					continue;
+				},
+			};
+			let mut zone = CameraZone::new(cz.name(), rect);
+			zone.zoom = cz.property_as_f32("zoom");
+			if cz.property_as_bool("clamp").unwrap_or(false) {
+				zone.bounds = Some(*rect);
+			}
+			zone.follow_mode = cz.property("follow").map(|f| f.into());
+			if let Some(blend_time) = cz.property_as_f32("blend_time") {
+				zone.blend_time = blend_time;
+			}
+			if let Some(rail) = cz.property("rail") {
+				let points = camera_rails
+					.iter()
+					.filter(|cr| cr.name() == rail)
+					.find_map(|cr| match cr.data() {
+						map::ObjectData::Polyline { points } => Some(points.clone()),
+						_ => None,
+					});
+				if points.is_none() {
+					warn!(
+						"Camera Rail >{}< for Camera Zone >{}< not found",
+						rail,
+						cz.name()
+					);
+				}
+				zone.rail = points;
+			}
+			self.camera.add_zone(zone);
+		}
+		ids
+	}
+
+	pub fn teardown(&mut self) {
+		self.entity_manager.teardown();
+	}
 
 	pub fn update(&mut self, auc: &mut AppUpdateContext) -> Vec<GameStateResponse> {
-		let response = Vec::new();
+		let mut response = Vec::new();
 		self.fixed_update_count = 0;
 
 		let wuc = match auc.wuc() {
@@ -186,8 +500,7 @@ impl Game {
 			} else {
 				&self.camera
 			};
-			let offset = active_camera.offset();
-			debug_renderer::debug_renderer_set_offset(&offset);
+			debug_draw::debug_draw_set_camera(active_camera);
 		}
 		// :HACK: we really need a better place to calculate our aspect ratio fixed frame
 		let scaling = 1024.0 / wuc.window_size.y;
@@ -199,74 +512,77 @@ impl Game {
 					self.camera.change_zoom( wuc.mouse_wheel_line_delta.y );
 				}
 		*/
-		if !self.is_paused {
-			let mut euc = EntityUpdateContext::new().with_world(&self.world);
+		if !self.is_paused && self.level_result.is_none() {
+			let mut euc = EntityUpdateContext::new()
+				.with_world(&self.world)
+				.with_camera_effects_state(self.camera.effects_state());
+			for p in self.players() {
+				if p.is_alive() {
+					euc.add_player_position(p.pos());
+				}
+			}
 
 			self.total_time += wuc.time_step;
+			self.level_time += wuc.time_step;
 
-			if wuc.was_key_pressed('[' as u8) {
+			let input_state = auc.input_state();
+			if input_state.was_pressed(InputActionSet::Debug, "toggle_fixed_camera") {
 				self.use_fixed_camera = !self.use_fixed_camera;
 			}
 
-			let mut pic = PlayerInputContext::default();
-			if let Some(p) = self.entity_manager.get_as_mut::<Player>(self.player_id) {
-				if p.is_alive() && wuc.is_key_pressed('t' as u8) {
-					// t for terminate
-					p.kill();
-				} else if wuc.is_key_pressed('r' as u8) {
-					p.respawn();
-					self.camera.thaw();
+			for id in self.player_ids.iter() {
+				if let Some(p) = self.entity_manager.get_as_mut::<Player>(*id) {
+					if p.is_alive() && input_state.is_down(InputActionSet::Debug, "kill") {
+						p.kill();
+					} else if input_state.is_down(InputActionSet::Debug, "respawn") {
+						p.respawn();
+						self.camera.thaw();
+					}
 				}
 			}
 
-			if wuc.is_key_pressed('a' as u8) {
-				pic.is_left_pressed = true;
-			}
-			if wuc.is_key_pressed('d' as u8) {
-				pic.is_right_pressed = true;
-			}
-			if wuc.is_key_pressed('w' as u8) {
-				pic.is_up_pressed = true;
-				// :HACK:
-
-				self.camera
-					.set_target_pos(&self.camera.pos().add(&Vector2::new(100.0, 0.0)));
-			}
-			if wuc.is_key_pressed('s' as u8) {
-				pic.is_down_pressed = true;
-			}
-			if wuc.is_space_pressed() {
-				pic.is_jump_pressed = true;
-			}
-			euc.add_player_input_context(pic);
+			for (index, tracker) in self.input_trackers.iter_mut().enumerate() {
+				let mut sample = PlayerInputSample::from_input_state(input_state, index as u8);
+				if let Some(touch) = self.touch_samples.get(index) {
+					sample = sample.merge(touch);
+				}
+				let pic = tracker.update(&sample, wuc.time_step);
+				if index == 0 && pic.up.is_down {
+					// :HACK:
 
-			let mut pic = PlayerInputContext::default();
-			if wuc.is_key_pressed('j' as u8) {
-				pic.is_left_pressed = true;
-			}
-			if wuc.is_key_pressed('l' as u8) {
-				pic.is_right_pressed = true;
-			}
-			if wuc.is_key_pressed('i' as u8) {
-				pic.is_up_pressed = true;
-			}
-			if wuc.is_key_pressed('k' as u8) {
-				pic.is_down_pressed = true;
+					self.camera
+						.set_target_pos(&self.camera.pos().add(&Vector2::new(100.0, 0.0)));
+				}
+				euc.add_player_input_context(pic);
 			}
-			euc.add_player_input_context(pic);
 
 			euc = euc.set_time_step(wuc.time_step);
 
 			for e in self.entity_manager.iter_mut() {
 				e.update(&mut euc);
 			}
+			for effect in euc.take_camera_effects() {
+				self.camera.add_effect(effect);
+			}
+
+			self.update_player_life_cycle();
+			if self.update_goals() {
+				response.push(GameStateResponse::new("LevelComplete"));
+			}
 
 			self.camera.update(wuc.time_step, &self.entity_manager);
 
 			self.fixed_camera
 				.update(wuc.time_step, &self.entity_manager);
+		}
 
-			self.world_renderer.update(wuc.time_step);
+		self.world_renderer
+			.set_tile_animations_paused(self.is_paused || self.level_result.is_some());
+		self.world_renderer.update(wuc.time_step);
+
+		if self.world.is_streamed() {
+			let frame = self.camera.frame();
+			self.stream_maps(&frame);
 		}
 
 		if let Some(data) = &self.data {
@@ -277,6 +593,7 @@ impl Game {
 						.and_then(|mut game| {
 							// could probably try_write here
 							game.is_paused = self.is_paused;
+							game.level_result = self.level_result.clone();
 							Ok(())
 						})
 						.unwrap();
@@ -290,15 +607,238 @@ impl Game {
 		response
 	}
 
+	fn dynamic_colliders(&self) -> Vec<DynamicCollider> {
+		self.platform_ids
+			.iter()
+			.filter_map(|id| {
+				self.entity_manager
+					.get_as::<Platform>(*id)
+					.map(|p| DynamicCollider {
+						entity_id: *id,
+						rect:      p.rect(),
+						movement:  *p.movement(),
+					})
+			})
+			.collect()
+	}
+
+	// one per player action map in the input config
+	fn input_context_count(&self) -> usize {
+		let count = self
+			.data
+			.as_ref()
+			.and_then(|data| data.as_any().downcast_ref::<RarData>())
+			.and_then(|data| {
+				data.input
+					.read()
+					.ok()
+					.map(|input| input.config.players.len())
+			})
+			.unwrap_or(0);
+		// the touch controls can always drive the first player
+		count.max(1)
+	}
+
+	fn players(&self) -> impl Iterator<Item = &Player> {
+		self.player_ids
+			.iter()
+			.filter_map(|id| self.entity_manager.get_as::<Player>(*id))
+	}
+
+	fn living_player_rects(&self) -> Vec<(EntityId, Rectangle)> {
+		self.player_ids
+			.iter()
+			.filter_map(|id| {
+				self.entity_manager
+					.get_as::<Player>(*id)
+					.filter(|p| p.is_alive())
+					.map(|p| (*id, p.bounding_rect()))
+			})
+			.collect()
+	}
+
+	fn update_checkpoints(&mut self) {
+		let rects = self.living_player_rects();
+
+		// checkpoints are shared, so any player can activate them
+		let mut touched = None;
+		for id in self.checkpoint_ids.iter() {
+			if Some(*id) == self.active_checkpoint_id {
+				continue;
+			}
+			if let Some(cp) = self.entity_manager.get_as::<Checkpoint>(*id) {
+				if rects
+					.iter()
+					.any(|(_, r)| collision::rectangles_overlap(cp.rect(), r))
+				{
+					touched = Some(*id);
+					break;
+				}
+			}
+		}
+
+		if let Some(id) = touched {
+			self.activate_checkpoint(id);
+		}
+	}
+
+	// moves the respawn point of all players to the checkpoint
+	fn activate_checkpoint(&mut self, id: EntityId) {
+		if let Some(old_id) = self.active_checkpoint_id {
+			if let Some(cp) = self.entity_manager.get_as_mut::<Checkpoint>(old_id) {
+				cp.deactivate();
+			}
+		}
+		let mut spawn_pos = None;
+		if let Some(cp) = self.entity_manager.get_as_mut::<Checkpoint>(id) {
+			cp.activate();
+			spawn_pos = Some(*cp.spawn_pos());
+		}
+		if let Some(spawn_pos) = spawn_pos {
+			for pid in self.player_ids.iter() {
+				if let Some(p) = self.entity_manager.get_as_mut::<Player>(*pid) {
+					p.set_spawn_pos(&spawn_pos);
+				}
+			}
+			self.active_checkpoint_id = Some(id);
+		}
+	}
+
+	// returns true when the level was completed during this update
+	fn update_goals(&mut self) -> bool {
+		let rects = self.living_player_rects();
+
+		// the first player to reach a goal completes the level for everybody
+		let reached_goal = self.goal_ids.iter().any(|id| {
+			self.entity_manager.get_as::<Goal>(*id).map_or(false, |g| {
+				rects
+					.iter()
+					.any(|(_, r)| collision::rectangles_overlap(g.rect(), r))
+			})
+		});
+		if !reached_goal {
+			return false;
+		}
+
+		let result = LevelResult {
+			time:   self.level_time,
+			deaths: self.death_count,
+		};
+		debug!("Level complete {:?}", &result);
+		self.level_result = Some(result);
+		true
+	}
+
+	fn update_enemies(&mut self) {
+		for i in 0..self.player_ids.len() {
+			self.update_enemies_for_player(self.player_ids[i]);
+		}
+	}
+
+	fn update_enemies_for_player(&mut self, player_id: EntityId) {
+		let (r, pos, speed) = match self.entity_manager.get_as::<Player>(player_id) {
+			Some(p) if p.is_alive() => (p.bounding_rect(), *p.pos(), *p.speed()),
+			_ => return,
+		};
+
+		let mut stomped = false;
+		let mut killed = false;
+		for id in self.enemy_ids.iter() {
+			if let Some(e) = self.entity_manager.get_as_mut::<Enemy>(*id) {
+				if !e.is_alive() || !collision::rectangles_overlap(&e.bounding_rect(), &r) {
+					continue;
+				}
+				// falling onto an enemy from above stomps it, everything else hurts
+				if e.is_stompable() && speed.y < 0.0 && pos.y > e.pos().y {
+					e.kill();
+					stomped = true;
+				} else {
+					killed = true;
+				}
+			}
+		}
+
+		if let Some(p) = self.entity_manager.get_as_mut::<Player>(player_id) {
+			if killed {
+				p.kill();
+			} else if stomped {
+				p.bounce();
+			}
+		}
+	}
+
+	fn update_player_life_cycle(&mut self) {
+		self.update_checkpoints();
+		self.update_enemies();
+
+		let obstacles: Vec<Rectangle> = self
+			.entity_manager
+			.iter()
+			.filter_map(|e| e.as_any().downcast_ref::<Obstacle>())
+			.map(|o| o.rect().clone())
+			.collect();
+
+		let was_anybody_alive = self.were_players_alive.iter().any(|a| *a);
+		let mut respawn_pos = None;
+		for (i, id) in self.player_ids.iter().enumerate() {
+			if let Some(p) = self.entity_manager.get_as_mut::<Player>(*id) {
+				if p.is_alive() {
+					let r = p.bounding_rect();
+					if obstacles
+						.iter()
+						.any(|o| collision::rectangles_overlap(o, &r))
+					{
+						p.kill();
+					}
+				}
+
+				let is_player_alive = p.is_alive();
+				if self.were_players_alive[i] && !is_player_alive {
+					self.death_count += 1;
+				} else if !self.were_players_alive[i] && is_player_alive {
+					respawn_pos = Some(*p.pos());
+				}
+				self.were_players_alive[i] = is_player_alive;
+			}
+		}
+
+		let is_anybody_alive = self.were_players_alive.iter().any(|a| *a);
+		if was_anybody_alive && !is_anybody_alive {
+			// keep looking at the spot where the last player died
+			self.camera.freeze();
+		} else if !was_anybody_alive && is_anybody_alive {
+			// respawned, so jump straight back to the player
+			if let Some(pos) = respawn_pos {
+				self.camera.set_pos(&pos);
+				self.camera.set_target_pos(&pos);
+			}
+			self.camera.thaw();
+		}
+	}
+
 	pub fn fixed_update(&mut self, time_step: f64) {
-		if !self.is_paused {
+		if !self.is_paused && self.level_result.is_none() {
 			let euc = EntityUpdateContext::new()
 				.set_time_step(time_step)
 				.with_fixed_update_count(self.fixed_update_count)
-				.with_world(&self.world);
+				.with_world(&self.world)
+				.with_camera_effects_state(self.camera.effects_state());
+
+			// move platforms first, so everybody else collides with their new position
+			for id in self.platform_ids.iter() {
+				if let Some(p) = self.entity_manager.get_as_mut::<Platform>(*id) {
+					p.fixed_update(&euc);
+				}
+			}
+			let euc = euc.with_dynamic_colliders(self.dynamic_colliders());
 
 			for e in self.entity_manager.iter_mut() {
-				e.fixed_update(&euc);
+				if e.entity_type() != EntityType::Platform {
+					e.fixed_update(&euc);
+				}
+			}
+			for effect in euc.take_camera_effects() {
+				self.camera.add_effect(effect);
 			}
 		}
 		self.fixed_update_count += 1;
@@ -316,6 +856,24 @@ impl Game {
 
 		self.world_renderer
 			.render(renderer, active_camera, &self.world);
+
+		self.render_letterbox(renderer, active_camera);
+	}
+
+	fn render_letterbox(&self, renderer: &mut Renderer, camera: &Camera) {
+		let amount = camera.letterbox();
+		if amount <= 0.0 {
+			return;
+		}
+		let frame_size = camera.frame_size();
+		let size = Vector2::new(frame_size.x, frame_size.y * amount);
+		let y = 0.5 * (frame_size.y - size.y);
+
+		renderer.use_layer(LayerId::Overlay as u8);
+		renderer.use_effect(EffectId::Colored as u16);
+		renderer.set_color(&Color::black());
+		renderer.render_quad(&Vector2::new(0.0, y), &size);
+		renderer.render_quad(&Vector2::new(0.0, -y), &size);
 	}
 	pub fn render_debug(&mut self, debug_renderer: &mut DebugRenderer) {
 		let r = if self.use_fixed_camera {
@@ -341,11 +899,9 @@ impl Game {
 		} else {
 			&self.camera
 		};
-		let offset = active_camera.offset();
-
 		// camera info
-		let cam_pos = self.camera.pos().add(&offset);
-		debug_renderer.add_circle(&cam_pos, r, 3.0, &Color::white());
+		let cam_pos = active_camera.world_to_screen(self.camera.pos());
+		debug_renderer.add_circle(&cam_pos, r * active_camera.scale(), 3.0, &Color::white());
 		//		let cam_pos_text = format!("{:+07.1} / {:+07.1}", self.camera.pos().x, self.camera.pos().y );
 		let cam_pos_text = format!(
 			"{:+07.0}/{:+07.0}",
@@ -361,17 +917,17 @@ impl Game {
 			&Color::white(),
 		);
 
-		for wm in self.world.maps() {
-			if let Some(m) = wm.map() {
-				for l in m.layers() {
-					self.world_renderer.render_debug_layer_objects(
-						debug_renderer,
-						active_camera,
-						l,
-					);
-				}
+		for m in self.world.loaded_maps() {
+			for l in m.layers() {
+				self.world_renderer
+					.render_debug_layer_objects(debug_renderer, active_camera, l);
 			}
 		}
+		if let Some(zone) = self.camera.active_zone() {
+			let rect = active_camera.world_rect_to_screen(&zone.rect);
+			debug_renderer.add_rectangle(&rect, 9.0, &Color::from_rgba(0.3, 0.9, 0.9, 0.8));
+		}
+
 		let frame = self.camera.frame();
 		let cam_frame_text = format!(
 			"{:+07.0}/{:+07.0} {:4.0}X{:4.0}",
@@ -388,12 +944,8 @@ impl Game {
 			&Color::white(),
 		);
 
-		// :HACK: we always want to see the moving camera's frame!
-		let frame = if self.use_fixed_camera {
-			frame
-		} else {
-			frame.with_offset(&offset)
-		};
+		// we always want to see the moving camera's frame, even through the fixed camera
+		let frame = active_camera.world_rect_to_screen(&frame);
 		debug_renderer.add_rectangle(&frame, 7.0, &Color::from_rgba(0.9, 0.8, 0.6, 0.8));
 		let cam_frame_center = frame.center();
 		let screen_center = Vector2::zero();
@@ -401,3 +953,86 @@ impl Game {
 		debug_renderer.add_line(&screen_center, cam_frame_center, 3.0, &Color::white());
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::rar::map::MapSources;
+
+	fn load_two_maps() -> anyhow::Result<World> {
+		let mut world = World::new();
+		world.load_from_str(
+			include_str!("../../fixtures/two_maps/two_maps.world"),
+			&mut |name| {
+				let tmj = match name {
+					"left" => include_str!("../../fixtures/two_maps/left.tmj"),
+					"right" => include_str!("../../fixtures/two_maps/right.tmj"),
+					_ => anyhow::bail!("Unknown map {}", name),
+				};
+				MapSources::load_from_str(name, tmj.to_string(), &mut |_| {
+					Ok(include_str!("../../fixtures/two_maps/blocks.tsj").to_string())
+				})
+			},
+		)?;
+		world.load_all_maps()?;
+		Ok(world)
+	}
+
+	fn frame_at(x: f32, y: f32) -> Rectangle {
+		Rectangle::default()
+			.with_size(&Vector2::new(64.0, 64.0))
+			.with_center(&Vector2::new(x, y))
+	}
+
+	#[test]
+	fn map_entities_follow_their_map_out_and_back_in() -> anyhow::Result<()> {
+		let mut game = Game::default();
+		game.world = load_two_maps()?;
+		game.map_streamer = MapStreamer::default().with_margins(&Vector2::zero(), &Vector2::zero());
+		let loaded_maps = game.world.loaded_map_indices();
+		game.spawn_map_entities(&loaded_maps);
+		// the goal lives in the right map
+		assert_eq!(game.goal_ids.len(), 1);
+		let goal_id = game.goal_ids[0];
+
+		// only looking at the left map streams the right one out
+		game.stream_maps(&frame_at(32.0, -32.0));
+		assert!(!game.world.maps()[1].is_loaded());
+		assert!(game.goal_ids.is_empty());
+		assert!(game.entity_manager.get(goal_id).is_none());
+		assert!(!game.spawned_maps.contains_key(&1));
+
+		// and looking at it again brings it back, with a fresh goal
+		for _ in 0..500 {
+			game.stream_maps(&frame_at(192.0, -128.0));
+			if game.world.maps()[1].is_loaded() {
+				break;
+			}
+			std::thread::sleep(std::time::Duration::from_millis(10));
+		}
+		assert!(game.world.maps()[1].is_loaded());
+		assert_eq!(game.goal_ids.len(), 1);
+		assert_ne!(game.goal_ids[0], goal_id);
+		assert!(game.entity_manager.get(game.goal_ids[0]).is_some());
+		Ok(())
+	}
+
+	#[test]
+	fn active_checkpoint_survives_pause_and_resume() {
+		let mut game = Game::default();
+		let rect = Rectangle::default()
+			.with_size(&Vector2::new(64.0, 128.0))
+			.with_center(&Vector2::zero());
+		let checkpoint = Checkpoint::new("cp", &rect, &Vector2::zero());
+		let id = game.entity_manager.add(Box::new(checkpoint));
+		game.checkpoint_ids.push(id);
+		game.activate_checkpoint(id);
+
+		// pausing only stops the updates, the level stays set up
+		assert!(game.toggle_pause());
+		assert!(!game.toggle_pause());
+		assert_eq!(game.active_checkpoint_id, Some(id));
+		let cp = game.entity_manager.get_as::<Checkpoint>(id).unwrap();
+		assert!(cp.is_active());
+	}
+}
@@ -9,9 +9,12 @@ use oml_game::system::Data;
 use oml_game::system::System;
 use tracing::*;
 
+use crate::rar::data::RarData;
 use crate::rar::dialogs::IngamePauseDialog;
+use crate::rar::dialogs::LevelCompleteDialog;
 use crate::rar::dialogs::SettingsDialog;
 use crate::rar::game_state::GameStateResponse;
+use crate::rar::input_actions::InputActionSet;
 use crate::rar::AppUpdateContext;
 //use oml_game::window::WindowUpdateContext;
 use crate::rar::AudioMessage;
@@ -19,10 +22,12 @@ use crate::rar::Game;
 use crate::rar::GameState;
 use crate::rar::RarApp;
 use crate::ui::UiElement;
+use crate::ui::UiElementContainer;
 use crate::ui::UiElementFactory;
 use crate::ui::UiEventResponse;
 use crate::ui::UiEventResponseButtonClicked;
 use crate::ui::UiEventResponseGenericMessage;
+use crate::ui::UiEventResponseVirtualInput;
 use crate::ui::UiSystem;
 
 #[derive(Debug)]
@@ -71,8 +76,7 @@ impl GameStateGame {
 			if let Some(gme) = ev.as_any().downcast_ref::<UiEventResponseGenericMessage>() {
 				match gme.message.as_str() {
 					"playpause/toggle" => {
-						let is_paused = self.toggle_game_pause();
-						if !is_paused {
+						if self.toggle_game_pause() == Some(false) {
 							self.ui_system.fade_out_child_by_tag("settings_dialog", 1.0);
 						}
 					},
@@ -83,6 +87,14 @@ impl GameStateGame {
 						let r = GameStateResponse::new("GotoMainMenu");
 						responses.push(r);
 					},
+					"level_complete/back" => {
+						let r = GameStateResponse::new("GotoWorldSelection");
+						responses.push(r);
+					},
+					"level_complete/restart" => {
+						let r = GameStateResponse::new("RestartGame");
+						responses.push(r);
+					},
 					_ => {
 						warn!("Unhandled generic message {}", &gme.message);
 					},
@@ -104,15 +116,49 @@ impl GameStateGame {
 						}
 					},
 					o => {
-						println!("Unhandled button click from {}", o);
+						if !self.start_rebinding(o) {
+							println!("Unhandled button click from {}", o);
+						}
 					},
 				}
+			} else if let Some(vi) = ev.as_any().downcast_ref::<UiEventResponseVirtualInput>() {
+				if !self.apply_touch_input(vi) {
+					warn!("Unhandled virtual input from {}", &vi.name);
+				}
+			}
+		}
+	}
+
+	// names are "touch/<player index>/<control>", e.g. "touch/0/stick"
+	fn apply_touch_input(&mut self, vi: &UiEventResponseVirtualInput) -> bool {
+		let mut parts = vi.name.split('/');
+		match (parts.next(), parts.next(), parts.next()) {
+			(Some("touch"), Some(index), Some(control)) => match index.parse::<usize>() {
+				Ok(index) => self
+					.game
+					.apply_touch_input(index, control, &vi.axis, vi.is_down),
+				Err(_) => false,
+			},
+			_ => false,
+		}
+	}
+
+	fn start_rebinding(&self, button_name: &str) -> bool {
+		if let Some(data) = &self.data {
+			if let Some(data) = data.as_any().downcast_ref::<RarData>() {
+				let set = SettingsDialog::REBINDABLE_SET;
+				return data.start_rebinding_from_button(button_name, set);
 			}
 		}
+		false
 	}
 
-	fn toggle_game_pause(&mut self) -> bool {
-		self.game.toggle_pause()
+	// returns the new pause state, or None while the level result is shown
+	fn toggle_game_pause(&mut self) -> Option<bool> {
+		if self.game.level_result().is_some() {
+			return None;
+		}
+		Some(self.game.toggle_pause())
 	}
 }
 
@@ -129,7 +175,8 @@ impl GameState for GameStateGame {
 			&Vector2::new(-1.0, 1.0),
 			IngamePauseDialog::new(system, &self.ui_element_factory)
 				.containerize()
-				.with_name("Ingame Pause Dialog"),
+				.with_name("Ingame Pause Dialog")
+				.with_tag("ingame_pause_dialog"),
 		);
 
 		self.ui_system.add_child(
@@ -141,6 +188,26 @@ impl GameState for GameStateGame {
 				.with_fade_out(0.0),
 		);
 
+		self.ui_system.add_child(
+			&Vector2::new(0.0, 0.0),
+			LevelCompleteDialog::new(system, &self.ui_element_factory)
+				.containerize()
+				.with_name("Level Complete Dialog")
+				.with_tag("level_complete_dialog")
+				.with_fade_out(0.0),
+		);
+
+		for (gravity, name) in [
+			(Vector2::new(-1.0, -1.0), "touch_controls_left"),
+			(Vector2::new(1.0, -1.0), "touch_controls_right"),
+		] {
+			if let Some(touch_controls) =
+				UiElementContainer::from_config_asset(system, &self.ui_element_factory, name)
+			{
+				self.ui_system.add_child(&gravity, touch_controls);
+			}
+		}
+
 		self.ui_system.layout();
 
 		Ok(())
@@ -167,14 +234,33 @@ impl GameState for GameStateGame {
 	fn update(&mut self, auc: &mut AppUpdateContext) -> Vec<GameStateResponse> {
 		let mut response = Vec::new();
 
-		let _gr = self.game.update(auc);
+		for r in self.game.update(auc) {
+			match r.name() {
+				"LevelComplete" => {
+					self.ui_system
+						.fade_out_child_by_tag("ingame_pause_dialog", 1.0);
+					self.ui_system.fade_out_child_by_tag("settings_dialog", 1.0);
+					self.ui_system
+						.fade_in_child_by_tag("level_complete_dialog", 1.0);
+				},
+				_ => response.push(r),
+			}
+		}
 
 		self.update_ui_system(auc, &mut response);
 		let wuc = match auc.wuc() {
 			Some(wuc) => wuc,
 			None => return Vec::new(),
 		};
-		if wuc.was_key_pressed('p' as u8) {
+		if let Some(data) = &self.data {
+			if let Some(data) = data.as_any().downcast_ref::<RarData>() {
+				data.update_rebinding(&wuc);
+			}
+		}
+		if auc
+			.input_state()
+			.was_pressed(InputActionSet::Gameplay, "pause")
+		{
 			//self.camera.punch(5.0);
 			self.toggle_game_pause();
 		}
@@ -1,13 +1,16 @@
 use core::any::Any;
 use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
 
 use oml_game::math::Matrix32;
 use oml_game::math::Vector2;
 use oml_game::renderer::debug_renderer::DebugRenderer;
 use oml_game::renderer::Renderer;
+use oml_game::system::Data;
 use oml_game::system::System;
 use tracing::*;
 
+use crate::rar::data::RarData;
 use crate::rar::dialogs::SettingsDialog;
 use crate::rar::effect_ids::EffectId;
 use crate::rar::game_state::GameStateResponse;
@@ -24,6 +27,7 @@ pub struct GameStateSettings {
 	ui_system:               UiSystem,
 	event_response_sender:   Sender<Box<dyn UiEventResponse>>,
 	event_response_receiver: Receiver<Box<dyn UiEventResponse>>,
+	data:                    Option<Arc<dyn Data>>,
 }
 
 impl Default for GameStateSettings {
@@ -33,6 +37,7 @@ impl Default for GameStateSettings {
 			ui_system:               UiSystem::default(),
 			event_response_sender:   tx,
 			event_response_receiver: rx,
+			data:                    None,
 		}
 	}
 }
@@ -43,6 +48,12 @@ impl GameStateSettings {
 			..Default::default()
 		}
 	}
+
+	fn rar_data(&self) -> Option<&RarData> {
+		self.data
+			.as_ref()
+			.and_then(|data| data.as_any().downcast_ref::<RarData>())
+	}
 }
 
 impl GameState for GameStateSettings {
@@ -56,6 +67,7 @@ impl GameState for GameStateSettings {
 		self
 	}
 	fn setup(&mut self, system: &mut System) -> anyhow::Result<()> {
+		self.data = system.data().as_ref().map(|data| Arc::clone(data));
 		self.ui_system
 			.setup("Settings", system, self.event_response_sender.clone())?;
 
@@ -99,6 +111,9 @@ impl GameState for GameStateSettings {
 		let mut responses = Vec::new();
 
 		self.ui_system.update(auc);
+		if let (Some(data), Some(wuc)) = (self.rar_data(), auc.wuc()) {
+			data.update_rebinding(&wuc);
+		}
 		// :TODO:
 		for ev in self.event_response_receiver.try_recv() {
 			debug!("{:?}", &ev);
@@ -123,8 +138,14 @@ impl GameState for GameStateSettings {
 								let _ = sound_tx.send(AudioMessage::ToggleSound);
 							}
 						},
-						_ => {
-							println!("Unhandled button click from {}", &e.button_name);
+						o => {
+							let set = SettingsDialog::REBINDABLE_SET;
+							let handled = self
+								.rar_data()
+								.map_or(false, |data| data.start_rebinding_from_button(o, set));
+							if !handled {
+								println!("Unhandled button click from {}", o);
+							}
 						},
 					}
 				},
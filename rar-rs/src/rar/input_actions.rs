@@ -0,0 +1,306 @@
+use std::collections::{BTreeMap, HashSet};
+
+use oml_game::system::System;
+use oml_game::window::WindowUpdateContext;
+use serde::{Deserialize, Serialize};
+use tracing::*;
+
+/// A single physical input an action can be bound to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum InputBinding {
+	Key(u8),
+	Space,
+	FunctionKey(u8),
+}
+
+impl TryFrom<String> for InputBinding {
+	type Error = String;
+
+	fn try_from(s: String) -> Result<Self, Self::Error> {
+		InputBinding::try_from(s.as_str())
+	}
+}
+
+impl TryFrom<&str> for InputBinding {
+	type Error = String;
+
+	fn try_from(s: &str) -> Result<Self, Self::Error> {
+		if s == "space" {
+			return Ok(InputBinding::Space);
+		}
+		if s.len() > 1 && s.starts_with('f') {
+			if let Ok(n) = s[1..].parse::<u8>() {
+				if (1..=12).contains(&n) {
+					return Ok(InputBinding::FunctionKey(n));
+				}
+			}
+		}
+		match s.as_bytes() {
+			[k] if k.is_ascii_graphic() => Ok(InputBinding::Key(*k)),
+			_ => Err(format!("Invalid input binding >{}<", s)),
+		}
+	}
+}
+
+impl From<InputBinding> for String {
+	fn from(b: InputBinding) -> Self {
+		match b {
+			InputBinding::Key(k) => (k as char).to_string(),
+			InputBinding::Space => "space".to_string(),
+			InputBinding::FunctionKey(n) => format!("f{}", n),
+		}
+	}
+}
+
+impl std::fmt::Display for InputBinding {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", String::from(*self))
+	}
+}
+
+impl InputBinding {
+	/// The first binding pressed during this frame, e.g. when rebinding an action.
+	pub fn find_pressed(wuc: &WindowUpdateContext) -> Option<Self> {
+		if wuc.is_space_pressed() {
+			return Some(InputBinding::Space);
+		}
+		if let Some(n) = (1..=12).find(|n| wuc.was_function_key_pressed(*n)) {
+			return Some(InputBinding::FunctionKey(n));
+		}
+		(b'!'..=b'~')
+			.find(|k| wuc.was_key_pressed(*k))
+			.map(InputBinding::Key)
+	}
+}
+
+/// Anything that can tell us if a binding is currently held down.
+pub trait InputSource {
+	fn is_binding_down(&self, binding: &InputBinding) -> bool;
+}
+
+impl InputSource for WindowUpdateContext {
+	fn is_binding_down(&self, binding: &InputBinding) -> bool {
+		match binding {
+			InputBinding::Key(k) => self.is_key_pressed(*k),
+			InputBinding::Space => self.is_space_pressed(),
+			// we only get the press for function keys
+			InputBinding::FunctionKey(n) => self.was_function_key_pressed(*n),
+		}
+	}
+}
+
+/// Named actions, each with any number of bindings.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct InputActionMap {
+	actions: BTreeMap<String, Vec<InputBinding>>,
+}
+
+impl InputActionMap {
+	pub fn bindings(&self, action: &str) -> &[InputBinding] {
+		self.actions.get(action).map_or(&[], |b| b.as_slice())
+	}
+
+	pub fn set_bindings(&mut self, action: &str, bindings: Vec<InputBinding>) {
+		self.actions.insert(action.to_string(), bindings);
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (&String, &Vec<InputBinding>)> {
+		self.actions.iter()
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum InputActionSet {
+	Player(u8),
+	Gameplay,
+	Debug,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct InputConfig {
+	/// one action map per local player
+	#[serde(default)]
+	pub players:  Vec<InputActionMap>,
+	#[serde(default)]
+	pub gameplay: InputActionMap,
+	#[serde(default)]
+	pub debug:    InputActionMap,
+}
+
+impl InputConfig {
+	pub fn from_config_asset(system: &mut System, name: &str) -> Option<Self> {
+		let dfs = system.default_filesystem_mut();
+		let name_yaml = format!("{}.input_config.yaml", &name);
+		if dfs.exists(&name_yaml) {
+			let mut f = dfs.open(&name_yaml);
+			let yaml = f.read_as_string();
+			match Self::from_yaml(&yaml) {
+				Ok(config) => Some(config),
+				Err(e) => {
+					warn!("Failed parsing {}: {}", &name_yaml, e);
+					None
+				},
+			}
+		} else {
+			None
+		}
+	}
+
+	pub fn from_yaml(yaml: &str) -> Result<Self, serde_yaml::Error> {
+		serde_yaml::from_str(yaml)
+	}
+
+	pub fn to_yaml(&self) -> Result<String, serde_yaml::Error> {
+		serde_yaml::to_string(self)
+	}
+
+	pub fn action_map(&self, set: InputActionSet) -> Option<&InputActionMap> {
+		match set {
+			InputActionSet::Player(i) => self.players.get(i as usize),
+			InputActionSet::Gameplay => Some(&self.gameplay),
+			InputActionSet::Debug => Some(&self.debug),
+		}
+	}
+
+	pub fn action_map_mut(&mut self, set: InputActionSet) -> Option<&mut InputActionMap> {
+		match set {
+			InputActionSet::Player(i) => self.players.get_mut(i as usize),
+			InputActionSet::Gameplay => Some(&mut self.gameplay),
+			InputActionSet::Debug => Some(&mut self.debug),
+		}
+	}
+
+	fn action_maps(&self) -> impl Iterator<Item = (InputActionSet, &InputActionMap)> {
+		self.players
+			.iter()
+			.enumerate()
+			.map(|(i, m)| (InputActionSet::Player(i as u8), m))
+			.chain([
+				(InputActionSet::Gameplay, &self.gameplay),
+				(InputActionSet::Debug, &self.debug),
+			])
+	}
+
+	/// Evaluates all actions, `previous` is needed to detect presses.
+	pub fn evaluate(
+		&self,
+		source: &dyn InputSource,
+		previous: &InputActionState,
+	) -> InputActionState {
+		let mut state = InputActionState::default();
+		for (set, map) in self.action_maps() {
+			for (action, bindings) in map.iter() {
+				if bindings.iter().any(|b| source.is_binding_down(b)) {
+					let key = (set, action.clone());
+					if !previous.down.contains(&key) {
+						state.pressed.insert(key.clone());
+					}
+					state.down.insert(key);
+				}
+			}
+		}
+		state
+	}
+}
+
+/// The state of all actions for one frame.
+#[derive(Debug, Default, Clone)]
+pub struct InputActionState {
+	down:    HashSet<(InputActionSet, String)>,
+	pressed: HashSet<(InputActionSet, String)>,
+}
+
+impl InputActionState {
+	pub fn is_down(&self, set: InputActionSet, action: &str) -> bool {
+		self.down.contains(&(set, action.to_string()))
+	}
+
+	/// True only in the frame the action went down.
+	pub fn was_pressed(&self, set: InputActionSet, action: &str) -> bool {
+		self.pressed.contains(&(set, action.to_string()))
+	}
+
+	/// Keeps track of what is held down, without triggering anything.
+	pub fn without_presses(mut self) -> Self {
+		self.pressed.clear();
+		self
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	struct MockSource {
+		down: Vec<InputBinding>,
+	}
+
+	impl InputSource for MockSource {
+		fn is_binding_down(&self, binding: &InputBinding) -> bool {
+			self.down.contains(binding)
+		}
+	}
+
+	const YAML: &str = r#"
+players:
+  - move_left: [ "a" ]
+    jump:      [ "space", "w" ]
+gameplay:
+  pause: [ "p" ]
+debug:
+  reload: [ "f5" ]
+"#;
+
+	#[test]
+	fn bindings_round_trip() {
+		for s in ["a", "[", "\\", "space", "f1", "f12"] {
+			let b = InputBinding::try_from(s).unwrap();
+			assert_eq!(String::from(b), s);
+		}
+		assert!(InputBinding::try_from("f13").is_err());
+		assert!(InputBinding::try_from("ab").is_err());
+		assert!(InputBinding::try_from("").is_err());
+	}
+
+	#[test]
+	fn config_loads_from_yaml() {
+		let config = InputConfig::from_yaml(YAML).unwrap();
+		let player = config.action_map(InputActionSet::Player(0)).unwrap();
+		assert_eq!(
+			player.bindings("jump"),
+			&[InputBinding::Space, InputBinding::Key(b'w')]
+		);
+		assert!(player.bindings("unknown").is_empty());
+		assert!(config.action_map(InputActionSet::Player(1)).is_none());
+		assert_eq!(
+			config.debug.bindings("reload"),
+			&[InputBinding::FunctionKey(5)]
+		);
+
+		let yaml = config.to_yaml().unwrap();
+		let again = InputConfig::from_yaml(&yaml).unwrap();
+		assert_eq!(again.gameplay.bindings("pause"), &[InputBinding::Key(b'p')]);
+	}
+
+	#[test]
+	fn pressed_is_only_set_on_the_first_frame() {
+		let config = InputConfig::from_yaml(YAML).unwrap();
+		let player = InputActionSet::Player(0);
+		let source = MockSource {
+			down: vec![InputBinding::Key(b'w')],
+		};
+
+		let first = config.evaluate(&source, &InputActionState::default());
+		assert!(first.is_down(player, "jump"));
+		assert!(first.was_pressed(player, "jump"));
+		assert!(!first.is_down(player, "move_left"));
+		// same action name in another set is separate
+		assert!(!first.is_down(InputActionSet::Gameplay, "jump"));
+
+		let second = config.evaluate(&source, &first);
+		assert!(second.is_down(player, "jump"));
+		assert!(!second.was_pressed(player, "jump"));
+	}
+}
@@ -0,0 +1,205 @@
+use oml_game::math::Vector2;
+use oml_game::renderer::Color;
+use oml_game::system::System;
+use serde::{Deserialize, Serialize};
+use tracing::*;
+
+use crate::rar::effect_ids::EffectId;
+use crate::rar::layer_ids::LayerId;
+use crate::rar::map;
+use crate::rar::MapStreamer;
+
+// Which tile layers of a world are rendered, and how.
+// Maps can override this per layer via Tiled properties, see `WorldRenderer`.
+
+fn default_layer_id() -> LayerId {
+	LayerId::TileMap1
+}
+
+fn default_effect_id() -> EffectId {
+	EffectId::Textured
+}
+
+fn default_opacity() -> f32 {
+	1.0
+}
+
+fn default_load_margin() -> [f32; 2] {
+	[512.0, 512.0]
+}
+
+fn default_unload_margin() -> [f32; 2] {
+	[1024.0, 1024.0]
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayerConfig {
+	/// the path for layers in groups, e.g. "background/far"
+	pub name:      String,
+	#[serde(default = "default_layer_id")]
+	pub layer:     LayerId,
+	#[serde(default = "default_effect_id")]
+	pub effect:    EffectId,
+	/// lower is drawn first, layers with the same order keep their order from the map
+	#[serde(default)]
+	pub order:     i32,
+	#[serde(default = "default_opacity")]
+	pub opacity:   f32,
+	/// "#AARRGGBB" or "#RRGGBB", needs a colored effect to be visible
+	#[serde(default)]
+	pub tint:      Option<String>,
+	/// generate the collider, and hazard, layers from this one
+	#[serde(default)]
+	pub colliders: bool,
+}
+
+impl LayerConfig {
+	pub fn new(name: &str, layer: LayerId, effect: EffectId) -> Self {
+		Self {
+			name: name.to_owned(),
+			layer,
+			effect,
+			order: 0,
+			opacity: default_opacity(),
+			tint: None,
+			colliders: false,
+		}
+	}
+
+	pub fn with_colliders(mut self) -> Self {
+		self.colliders = true;
+		self
+	}
+
+	/// The tint with the opacity applied.
+	pub fn color(&self) -> Color {
+		let mut color = match &self.tint {
+			Some(tint) => map::parse_color(tint).unwrap_or_else(|| {
+				warn!("Invalid tint {:?} for layer {}", tint, &self.name);
+				Color::white()
+			}),
+			None => Color::white(),
+		};
+		color.a *= self.opacity;
+		color
+	}
+}
+
+/// Only used by streamed worlds, see `MapStreamer`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamingConfig {
+	#[serde(default = "default_load_margin")]
+	pub load_margin:   [f32; 2],
+	#[serde(default = "default_unload_margin")]
+	pub unload_margin: [f32; 2],
+}
+
+impl Default for StreamingConfig {
+	fn default() -> Self {
+		Self {
+			load_margin:   default_load_margin(),
+			unload_margin: default_unload_margin(),
+		}
+	}
+}
+
+impl StreamingConfig {
+	pub fn map_streamer(&self) -> MapStreamer {
+		let [lx, ly] = self.load_margin;
+		let [ux, uy] = self.unload_margin;
+		MapStreamer::default().with_margins(&Vector2::new(lx, ly), &Vector2::new(ux, uy))
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorldLayerConfig {
+	#[serde(default)]
+	pub layers:    Vec<LayerConfig>,
+	#[serde(default)]
+	pub streaming: StreamingConfig,
+}
+
+impl Default for WorldLayerConfig {
+	// what all worlds used before they could configure their layers
+	fn default() -> Self {
+		Self {
+			layers:    vec![
+				LayerConfig::new("Tile Layer 1", LayerId::TileMap1, EffectId::Textured)
+					.with_colliders(),
+				LayerConfig::new("terrain", LayerId::TileMap1, EffectId::Textured).with_colliders(),
+			],
+			streaming: StreamingConfig::default(),
+		}
+	}
+}
+
+impl WorldLayerConfig {
+	pub fn from_config_asset(system: &mut System, name: &str) -> Option<Self> {
+		let dfs = system.default_filesystem_mut();
+		let name_yaml = format!("{}.layer_config.yaml", &name);
+		if dfs.exists(&name_yaml) {
+			let mut f = dfs.open(&name_yaml);
+			let yaml = f.read_as_string();
+			match Self::from_yaml(&yaml) {
+				Ok(config) => Some(config),
+				Err(e) => {
+					warn!("Failed parsing {}: {}", &name_yaml, e);
+					None
+				},
+			}
+		} else {
+			None
+		}
+	}
+
+	pub fn from_yaml(yaml: &str) -> Result<Self, serde_yaml::Error> {
+		serde_yaml::from_str(yaml)
+	}
+
+	/// Names of the layers colliders and hazards are generated from.
+	pub fn collider_layers(&self) -> Vec<&str> {
+		self.layers
+			.iter()
+			.filter(|l| l.colliders)
+			.map(|l| l.name.as_str())
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn layer_config_fills_in_defaults() -> anyhow::Result<()> {
+		let config = WorldLayerConfig::from_yaml(
+			r##"
+layers:
+  - name: "Tile Layer 1"
+    colliders: true
+  - name: "Tile Layer 2"
+    layer: TileMap2
+    effect: TexturedDesaturated
+    order: -1
+    opacity: 0.5
+    tint: "#ff8080ff"
+streaming:
+  load_margin: [256.0, 128.0]
+"##,
+		)?;
+		assert_eq!(config.layers.len(), 2);
+		let l1 = &config.layers[0];
+		assert_eq!(l1.layer, LayerId::TileMap1);
+		assert_eq!(l1.effect, EffectId::Textured);
+		assert_eq!(l1.opacity, 1.0);
+		let l2 = &config.layers[1];
+		assert_eq!(l2.layer, LayerId::TileMap2);
+		assert_eq!(l2.effect, EffectId::TexturedDesaturated);
+		assert_eq!(l2.order, -1);
+		assert_eq!(l2.color().a, 0.5);
+		assert_eq!(config.collider_layers(), vec!["Tile Layer 1"]);
+		assert_eq!(config.streaming.load_margin, [256.0, 128.0]);
+		assert_eq!(config.streaming.unload_margin, [1024.0, 1024.0]);
+		Ok(())
+	}
+}
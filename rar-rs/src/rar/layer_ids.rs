@@ -1,10 +1,14 @@
-#[derive(Debug, Copy, Clone)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum LayerId {
 	None          = 0,
 	Background    = 1,
 	TileMap1      = 2,
 	TileMap2      = 3,
+	Props         = 4,
 	Player        = 5,
+	Overlay       = 6,
 	Debug         = 7,
 	Ui            = 8,
 	UiFront       = 9,
@@ -1,11 +1,14 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use derive_getters::Getters;
 use oml_game::math::{Circle, Rectangle, Vector2};
+use oml_game::renderer::Color;
 use oml_game::system::System;
 use tracing::*;
 
-use crate::rar::Tileset;
+use crate::rar::tileset::TileAnimationFrame;
+use crate::rar::{TileImage, Tileset};
 
 /* we could use an enum for the different layer types, but for now we just mix into on struct?!
 #[derive(Debug)]
@@ -17,6 +20,7 @@ enum Layer {
 pub enum LayerType {
 	Objects,
 	Tile,
+	Image,
 	#[default]
 	None,
 }
@@ -30,50 +34,459 @@ pub enum ObjectData {
 	Point {
 		pos: Vector2,
 	},
+	Polyline {
+		points: Vec<Vector2>,
+	},
+	/// closed, also used for rotated rectangles
+	Polygon {
+		points: Vec<Vector2>,
+	},
+	/// unrotated, see `Object::rotation`
+	Ellipse {
+		rect: Rectangle,
+	},
+	/// a tile placed as an object, unrotated, see `Object::rotation`
+	Tile {
+		gid:  u32,
+		rect: Rectangle,
+	},
+	/// unrotated, see `Object::rotation`
+	Text {
+		rect: Rectangle,
+		text: ObjectText,
+	},
 	#[default]
 	Unknown,
 }
 
+#[derive(Debug, Default, Clone)]
+pub struct ObjectText {
+	pub text:       String,
+	pub wrap:       bool,
+	pub pixel_size: u32,
+	pub halign:     String,
+	pub valign:     String,
+}
+
+/// A typed custom property as set in Tiled.
+#[derive(Debug, Clone)]
+pub enum PropertyValue {
+	String(String),
+	Int(i64),
+	Float(f64),
+	Bool(bool),
+	Color(Color),
+	File(String),
+	/// id of another object in the same map, 0 if unset
+	Object(u32),
+	Class {
+		class:   String,
+		members: HashMap<String, PropertyValue>,
+	},
+}
+
+impl PropertyValue {
+	pub fn as_str(&self) -> Option<&str> {
+		match self {
+			PropertyValue::String(s) | PropertyValue::File(s) => Some(s.as_str()),
+			_ => None,
+		}
+	}
+
+	pub fn as_f32(&self) -> Option<f32> {
+		match self {
+			PropertyValue::Float(f) => Some(*f as f32),
+			PropertyValue::Int(i) => Some(*i as f32),
+			// :HACK: older maps store numbers as strings
+			PropertyValue::String(s) => s.parse::<f32>().ok(),
+			_ => None,
+		}
+	}
+
+	pub fn as_i64(&self) -> Option<i64> {
+		match self {
+			PropertyValue::Int(i) => Some(*i),
+			PropertyValue::String(s) => s.parse::<i64>().ok(),
+			_ => None,
+		}
+	}
+
+	pub fn as_bool(&self) -> Option<bool> {
+		match self {
+			PropertyValue::Bool(b) => Some(*b),
+			PropertyValue::String(s) => s.parse::<bool>().ok(),
+			_ => None,
+		}
+	}
+
+	pub fn as_color(&self) -> Option<&Color> {
+		match self {
+			PropertyValue::Color(c) => Some(c),
+			_ => None,
+		}
+	}
+
+	pub fn as_object_ref(&self) -> Option<u32> {
+		match self {
+			PropertyValue::Object(id) if *id != 0 => Some(*id),
+			_ => None,
+		}
+	}
+
+	fn from_json(value: &serde_json::Value) -> Self {
+		match value {
+			serde_json::Value::Bool(b) => PropertyValue::Bool(*b),
+			serde_json::Value::Number(n) => match n.as_i64() {
+				Some(i) => PropertyValue::Int(i),
+				None => PropertyValue::Float(n.as_f64().unwrap_or_default()),
+			},
+			serde_json::Value::String(s) => PropertyValue::String(s.clone()),
+			serde_json::Value::Object(o) => PropertyValue::Class {
+				class:   String::new(),
+				members: o
+					.iter()
+					.map(|(k, v)| (k.clone(), PropertyValue::from_json(v)))
+					.collect(),
+			},
+			v => PropertyValue::String(v.to_string()),
+		}
+	}
+}
+
+// "#AARRGGBB" or "#RRGGBB"
+pub(crate) fn parse_color(s: &str) -> Option<Color> {
+	let hex = s.strip_prefix('#')?;
+	let v = u32::from_str_radix(hex, 16).ok()?;
+	let (a, rgb) = match hex.len() {
+		8 => (v >> 24, v & 0x00ff_ffff),
+		6 => (0xff, v),
+		_ => return None,
+	};
+	let c = |x: u32| (x & 0xff) as f32 / 255.0;
+	Some(Color::from_rgba(c(rgb >> 16), c(rgb >> 8), c(rgb), c(a)))
+}
+
+/// Component wise, e.g. to tint a tint.
+pub(crate) fn multiply_colors(a: &Color, b: &Color) -> Color {
+	Color::from_rgba(a.r * b.r, a.g * b.g, a.b * b.b, a.a * b.a)
+}
+
+impl From<&map_tmj::ObjectProperty> for PropertyValue {
+	fn from(ptmj: &map_tmj::ObjectProperty) -> Self {
+		let value = ptmj.value();
+		match ptmj.objectpropertytype().as_str() {
+			"int" => value
+				.as_i64()
+				.map(PropertyValue::Int)
+				.unwrap_or_else(|| PropertyValue::from_json(value)),
+			"float" => value
+				.as_f64()
+				.map(PropertyValue::Float)
+				.unwrap_or_else(|| PropertyValue::from_json(value)),
+			"bool" => value
+				.as_bool()
+				.map(PropertyValue::Bool)
+				.unwrap_or_else(|| PropertyValue::from_json(value)),
+			"color" => match parse_color(&ptmj.value_as_string()) {
+				Some(c) => PropertyValue::Color(c),
+				None => PropertyValue::String(ptmj.value_as_string()),
+			},
+			"file" => PropertyValue::File(ptmj.value_as_string()),
+			"object" => PropertyValue::Object(value.as_u64().unwrap_or(0) as u32),
+			"class" => match PropertyValue::from_json(value) {
+				PropertyValue::Class { members, .. } => PropertyValue::Class {
+					class: ptmj.propertytype().to_owned(),
+					members,
+				},
+				_ => PropertyValue::Class {
+					class:   ptmj.propertytype().to_owned(),
+					members: HashMap::new(),
+				},
+			},
+			_ => PropertyValue::String(ptmj.value_as_string()),
+		}
+	}
+}
+
 #[derive(Debug, Default, Getters)]
 pub struct Object {
-	name:  String,
-	class: String,
-	data:  ObjectData,
+	id:         u32,
+	name:       String,
+	class:      String,
+	data:       ObjectData,
+	/// degrees, clockwise in Tiled's y down space, `hflip` negates it for the y up world.
+	/// Ellipses, tiles and texts keep their rect unrotated, see `rotated_corners`.
+	rotation:   f32,
+	/// the position Tiled places the object with, and rotates it around,
+	/// the top left, or the bottom left for tiles
+	pivot:      Vector2,
+	properties: HashMap<String, PropertyValue>,
 }
 
 impl Object {
+	pub fn property_value(&self, name: &str) -> Option<&PropertyValue> {
+		self.properties.get(name)
+	}
+
+	/// Only for string and file properties.
+	pub fn property(&self, name: &str) -> Option<&str> {
+		self.property_value(name).and_then(|p| p.as_str())
+	}
+
+	pub fn property_as_f32(&self, name: &str) -> Option<f32> {
+		self.property_value(name).and_then(|p| p.as_f32())
+	}
+
+	pub fn property_as_i64(&self, name: &str) -> Option<i64> {
+		self.property_value(name).and_then(|p| p.as_i64())
+	}
+
+	pub fn property_as_bool(&self, name: &str) -> Option<bool> {
+		self.property_value(name).and_then(|p| p.as_bool())
+	}
+
+	pub fn property_as_color(&self, name: &str) -> Option<&Color> {
+		self.property_value(name).and_then(|p| p.as_color())
+	}
+
+	/// The id of the referenced object.
+	pub fn property_as_object_ref(&self, name: &str) -> Option<u32> {
+		self.property_value(name).and_then(|p| p.as_object_ref())
+	}
+
 	pub fn with_data(mut self, data: ObjectData) -> Self {
 		self.data = data;
 
 		self
 	}
+	/// The corners of an ellipse, tile, or text rect with the rotation applied,
+	/// None for all other shapes, they are rotated already.
+	pub fn rotated_corners(&self) -> Option<[Vector2; 4]> {
+		let rect = match &self.data {
+			ObjectData::Ellipse { rect }
+			| ObjectData::Tile { rect, .. }
+			| ObjectData::Text { rect, .. } => rect,
+			_ => return None,
+		};
+		let c = rect.center();
+		let half = rect.size().scaled(0.5);
+		let pivot = (self.pivot.x as f64, self.pivot.y as f64);
+		// hflip negates the rotation, so this works in both spaces
+		Some(
+			[(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].map(|(sx, sy)| {
+				let x = (c.x + sx * half.x) as f64;
+				let y = (c.y + sy * half.y) as f64;
+				rotate_around(x, y, pivot, self.rotation as f64)
+			}),
+		)
+	}
+
+	/// The axis aligned bounds of `rotated_corners`.
+	pub fn rotated_bounds(&self) -> Option<Rectangle> {
+		self.rotated_corners().map(|corners| {
+			let min = corners
+				.iter()
+				.fold(corners[0], |m, p| Vector2::new(m.x.min(p.x), m.y.min(p.y)));
+			let max = corners
+				.iter()
+				.fold(corners[0], |m, p| Vector2::new(m.x.max(p.x), m.y.max(p.y)));
+			Rectangle::default()
+				.with_size(&max.sub(&min))
+				.with_center(&min.add(&max).scaled(0.5))
+		})
+	}
+
 	pub fn hflip(&mut self, pivot_y: f32) {
-		let data: &mut ObjectData = &mut self.data;
-		//		let mut u = ObjectData::Unknown;
-		//		data = &mut u;
-		match data {
+		// mirroring turns clockwise into counter clockwise
+		self.rotation = -self.rotation;
+		self.pivot.y = pivot_y - self.pivot.y;
+		match &mut self.data {
+			ObjectData::Rectangle { rect, .. }
+			| ObjectData::Ellipse { rect }
+			| ObjectData::Tile { rect, .. }
+			| ObjectData::Text { rect, .. } => {
+				rect.hflip(pivot_y);
+			},
+			ObjectData::Point { pos } => {
+				pos.y = pivot_y - pos.y;
+			},
+			ObjectData::Polyline { points } | ObjectData::Polygon { points } => {
+				for pos in points.iter_mut() {
+					pos.y = pivot_y - pos.y;
+				}
+			},
+			ObjectData::Unknown => {},
+		}
+	}
+	pub fn translate(&mut self, offset: &Vector2) {
+		self.pivot = self.pivot.add(offset);
+		match &mut self.data {
 			ObjectData::Rectangle {
 				rect,
-				bounding_circle: _,
+				bounding_circle,
 			} => {
-				rect.hflip(pivot_y);
+				rect.offset(offset);
+				if let Some(bounding_circle) = bounding_circle {
+					*bounding_circle = rect.calculate_bounding_circle();
+				}
+			},
+			ObjectData::Ellipse { rect }
+			| ObjectData::Tile { rect, .. }
+			| ObjectData::Text { rect, .. } => {
+				rect.offset(offset);
 			},
 			ObjectData::Point { pos } => {
-				pos.y = pivot_y - pos.y;
+				*pos = pos.add(offset);
 			},
-			_ => {
-				panic!("Warning: hflip for {:?} not implemented", &data);
+			ObjectData::Polyline { points } | ObjectData::Polygon { points } => {
+				for pos in points.iter_mut() {
+					*pos = pos.add(offset);
+				}
+			},
+			ObjectData::Unknown => {},
+		}
+	}
+}
+
+// Tiled stores flips and rotations in the highest bits of the global tile id
+const GID_FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const GID_FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+const GID_FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+// only used by hexagonal maps, but never part of the id
+const GID_ROTATED_HEXAGONAL_120: u32 = 0x1000_0000;
+const GID_FLAGS: u32 = GID_FLIPPED_HORIZONTALLY
+	| GID_FLIPPED_VERTICALLY
+	| GID_FLIPPED_DIAGONALLY
+	| GID_ROTATED_HEXAGONAL_120;
+
+/// How a tile is mirrored, the diagonal flip is applied first.
+/// A diagonal plus horizontal flip is a clockwise rotation by 90 degrees.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TileFlip {
+	pub horizontal: bool,
+	pub vertical:   bool,
+	pub diagonal:   bool,
+}
+
+impl TileFlip {
+	pub fn is_none(&self) -> bool {
+		!(self.horizontal || self.vertical || self.diagonal)
+	}
+
+	/// The x axis, y axis, and offset mapping the uvs of the rendered quad to the uvs in the image,
+	/// both with y down.
+	pub fn uv_transform(&self) -> (Vector2, Vector2, Vector2) {
+		let sample = |p: Vector2| {
+			let p = if self.vertical {
+				Vector2::new(p.x, -p.y)
+			} else {
+				p
+			};
+			let p = if self.horizontal {
+				Vector2::new(-p.x, p.y)
+			} else {
+				p
+			};
+			if self.diagonal {
+				Vector2::new(p.y, p.x)
+			} else {
+				p
+			}
+		};
+		let x = sample(Vector2::new(1.0, 0.0));
+		let y = sample(Vector2::new(0.0, 1.0));
+		// keep the center in place
+		let offset = Vector2::new(0.5, 0.5).sub(&x.add(&y).scaled(0.5));
+		(x, y, offset)
+	}
+}
+
+/// A global tile id, with the flip flags split off.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Gid {
+	id:   u32,
+	flip: TileFlip,
+}
+
+impl Gid {
+	pub fn id(&self) -> u32 {
+		self.id
+	}
+	pub fn flip(&self) -> &TileFlip {
+		&self.flip
+	}
+	/// Zero is the empty tile, no matter how it is flipped.
+	pub fn is_empty(&self) -> bool {
+		self.id == 0
+	}
+}
+
+impl From<u32> for Gid {
+	fn from(raw: u32) -> Self {
+		Self {
+			id:   raw & !GID_FLAGS,
+			flip: TileFlip {
+				horizontal: raw & GID_FLIPPED_HORIZONTALLY != 0,
+				vertical:   raw & GID_FLIPPED_VERTICALLY != 0,
+				diagonal:   raw & GID_FLIPPED_DIAGONALLY != 0,
 			},
 		}
 	}
 }
 
+/// The frames of an animated tile, as gids.
+#[derive(Debug, Clone)]
+pub struct TileAnimation {
+	// start time, gid
+	frames:   Vec<(f64, u32)>,
+	duration: f64,
+}
+
+impl TileAnimation {
+	/// Tile ids in the frames are local to the tileset starting at `firstgid`.
+	pub fn new(firstgid: u32, frames: &[TileAnimationFrame]) -> Option<Self> {
+		let mut duration = 0.0;
+		let frames: Vec<(f64, u32)> = frames
+			.iter()
+			.map(|f| {
+				let start = duration;
+				duration += f.duration;
+				(start, firstgid + f.tile_id)
+			})
+			.collect();
+		if frames.is_empty() || duration <= 0.0 {
+			None
+		} else {
+			Some(Self { frames, duration })
+		}
+	}
+
+	/// Loops, time is in seconds.
+	pub fn gid_at(&self, time: f64) -> u32 {
+		let t = time.rem_euclid(self.duration);
+		// only a handful of frames, no need for a binary search
+		match self.frames.iter().rev().find(|(start, _)| *start <= t) {
+			Some((_, gid)) => *gid,
+			None => self.frames[0].1,
+		}
+	}
+}
+
+// shared by all tile maps, so a map streamed back in never reuses the revision of an old one
+static NEXT_TILE_MAP_REVISION: AtomicU64 = AtomicU64::new(1);
+
+fn next_tile_map_revision() -> u64 {
+	NEXT_TILE_MAP_REVISION.fetch_add(1, Ordering::Relaxed)
+}
+
 //#[derive(Debug)]
 #[derive(Clone)]
 pub struct TileMap {
-	width:  u32,
-	height: u32,
-	tiles:  Vec<u32>,
+	width:    u32,
+	height:   u32,
+	tiles:    Vec<u32>,
+	// changes with every change, so renderers know when to rebuild their caches
+	revision: u64,
 }
 
 impl TileMap {
@@ -82,11 +495,18 @@ impl TileMap {
 			width,
 			height,
 			tiles: Vec::with_capacity((width * height) as usize),
+			revision: next_tile_map_revision(),
 		}
 	}
 
 	pub fn push(&mut self, tile: u32) {
 		self.tiles.push(tile);
+		self.revision = next_tile_map_revision();
+	}
+
+	/// Unique across all tile maps, clones share it until one of them changes.
+	pub fn revision(&self) -> u64 {
+		self.revision
 	}
 
 	pub fn width(&self) -> u32 {
@@ -95,6 +515,7 @@ impl TileMap {
 	pub fn height(&self) -> u32 {
 		self.height
 	}
+	/// The raw global tile id, including the flip flags.
 	pub fn get_xy(&self, x: u32, y: u32) -> u32 {
 		let o = (y * self.width + x) as usize;
 		if o < self.tiles.len() {
@@ -103,14 +524,18 @@ impl TileMap {
 			0 // tile ZERO is special
 		}
 	}
+	pub fn get_gid_xy(&self, x: u32, y: u32) -> Gid {
+		self.get_xy(x, y).into()
+	}
 }
 
 impl Default for TileMap {
 	fn default() -> Self {
 		Self {
-			width:  0,
-			height: 0,
-			tiles:  Vec::default(),
+			width:    0,
+			height:   0,
+			tiles:    Vec::default(),
+			revision: next_tile_map_revision(),
 		}
 	}
 }
@@ -143,20 +568,119 @@ pub struct Chunk {
 pub struct MapTileset {
 	firstgid: u32,
 	source:   String,
+	/// the tsj of tilesets embedded in the map, they have no source
+	embedded: Option<String>,
 	tileset:  Option<Tileset>,
 }
 
-#[derive(Debug, Default, Getters)]
+/// The image of an image layer.
+#[derive(Debug, Clone)]
+pub struct LayerImage {
+	pub texture:  String,
+	pub size:     Vector2,
+	pub repeat_x: bool,
+	pub repeat_y: bool,
+}
+
+#[derive(Debug, Getters)]
 pub struct Layer {
-	layertype: LayerType,
-	name:      String,
-	objects:   Vec<Object>,
-	chunks:    Vec<Chunk>,
+	layertype:  LayerType,
+	name:       String,
+	/// the names of the enclosing groups and the layer, e.g. "background/far"
+	path:       String,
+	/// false also excludes the layer from collisions
+	visible:    bool,
+	opacity:    f32,
+	tint:       Color,
+	objects:    Vec<Object>,
+	chunks:     Vec<Chunk>,
+	properties: HashMap<String, PropertyValue>,
+	/// how fast the layer scrolls compared to the camera, 1.0 for normal layers
+	parallax:   Vector2,
+	/// in pixels, y down like in Tiled, already applied to the objects
+	offset:     Vector2,
+	image:      Option<LayerImage>,
+}
+
+impl Default for Layer {
+	fn default() -> Self {
+		Self {
+			layertype:  LayerType::default(),
+			name:       String::new(),
+			path:       String::new(),
+			visible:    true,
+			opacity:    1.0,
+			tint:       Color::white(),
+			objects:    Vec::new(),
+			chunks:     Vec::new(),
+			properties: HashMap::new(),
+			parallax:   Vector2::new(1.0, 1.0),
+			offset:     Vector2::zero(),
+			image:      None,
+		}
+	}
 }
 
 impl Layer {
+	// group layers are flattened, their children inherit their state
+	fn inherit_from_group(&mut self, group: &Layer) {
+		self.path = format!("{}/{}", group.path, self.name);
+		self.visible &= group.visible;
+		self.opacity *= group.opacity;
+		self.tint = multiply_colors(&self.tint, &group.tint);
+		self.parallax = self.parallax.scaled_vector2(&group.parallax);
+		// the objects already have their own offset, and are still in tiled space
+		for o in &mut self.objects {
+			o.translate(&group.offset);
+		}
+		self.offset = self.offset.add(&group.offset);
+	}
+
+	/// What to add to world positions of the layer to get render positions.
+	/// Parallax is relative to the origin of the map, like in Tiled.
+	pub fn render_offset(&self, camera_offset: &Vector2, map_origin: &Vector2) -> Vector2 {
+		let fixed = Vector2::new(1.0 - self.parallax.x, 1.0 - self.parallax.y);
+		camera_offset
+			.scaled_vector2(&self.parallax)
+			.sub(&map_origin.scaled_vector2(&fixed))
+			.add(&Vector2::new(self.offset.x, -self.offset.y))
+	}
+
 	pub fn set_name(&mut self, name: &str) {
 		self.name = name.to_string();
+		self.path = name.to_string();
+	}
+	pub fn property_value(&self, name: &str) -> Option<&PropertyValue> {
+		self.properties.get(name)
+	}
+
+	/// Only for string and file properties.
+	pub fn property(&self, name: &str) -> Option<&str> {
+		self.property_value(name).and_then(|p| p.as_str())
+	}
+
+	pub fn property_as_f32(&self, name: &str) -> Option<f32> {
+		self.property_value(name).and_then(|p| p.as_f32())
+	}
+
+	pub fn property_as_i64(&self, name: &str) -> Option<i64> {
+		self.property_value(name).and_then(|p| p.as_i64())
+	}
+
+	pub fn property_as_bool(&self, name: &str) -> Option<bool> {
+		self.property_value(name).and_then(|p| p.as_bool())
+	}
+
+	pub fn property_as_color(&self, name: &str) -> Option<&Color> {
+		self.property_value(name).and_then(|p| p.as_color())
+	}
+
+	/// Visible tile layers with a path starting with one of `layers`,
+	/// or flagged via their `colliders` property.
+	fn is_collider_source(&self, layers: &[&str]) -> bool {
+		self.visible
+			&& (layers.iter().any(|ul| self.path.starts_with(ul))
+				|| self.property_as_bool("colliders").unwrap_or(false))
 	}
 	pub fn list_objects_for_class(&self, class: &str) -> Vec<&Object> {
 		let mut r = Vec::new();
@@ -191,15 +715,70 @@ impl Layer {
 				*/
 		}
 	}
+	pub fn translate(&mut self, offset: &Vector2) {
+		for o in &mut self.objects {
+			o.translate(offset);
+		}
+	}
+}
+
+/// The raw files needed to build a map, read up front so the map can be built anywhere,
+/// e.g. on a worker thread.
+#[derive(Debug, Default, Getters)]
+pub struct MapSources {
+	name:     String,
+	tmj:      String,
+	// by tileset name
+	tilesets: HashMap<String, String>,
+}
+
+impl MapSources {
+	pub fn load(system: &mut System, name: &str) -> anyhow::Result<Self> {
+		let tmj = Map::read_as_tmj(system, name)?;
+		Self::load_from_str(name, tmj, &mut |ts_name| {
+			Tileset::read_as_tsj(system, ts_name)
+		})
+	}
+
+	/// `load_tileset` reads the tileset with the given name, it is called once per tileset.
+	pub fn load_from_str(
+		name: &str,
+		tmj: String,
+		load_tileset: &mut dyn FnMut(&str) -> anyhow::Result<String>,
+	) -> anyhow::Result<Self> {
+		let mut tilesets = HashMap::new();
+		for source in MapTmj::tileset_sources_from_str(&tmj)? {
+			let ts_name = tileset_name_from_source(&source);
+			if !tilesets.contains_key(&ts_name) {
+				let tsj = load_tileset(&ts_name)?;
+				tilesets.insert(ts_name, tsj);
+			}
+		}
+
+		Ok(Self {
+			name: name.to_owned(),
+			tmj,
+			tilesets,
+		})
+	}
 }
 
+/// Tiles stay in map local tile coordinates, x right and y down like in Tiled.
+/// Objects, and generated layers, are in world coordinates (y up) once the map is placed,
+/// `origin` is the world position of the top left corner of tile 0,0.
 #[derive(Debug, Default, Getters)]
 pub struct Map {
-	layers:     Vec<Layer>,
-	tilesets:   Vec<MapTileset>,
-	upsideup:   bool,
-	tileheight: u32,
-	tilewidth:  u32,
+	layers:          Vec<Layer>,
+	tilesets:        Vec<MapTileset>,
+	upsideup:        bool,
+	tileheight:      u32,
+	tilewidth:       u32,
+	origin:          Vector2,
+	// indexed by gid, built once the tilesets are loaded
+	#[getter(skip)]
+	tile_images:     Vec<Option<TileImage>>,
+	#[getter(skip)]
+	tile_animations: Vec<Option<TileAnimation>>,
 }
 
 impl Map {
@@ -213,7 +792,7 @@ impl Map {
 		let mut r = Vec::new();
 
 		for l in self.layers.iter() {
-			if l.name() == layer {
+			if l.path() == layer {
 				let mut rl = l.list_objects_for_class(class);
 				r.append(&mut rl);
 			}
@@ -221,11 +800,12 @@ impl Map {
 		r
 	}
 
+	/// Hidden layers are skipped, e.g. for colliders.
 	pub fn list_objects_in_layer(&self, layer: &str) -> Vec<&Object> {
 		let mut r = Vec::new();
 
 		for l in self.layers.iter() {
-			if l.name() == layer {
+			if l.path() == layer && l.visible {
 				let mut rl = l.list_objects();
 				r.append(&mut rl);
 			}
@@ -237,26 +817,118 @@ impl Map {
 		self.layers.push(layer);
 	}
 
+	/// By path, e.g. "background/far", or just the name for layers outside of groups.
+	pub fn layer(&self, path: &str) -> Option<&Layer> {
+		self.layers.iter().find(|l| l.path == path)
+	}
+
+	// group layers are not kept, only their children
+	fn add_tmj_layers(&mut self, layers: &[map_tmj::Layer], group: Option<&Layer>) {
+		for ltmj in layers {
+			let mut l: Layer = ltmj.into();
+			if let Some(group) = group {
+				l.inherit_from_group(group);
+			}
+			if ltmj.layertype() == "group" {
+				self.add_tmj_layers(ltmj.layers(), Some(&l));
+			} else {
+				self.add_layer(l);
+			}
+		}
+	}
+
 	pub fn add_tileset(&mut self, tileset: MapTileset) {
 		self.tilesets.push(tileset);
+		self.tilesets.sort_by_key(|ts| ts.firstgid);
 	}
 
 	pub fn load_all_tilesets(&mut self, system: &mut System) -> anyhow::Result<()> {
 		for ts in self.tilesets.iter_mut() {
 			let mut tileset = Tileset::new();
 			tileset.enable_remove_path();
-			tileset.load(system, &ts.source)?;
+			match &ts.embedded {
+				Some(tsj) => tileset.load_from_str(tsj)?,
+				None => tileset.load(system, &ts.source)?,
+			}
 
 			ts.tileset = Some(tileset);
 		}
+		self.build_tile_lookup();
 
 		Ok(())
 	}
 
+	/// Resolves every tile of every tileset up front, so rendering doesn't search per tile.
+	fn build_tile_lookup(&mut self) {
+		let mut tile_images = Vec::new();
+		let mut tile_animations = Vec::new();
+		// sorted by firstgid, so later tilesets win, same as `tileset_for_gid`
+		for mts in self.tilesets.iter() {
+			if let Some(ts) = &mts.tileset {
+				for t in ts.tiles().iter() {
+					let gid = (mts.firstgid + t.id()) as usize;
+					if let Some(animation) = TileAnimation::new(mts.firstgid, t.animation()) {
+						if gid >= tile_animations.len() {
+							tile_animations.resize(gid + 1, None);
+						}
+						tile_animations[gid] = Some(animation);
+					}
+					if t.image().texture.is_empty() {
+						continue;
+					}
+					if gid >= tile_images.len() {
+						tile_images.resize(gid + 1, None);
+					}
+					tile_images[gid] = Some(t.image().clone());
+				}
+			}
+		}
+		self.tile_images = tile_images;
+		self.tile_animations = tile_animations;
+	}
+
 	pub fn generate_collider_layers(
 		&mut self,
 		name: &str,
 		layers: &Vec<&str>,
+	) -> anyhow::Result<()> {
+		// hazards are handled via their own layer, and must not be solid
+		let hazards: HashSet<u32> = self.collect_hazard_tids(layers);
+		self.generate_tile_rectangle_layer(name, layers, &|tid| !hazards.contains(&tid))
+	}
+
+	pub fn generate_hazard_layers(&mut self, name: &str, layers: &Vec<&str>) -> anyhow::Result<()> {
+		let hazards: HashSet<u32> = self.collect_hazard_tids(layers);
+		self.generate_tile_rectangle_layer(name, layers, &|tid| hazards.contains(&tid))
+	}
+
+	fn collect_hazard_tids(&self, layers: &Vec<&str>) -> HashSet<u32> {
+		let mut hazards = HashSet::new();
+		for l in self.layers.iter() {
+			if !l.is_collider_source(layers) {
+				continue;
+			}
+			for c in l.chunks().iter() {
+				let tm = c.tile_map();
+				for y in 0..*c.height() {
+					for x in 0..*c.width() {
+						// flipped tiles are still the same tile
+						let tid = tm.get_gid_xy(x, y).id();
+						if tid > 0 && self.is_tile_hazard(tid) {
+							hazards.insert(tid);
+						}
+					}
+				}
+			}
+		}
+		hazards
+	}
+
+	fn generate_tile_rectangle_layer(
+		&mut self,
+		name: &str,
+		layers: &Vec<&str>,
+		use_tile: &dyn Fn(u32) -> bool,
 	) -> anyhow::Result<()> {
 		if let Some(layer) = self.layers.iter().find(|l| l.name == name) {
 			warn!("Layer >{}< already exists -> {:#?}", &name, layer);
@@ -276,7 +948,7 @@ impl Map {
 		let mut max_y = i32::MIN;
 
 		for l in self.layers.iter() {
-			if !layers.iter().any(|ul| l.name().starts_with(ul)) {
+			if !l.is_collider_source(layers) {
 				debug!(
 					"Skipping layer >{}< as for Collider layer >{}<",
 					l.name(),
@@ -294,14 +966,13 @@ impl Map {
 			for c in l.chunks().iter() {
 				let chunk_x = *c.x();
 				let chunk_y = *c.y();
-				let chunk_y = chunk_y - 7; // :HACK: what the fudge? 7?
 				let tm = c.tile_map();
 				// no visibility checks here, it's pre-processed anyway
 				for y in 0..*c.height() {
 					for x in 0..*c.width() {
 						// :TODO: maybe we need the tilemap here to lookup non-1x1 tiles?
-						let tid = tm.get_xy(x, y);
-						if tid > 0 {
+						let tid = tm.get_gid_xy(x, y).id();
+						if tid > 0 && use_tile(tid) {
 							let cx = chunk_x + x as i32;
 							let cy = chunk_y + y as i32;
 							all_tiles.insert((cx, cy));
@@ -329,10 +1000,7 @@ impl Map {
 				}
 				if all_tiles.contains(&(cx, cy)) {
 					let (x, y) = all_tiles.take(&(cx, cy)).unwrap();
-					let start = Vector2::zero(); //tile_size.scaled_vector2(&Vector2::new(x as f32, y as f32));
-					let pos = start
-						.add(&tile_size.scaled_vector2(&Vector2::new(x as f32, y as f32 * -1.0)));
-					let mut pos = pos.add(&half_tile_size);
+					let mut pos = self.tile_center(x, y);
 					let mut rect_size = tile_size;
 					let t10 = (x + 1, y + 0);
 					let t01 = (x + 0, y + 1);
@@ -381,23 +1049,51 @@ impl Map {
 		Ok(())
 	}
 
+	/// Builds the map, including its tilesets, without touching the filesystem.
+	pub fn load_from_sources(&mut self, sources: &MapSources) -> anyhow::Result<()> {
+		let mut map_tmj = MapTmj::new();
+		map_tmj.load_from_str(&sources.tmj)?;
+		*self = map_tmj.into();
+
+		for ts in self.tilesets.iter_mut() {
+			let tsj = match (&ts.embedded, sources.tilesets.get(&ts.source)) {
+				(Some(tsj), _) | (None, Some(tsj)) => tsj,
+				(None, None) => anyhow::bail!("Missing tileset source: {}", &ts.source),
+			};
+			let mut tileset = Tileset::new();
+			tileset.enable_remove_path();
+			tileset.load_from_str(tsj)?;
+
+			ts.tileset = Some(tileset);
+		}
+		self.build_tile_lookup();
+		Ok(())
+	}
+
 	pub fn load(&mut self, system: &mut System, name: &str) -> anyhow::Result<()> {
-		//		return anyhow::bail!("Just testing...");
+		let tmj_string = Self::read_as_tmj(system, name)?;
+		let mut map_tmj = MapTmj::new();
+		map_tmj.load_from_str(&tmj_string)?;
 
+		*self = map_tmj.into();
+		Ok(())
+	}
+
+	/// Probes for `.tmj`, then `.tmx`, and returns the map in the TMJ layout.
+	pub fn read_as_tmj(system: &mut System, name: &str) -> anyhow::Result<String> {
 		let tmj_name = format!("{}.tmj", &name);
-		dbg!(&tmj_name);
 		if system.default_filesystem().exists(&tmj_name) {
-			println!("Trying to load map from {}", &tmj_name);
-			let mut map_tmj = MapTmj::new();
-			map_tmj.load(system, &tmj_name)?;
-
-			dbg!(&map_tmj);
-
-			*self = map_tmj.into();
-		} else {
-			anyhow::bail!("No remaining loader for map: {}", &name);
+			debug!("Loading map from {}", &tmj_name);
+			let mut tmj_file = system.default_filesystem_mut().open(&tmj_name);
+			return Ok(tmj_file.read_as_string());
 		}
-		Ok(())
+		let tmx_name = format!("{}.tmx", &name);
+		if system.default_filesystem().exists(&tmx_name) {
+			debug!("Loading map from {}", &tmx_name);
+			let mut tmx_file = system.default_filesystem_mut().open(&tmx_name);
+			return map_tmx::tmx_to_tmj(&tmx_file.read_as_string());
+		}
+		anyhow::bail!("No remaining loader for map: {}", &name);
 	}
 
 	pub fn hflip(&mut self, pivot_y: f32) {
@@ -407,20 +1103,78 @@ impl Map {
 		self.upsideup = !self.upsideup;
 	}
 
-	pub fn get_tile_image(&self, tid: u32) -> &str {
-		// :TODO: combine tileset on load?!
-		for mts in self.tilesets.iter() {
-			if let Some(ts) = &mts.tileset {
-				if tid >= mts.firstgid {
-					let image = ts.get_tile_image(tid - mts.firstgid);
-					if !image.is_empty() {
-						return image;
-					}
-				}
-			}
+	/// Moves all objects from map local Tiled coordinates into the world.
+	/// Must be called once, right after loading, and before generating any layers.
+	pub fn place_in_world(&mut self, origin: &Vector2) {
+		if !self.upsideup {
+			self.hflip(0.0);
 		}
-		//"tile_default_block"
-		""
+		for l in &mut self.layers {
+			l.translate(origin);
+		}
+		self.origin = *origin;
+	}
+
+	/// World position of the center of a map local tile.
+	pub fn tile_center(&self, tile_x: i32, tile_y: i32) -> Vector2 {
+		let tw = self.tilewidth as f32;
+		let th = self.tileheight as f32;
+		self.origin.add(&Vector2::new(
+			(tile_x as f32 + 0.5) * tw,
+			-(tile_y as f32 + 0.5) * th,
+		))
+	}
+
+	/// The map local tile containing the world position.
+	pub fn world_to_tile(&self, pos: &Vector2) -> (i32, i32) {
+		let local = pos.sub(&self.origin);
+		(
+			(local.x / self.tilewidth as f32).floor() as i32,
+			(-local.y / self.tileheight as f32).floor() as i32,
+		)
+	}
+
+	/// The tileset containing the tile, and the id local to it.
+	/// Tilesets are sorted by `firstgid`, so the last one that starts at or before the id wins.
+	pub fn tileset_for_gid(&self, gid: &Gid) -> Option<(&Tileset, u32)> {
+		if gid.is_empty() {
+			return None;
+		}
+		let mts = self
+			.tilesets
+			.iter()
+			.rev()
+			.find(|mts| gid.id() >= mts.firstgid)?;
+		mts.tileset.as_ref().map(|ts| (ts, gid.id() - mts.firstgid))
+	}
+
+	pub fn is_tile_hazard(&self, tid: u32) -> bool {
+		match self.tileset_for_gid(&tid.into()) {
+			Some((ts, local_id)) => ts.is_tile_hazard(local_id),
+			None => false,
+		}
+	}
+
+	/// O(1), only valid once the tilesets are loaded.
+	/// Animated tiles show their first frame.
+	pub fn get_tile_image(&self, tid: u32) -> Option<&TileImage> {
+		self.get_tile_image_at(tid, 0.0)
+	}
+
+	/// Like `get_tile_image`, with animated tiles showing the frame for the given time.
+	pub fn get_tile_image_at(&self, tid: u32, time: f64) -> Option<&TileImage> {
+		let gid: Gid = tid.into();
+		let id = match self.tile_animations.get(gid.id() as usize) {
+			Some(Some(animation)) => animation.gid_at(time),
+			_ => gid.id(),
+		};
+		self.tile_images.get(id as usize).and_then(|i| i.as_ref())
+	}
+
+	/// Searches the tilesets, prefer `get_tile_image`.
+	pub fn find_tile_image(&self, tid: u32) -> Option<&TileImage> {
+		self.tileset_for_gid(&tid.into())
+			.and_then(|(ts, local_id)| ts.get_tile_image(local_id))
 	}
 }
 
@@ -436,41 +1190,115 @@ impl From<&map_tmj::Chunk> for Chunk {
 	}
 }
 
+// rotates (x, y) clockwise around the pivot, in tiled's y down space
+fn rotate_around(x: f64, y: f64, pivot: (f64, f64), degrees: f64) -> Vector2 {
+	let (s, c) = degrees.to_radians().sin_cos();
+	let (dx, dy) = (x - pivot.0, y - pivot.1);
+	Vector2::new(
+		(pivot.0 + dx * c - dy * s) as f32,
+		(pivot.1 + dx * s + dy * c) as f32,
+	)
+}
+
 impl From<&map_tmj::Object> for Object {
 	fn from(otmj: &map_tmj::Object) -> Self {
+		let (x, y, w, h) = (otmj.x(), otmj.y(), otmj.width(), otmj.height());
+		let rotation = otmj.rotation();
+		// points are relative to the object, and rotate around it
+		let to_points = |points: &Vec<map_tmj::ObjectPoint>| -> Vec<Vector2> {
+			points
+				.iter()
+				.map(|p| rotate_around(x + p.x, y + p.y, (x, y), rotation))
+				.collect()
+		};
 		let data = if otmj.point() {
-			//			ObjectData::Unknown
-			ObjectData::Point {
-				pos: (otmj.x(), otmj.y()).into(),
+			ObjectData::Point { pos: (x, y).into() }
+		} else if let Some(polyline) = otmj.polyline() {
+			ObjectData::Polyline {
+				points: to_points(polyline),
+			}
+		} else if let Some(polygon) = otmj.polygon() {
+			ObjectData::Polygon {
+				points: to_points(polygon),
+			}
+		} else if otmj.ellipse() {
+			ObjectData::Ellipse {
+				rect: (x, y, w, h).into(),
+			}
+		} else if let Some(gid) = otmj.gid() {
+			// tile objects are anchored at their bottom left
+			ObjectData::Tile {
+				gid,
+				rect: (x, y - h, w, h).into(),
+			}
+		} else if let Some(text) = otmj.text() {
+			ObjectData::Text {
+				rect: (x, y, w, h).into(),
+				text: ObjectText {
+					text:       text.text().to_owned(),
+					wrap:       *text.wrap(),
+					pixel_size: *text.pixelsize(),
+					halign:     text.halign().to_owned(),
+					valign:     text.valign().to_owned(),
+				},
+			}
+		} else if rotation != 0.0 {
+			let corners = [(0.0, 0.0), (w, 0.0), (w, h), (0.0, h)];
+			ObjectData::Polygon {
+				points: corners
+					.iter()
+					.map(|(cx, cy)| rotate_around(x + cx, y + cy, (x, y), rotation))
+					.collect(),
 			}
 		} else {
 			ObjectData::Rectangle {
-				rect:            (otmj.x(), otmj.y(), otmj.width(), otmj.height()).into(),
+				rect:            (x, y, w, h).into(),
 				bounding_circle: None,
 			}
 		};
 
+		let properties = otmj
+			.properties()
+			.iter()
+			.map(|p| (p.name().to_owned(), p.into()))
+			.collect();
+
 		Self {
+			id: otmj.id(),
 			name: otmj.name().to_owned(),
 			class: otmj.class().to_owned(),
 			data,
+			rotation: rotation as f32,
+			pivot: (x, y).into(),
+			properties,
 		}
 	}
 }
+// e.g. "../tilesets/grassland.tsj" -> "grassland"
+fn tileset_name_from_source(source: &str) -> String {
+	source
+		.split("/")
+		.last()
+		.unwrap_or(source)
+		.split(".")
+		.nth(0)
+		.unwrap_or(source)
+		.to_owned()
+}
+
 impl From<&map_tmj::Tileset> for MapTileset {
 	fn from(tstmj: &map_tmj::Tileset) -> Self {
-		let source = tstmj.source();
-		let source = source
-			.split("/")
-			.last()
-			.unwrap_or(source)
-			.split(".")
-			.nth(0)
-			.unwrap_or(source)
-			.to_owned();
+		let embedded = match tstmj.embedded_tsj() {
+			Ok(embedded) => embedded,
+			Err(e) => {
+				warn!("Broken embedded tileset at {}: {:?}", tstmj.firstgid(), e);
+				None
+			},
+		};
 		Self {
 			firstgid: *tstmj.firstgid(),
-			source,
+			source: tileset_name_from_source(tstmj.source()),
+			embedded,
 			..Default::default()
 		}
 	}
@@ -480,18 +1308,47 @@ impl From<&map_tmj::Layer> for Layer {
 	fn from(ltmj: &map_tmj::Layer) -> Self {
 		let mut l = Self {
 			name: ltmj.name().to_owned(),
+			path: ltmj.name().to_owned(),
+			visible: ltmj.visible(),
+			opacity: ltmj.opacity() as f32,
+			tint: parse_color(ltmj.tintcolor()).unwrap_or_else(Color::white),
+			properties: ltmj
+				.properties()
+				.iter()
+				.map(|p| (p.name().to_owned(), p.into()))
+				.collect(),
 			..Default::default()
 		};
+		let (px, py) = ltmj.parallax();
+		l.parallax = Vector2::new(px, py);
+		let (ox, oy) = ltmj.offset();
+		l.offset = Vector2::new(ox, oy);
 		l.layertype = match ltmj.layertype() {
 			"objectgroup" => {
 				if let Some(objects) = &ltmj.objects() {
 					for o in objects {
-						let obj = o.into();
+						let mut obj: Object = o.into();
+						// still in tiled space, so y is down
+						obj.translate(&l.offset);
 						l.add_object(obj);
 					}
 				}
 				LayerType::Objects
 			},
+			"imagelayer" => {
+				let (w, h) = ltmj.image_size();
+				let (repeat_x, repeat_y) = ltmj.repeat();
+				if !ltmj.image().is_empty() {
+					// textures are named like tilesets, without path and extension
+					l.image = Some(LayerImage {
+						texture: tileset_name_from_source(ltmj.image()),
+						size: Vector2::new(w as f32, h as f32),
+						repeat_x,
+						repeat_y,
+					});
+				}
+				LayerType::Image
+			},
 			"tilelayer" => {
 				if let Some(chunks) = &ltmj.chunks() {
 					for c in chunks {
@@ -514,9 +1371,7 @@ impl From<map_tmj::MapTmj> for Map {
 		m.tilewidth = mtmj.tilewidth();
 		m.tileheight = mtmj.tileheight();
 		m.upsideup = false; // tiled is upside down!
-		for l in mtmj.layers() {
-			m.add_layer(l.into());
-		}
+		m.add_tmj_layers(mtmj.layers(), None);
 
 		for ts in mtmj.tilesets() {
 			m.add_tileset(ts.into());
@@ -528,6 +1383,8 @@ impl From<map_tmj::MapTmj> for Map {
 
 #[path = "./map_tmj.rs"]
 mod map_tmj;
+#[path = "./map_tmx.rs"]
+mod map_tmx;
 use map_tmj::MapTmj;
 
 #[cfg(test)]
@@ -572,4 +1429,226 @@ mod tests {
 
 		Ok(())
 	}
+
+	#[test]
+	fn image_layers_scroll_with_parallax() -> anyhow::Result<()> {
+		let ltmj: map_tmj::Layer = serde_json::from_str(
+			r##"{
+				"name": "sky", "id": 4, "type": "imagelayer", "x": 0, "y": 0,
+				"opacity": 1, "visible": true,
+				"image": "../backgrounds/bg-title.png", "imagewidth": 512, "imageheight": 256,
+				"repeatx": true, "parallaxx": 0.5, "parallaxy": 0.25,
+				"offsetx": 16, "offsety": -8
+			}"##,
+		)?;
+		let l: Layer = (&ltmj).into();
+
+		let image = l.image().as_ref().expect("image layer without image");
+		assert_eq!(image.texture, "bg-title");
+		assert_eq!(image.size.x, 512.0);
+		assert!(image.repeat_x && !image.repeat_y);
+
+		// normal layers just follow the camera
+		let camera_offset = Vector2::new(-100.0, 40.0);
+		let origin = Vector2::new(1000.0, 0.0);
+		let normal = Layer::default();
+		assert_eq!(normal.render_offset(&camera_offset, &origin).x, -100.0);
+
+		// half the camera movement, relative to the map, plus the offset with y flipped
+		let ro = l.render_offset(&camera_offset, &origin);
+		assert_eq!(ro.x, -50.0 - 500.0 + 16.0);
+		assert_eq!(ro.y, 10.0 + 8.0);
+		Ok(())
+	}
+
+	#[test]
+	fn objects_keep_shape_rotation_and_typed_properties() -> anyhow::Result<()> {
+		let otmj: map_tmj::Object = serde_json::from_str(
+			r##"{
+				"name": "door", "class": "Trigger", "id": 7,
+				"x": 100, "y": 200, "width": 0, "height": 0,
+				"rotation": 90, "visible": true,
+				"polygon": [ { "x": 0, "y": 0 }, { "x": 10, "y": 0 }, { "x": 0, "y": 20 } ],
+				"properties": [
+					{ "name": "count", "type": "int", "value": 3 },
+					{ "name": "open", "type": "bool", "value": true },
+					{ "name": "tint", "type": "color", "value": "#80ff0000" },
+					{ "name": "target", "type": "object", "value": 12 },
+					{ "name": "label", "type": "string", "value": "exit" }
+				]
+			}"##,
+		)?;
+		let o: Object = (&otmj).into();
+
+		assert_eq!(*o.id(), 7);
+		assert_eq!(*o.rotation(), 90.0);
+		match o.data() {
+			ObjectData::Polygon { points } => {
+				// rotated clockwise around the object's position
+				assert_eq!(points.len(), 3);
+				assert!(points[1].sub(&Vector2::new(100.0, 210.0)).length() < 0.001);
+				assert!(points[2].sub(&Vector2::new(80.0, 200.0)).length() < 0.001);
+			},
+			d => panic!("Expected polygon, got {:?}", d),
+		}
+
+		assert_eq!(o.property_as_i64("count"), Some(3));
+		assert_eq!(o.property_as_bool("open"), Some(true));
+		assert_eq!(o.property_as_object_ref("target"), Some(12));
+		assert_eq!(o.property("label"), Some("exit"));
+		assert_eq!(o.property("count"), None);
+		assert!(o.property_as_color("tint").is_some());
+
+		let mut o = o;
+		o.hflip(0.0);
+		assert_eq!(*o.rotation(), -90.0);
+
+		Ok(())
+	}
+
+	#[test]
+	fn rotated_tiles_keep_their_bounds_through_hflip() -> anyhow::Result<()> {
+		let otmj: map_tmj::Object = serde_json::from_str(
+			r##"{
+				"name": "sign", "class": "", "id": 8, "gid": 5,
+				"x": 100, "y": 200, "width": 10, "height": 20,
+				"rotation": 90, "visible": true
+			}"##,
+		)?;
+		let mut o: Object = (&otmj).into();
+		let close_to =
+			|a: &Vector2, x: f32, y: f32| (a.x - x).abs() < 0.001 && (a.y - y).abs() < 0.001;
+
+		// rotated clockwise around its bottom left, so it ends up right of, and below it
+		let bounds = o.rotated_bounds().unwrap();
+		assert!(close_to(bounds.center(), 110.0, 205.0));
+		assert!(close_to(bounds.size(), 20.0, 10.0));
+
+		// the rotation follows the object into the y up world
+		let corners = o.rotated_corners().unwrap();
+		o.hflip(0.0);
+		o.translate(&Vector2::new(5.0, 0.0));
+		for after in o.rotated_corners().unwrap().iter() {
+			assert!(corners
+				.iter()
+				.any(|before| close_to(after, before.x + 5.0, -before.y)));
+		}
+		let bounds = o.rotated_bounds().unwrap();
+		assert!(close_to(bounds.center(), 115.0, -205.0));
+		assert!(close_to(bounds.size(), 20.0, 10.0));
+		Ok(())
+	}
+
+	#[test]
+	fn tile_maps_never_share_a_revision() {
+		let mut a = TileMap::new(2, 1);
+		let mut b = TileMap::new(2, 1);
+		assert_ne!(a.revision(), b.revision());
+		for tile in [1, 2] {
+			a.push(tile);
+			b.push(tile);
+		}
+		// same size, and number of changes, but still told apart
+		assert_ne!(a.revision(), b.revision());
+
+		let c = a.clone();
+		assert_eq!(a.revision(), c.revision());
+		a.push(3);
+		assert_ne!(a.revision(), c.revision());
+	}
+
+	#[test]
+	fn gids_split_off_the_flip_flags() {
+		let gid: Gid = (0xa000_0000 | 42).into();
+		assert_eq!(gid.id(), 42);
+		assert!(gid.flip().horizontal && gid.flip().diagonal && !gid.flip().vertical);
+
+		let empty: Gid = 0x8000_0000.into();
+		assert!(empty.is_empty());
+
+		// diagonal and horizontal is a clockwise rotation,
+		// so the top left of the quad shows the bottom left of the image
+		let (x, y, offset) = gid.flip().uv_transform();
+		let top_left = offset;
+		assert_eq!((top_left.x, top_left.y), (0.0, 1.0));
+		let bottom_right = offset.add(&x).add(&y);
+		assert_eq!((bottom_right.x, bottom_right.y), (1.0, 0.0));
+	}
+
+	#[test]
+	fn groups_are_flattened_into_their_children() -> anyhow::Result<()> {
+		let tmj = include_str!("../../fixtures/groups/groups.tmj").to_owned();
+		let tmx = map_tmx::tmx_to_tmj(include_str!("../../fixtures/groups/groups.tmx"))?;
+		for tmj in [tmj, tmx] {
+			let sources = MapSources::load_from_str("groups", tmj, &mut |name| {
+				anyhow::bail!("No tileset expected: {}", name)
+			})?;
+			let mut map = Map::new();
+			map.load_from_sources(&sources)?;
+
+			assert_eq!(map.layers().len(), 3);
+			assert!(map.layer("background").is_none());
+
+			let far = map.layer("background/far").expect("background/far");
+			assert_eq!(far.name(), "far");
+			assert_eq!(*far.opacity(), 0.25);
+			assert_eq!(far.parallax().x, 0.25);
+			assert_eq!((far.offset().x, far.offset().y), (10.0, 20.0));
+			assert_eq!((far.tint().r, far.tint().g), (1.0, 0.0));
+			assert_eq!(far.chunks().len(), 1);
+
+			let collider = map.layer("background/hidden/Collider").expect("Collider");
+			assert!(!*collider.visible());
+			assert!(map
+				.list_objects_in_layer("background/hidden/Collider")
+				.is_empty());
+			match collider.objects()[0].data() {
+				ObjectData::Point { pos } => assert_eq!((pos.x, pos.y), (11.0, 22.0)),
+				d => panic!("Expected a point, got {:?}", d),
+			}
+
+			let player = map.layer("Player").expect("Player");
+			assert!(*player.visible());
+			assert_eq!(map.list_objects_in_layer("Player").len(), 1);
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn embedded_image_tilesets_are_sliced() -> anyhow::Result<()> {
+		let tmj = include_str!("../../fixtures/embedded/atlas.tmj").to_owned();
+		let tmx = map_tmx::tmx_to_tmj(include_str!("../../fixtures/embedded/atlas.tmx"))?;
+		for tmj in [tmj, tmx] {
+			let sources = MapSources::load_from_str("atlas", tmj, &mut |name| {
+				anyhow::bail!("No external tileset expected: {}", name)
+			})?;
+			let mut map = Map::new();
+			map.load_from_sources(&sources)?;
+
+			let image = map.get_tile_image(1 + 5).expect("tile 5");
+			assert_eq!(image.texture, "sheet");
+			// margin 2, spacing 2, so the second row and column start at 36
+			assert_eq!(
+				(image.uv_pos.x, image.uv_pos.y),
+				(36.0 / 136.0, 36.0 / 70.0)
+			);
+			assert_eq!(
+				(image.uv_size.x, image.uv_size.y),
+				(32.0 / 136.0, 32.0 / 70.0)
+			);
+			assert!(map.is_tile_hazard(1 + 5));
+			assert!(!map.is_tile_hazard(1));
+			assert!(map.get_tile_image(1 + 8).is_none());
+
+			// tile 1 shows itself for 0.1s, then tile 2 for 0.3s
+			let frame_at = |t| map.get_tile_image_at(1 + 1, t).map(|i| i.uv_pos.x);
+			assert_eq!(
+				frame_at(0.05),
+				map.get_tile_image(1 + 1).map(|i| i.uv_pos.x)
+			);
+			assert_eq!(frame_at(0.2), Some(70.0 / 136.0));
+			assert_eq!(frame_at(0.45), frame_at(0.05));
+		}
+		Ok(())
+	}
 }
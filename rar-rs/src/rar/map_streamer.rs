@@ -0,0 +1,179 @@
+use std::collections::HashSet;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+
+use oml_game::math::{Rectangle, Vector2};
+use tracing::*;
+
+use crate::rar::map::MapSources;
+use crate::rar::Map;
+
+/// Turns the sources into a map placed at the given world origin, called on the worker.
+pub type MapBuilder = dyn Fn(&MapSources, &Vector2) -> anyhow::Result<Map> + Send + Sync;
+
+struct MapLoadJob {
+	index:   usize,
+	sources: Arc<MapSources>,
+	origin:  Vector2,
+	builder: Arc<MapBuilder>,
+}
+
+#[derive(Debug)]
+pub struct MapLoadResult {
+	pub index: usize,
+	pub map:   anyhow::Result<Map>,
+}
+
+struct MapStreamerWorker {
+	job_tx:    Sender<MapLoadJob>,
+	result_rx: Receiver<MapLoadResult>,
+}
+
+impl std::fmt::Debug for MapStreamerWorker {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+		write!(f, "MapStreamerWorker")
+	}
+}
+
+/// Decides which maps should be loaded for a given frame, and builds them on a worker thread.
+/// Maps are loaded when they get within `load_margin` of the frame, and only unloaded once they
+/// are further away than `unload_margin`, so maps on the border don't flip flop.
+#[derive(Debug)]
+pub struct MapStreamer {
+	load_margin:   Vector2,
+	unload_margin: Vector2,
+	in_flight:     HashSet<usize>,
+	worker:        Option<MapStreamerWorker>,
+}
+
+impl Default for MapStreamer {
+	fn default() -> Self {
+		Self {
+			load_margin:   Vector2::new(512.0, 512.0),
+			unload_margin: Vector2::new(1024.0, 1024.0),
+			in_flight:     HashSet::new(),
+			worker:        None,
+		}
+	}
+}
+
+impl MapStreamer {
+	/// `unload_margin` is never smaller than `load_margin`.
+	pub fn with_margins(mut self, load_margin: &Vector2, unload_margin: &Vector2) -> Self {
+		self.load_margin = *load_margin;
+		self.unload_margin = Vector2::new(
+			unload_margin.x.max(load_margin.x),
+			unload_margin.y.max(load_margin.y),
+		);
+		self
+	}
+
+	pub fn should_load(&self, frame: &Rectangle, bounds: &Rectangle) -> bool {
+		overlaps(&grow(frame, &self.load_margin), bounds)
+	}
+
+	pub fn should_unload(&self, frame: &Rectangle, bounds: &Rectangle) -> bool {
+		!overlaps(&grow(frame, &self.unload_margin), bounds)
+	}
+
+	pub fn is_in_flight(&self, index: usize) -> bool {
+		self.in_flight.contains(&index)
+	}
+
+	pub fn request(
+		&mut self,
+		index: usize,
+		sources: &Arc<MapSources>,
+		origin: &Vector2,
+		builder: Arc<MapBuilder>,
+	) {
+		if !self.in_flight.insert(index) {
+			return;
+		}
+		let worker = self.worker.get_or_insert_with(Self::spawn_worker);
+		let job = MapLoadJob {
+			index,
+			sources: Arc::clone(sources),
+			origin: *origin,
+			builder,
+		};
+		if worker.job_tx.send(job).is_err() {
+			warn!("Map streaming worker is gone, can not load map {}", index);
+			self.in_flight.remove(&index);
+		}
+	}
+
+	/// All maps that finished building since the last call, never blocks.
+	pub fn receive(&mut self) -> Vec<MapLoadResult> {
+		let mut results = Vec::new();
+		if let Some(worker) = &self.worker {
+			while let Ok(r) = worker.result_rx.try_recv() {
+				self.in_flight.remove(&r.index);
+				results.push(r);
+			}
+		}
+		results
+	}
+
+	fn spawn_worker() -> MapStreamerWorker {
+		let (job_tx, job_rx) = channel::<MapLoadJob>();
+		let (result_tx, result_rx) = channel();
+		// the worker ends once the streamer, and with it the job sender, is dropped
+		std::thread::spawn(move || {
+			while let Ok(job) = job_rx.recv() {
+				debug!("Streaming in map {}", job.sources.name());
+				let r = MapLoadResult {
+					index: job.index,
+					map:   (job.builder)(&job.sources, &job.origin),
+				};
+				if result_tx.send(r).is_err() {
+					break;
+				}
+			}
+		});
+		MapStreamerWorker { job_tx, result_rx }
+	}
+}
+
+fn grow(r: &Rectangle, margin: &Vector2) -> Rectangle {
+	Rectangle::default()
+		.with_size(&r.size().add(&margin.scaled(2.0)))
+		.with_center(r.center())
+}
+
+fn overlaps(a: &Rectangle, b: &Rectangle) -> bool {
+	let d = a.center().sub(b.center());
+	let s = a.size().add(b.size()).scaled(0.5);
+	d.x.abs() <= s.x && d.y.abs() <= s.y
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn rect(x: f32, y: f32, w: f32, h: f32) -> Rectangle {
+		Rectangle::default()
+			.with_size(&Vector2::new(w, h))
+			.with_center(&Vector2::new(x, y))
+	}
+
+	#[test]
+	fn margins_have_hysteresis() {
+		let streamer = MapStreamer::default()
+			.with_margins(&Vector2::new(100.0, 100.0), &Vector2::new(300.0, 300.0));
+		let frame = rect(0.0, 0.0, 1000.0, 1000.0);
+
+		let near = rect(1000.0, 0.0, 800.0, 800.0); // 100 away from the frame
+		let middle = rect(1150.0, 0.0, 800.0, 800.0);
+		let far = rect(1500.0, 0.0, 800.0, 800.0);
+
+		assert!(streamer.should_load(&frame, &near));
+		assert!(!streamer.should_unload(&frame, &near));
+
+		assert!(!streamer.should_load(&frame, &middle));
+		assert!(!streamer.should_unload(&frame, &middle));
+
+		assert!(!streamer.should_load(&frame, &far));
+		assert!(streamer.should_unload(&frame, &far));
+	}
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::io::Read;
 
 use derive_getters::Getters;
 use oml_game::system::System;
@@ -9,28 +10,98 @@ use serde::{Deserialize, Serialize};
 use crate::rar::map::TileMap;
 
 #[derive(Debug, Default, Getters, Serialize, Deserialize)]
-#[serde(deny_unknown_fields)]
 pub struct Tileset {
 	firstgid: u32,
+	// empty for embedded tilesets
+	#[serde(default)]
 	source:   String,
 	//	#[serde(rename = "type")]
 	//	tilesettype: String,
+	// everything else is the embedded tileset, in the tsj layout
+	#[serde(flatten)]
+	embedded: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Tileset {
+	pub fn embedded_tsj(&self) -> anyhow::Result<Option<String>> {
+		if !self.source.is_empty() {
+			return Ok(None);
+		}
+		Ok(Some(serde_json::to_string(&self.embedded)?))
+	}
+}
+
+#[derive(Debug, Default, Getters, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ObjectProperty {
+	name:               String,
+	#[serde(rename = "type")]
+	objectpropertytype: String,
+	value:              serde_json::Value,
+	// only set for class properties
+	#[serde(default)]
+	propertytype:       String,
+}
+
+impl ObjectProperty {
+	pub fn value_as_string(&self) -> String {
+		match &self.value {
+			serde_json::Value::String(s) => s.clone(),
+			v => v.to_string(),
+		}
+	}
+}
+
+fn default_pixelsize() -> u32 {
+	16
+}
+
+#[derive(Debug, Default, Getters, Serialize, Deserialize)]
+pub struct ObjectText {
+	text:      String,
+	#[serde(default)]
+	wrap:      bool,
+	#[serde(default = "default_pixelsize")]
+	pixelsize: u32,
+	#[serde(default)]
+	halign:    String,
+	#[serde(default)]
+	valign:    String,
+}
+
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ObjectPoint {
+	pub x: f64,
+	pub y: f64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Object {
-	name:     String,
-	class:    String,
-	id:       u32,
-	x:        f64,
-	y:        f64,
-	height:   f64,
-	width:    f64,
-	rotation: f64, // deg, clockwise
-	visible:  bool,
+	name:       String,
+	class:      String,
+	id:         u32,
+	x:          f64,
+	y:          f64,
+	height:     f64,
+	width:      f64,
+	rotation:   f64, // deg, clockwise
+	visible:    bool,
+	#[serde(default)]
+	point:      bool,
 	#[serde(default)]
-	point:    bool,
+	properties: Vec<ObjectProperty>,
+	#[serde(default)]
+	polyline:   Option<Vec<ObjectPoint>>,
+	#[serde(default)]
+	polygon:    Option<Vec<ObjectPoint>>,
+	#[serde(default)]
+	ellipse:    bool,
+	#[serde(default)]
+	gid:        Option<u32>,
+	#[serde(default)]
+	text:       Option<ObjectText>,
 }
 
 impl Object {
@@ -40,6 +111,9 @@ impl Object {
 	pub fn class(&self) -> &str {
 		&self.class
 	}
+	pub fn id(&self) -> u32 {
+		self.id
+	}
 	pub fn x(&self) -> f64 {
 		self.x
 	}
@@ -55,12 +129,104 @@ impl Object {
 	pub fn point(&self) -> bool {
 		self.point
 	}
+	pub fn properties(&self) -> &Vec<ObjectProperty> {
+		&self.properties
+	}
+	pub fn polyline(&self) -> &Option<Vec<ObjectPoint>> {
+		&self.polyline
+	}
+	pub fn polygon(&self) -> &Option<Vec<ObjectPoint>> {
+		&self.polygon
+	}
+	pub fn ellipse(&self) -> bool {
+		self.ellipse
+	}
+	pub fn gid(&self) -> Option<u32> {
+		self.gid
+	}
+	pub fn text(&self) -> &Option<ObjectText> {
+		&self.text
+	}
+	pub fn rotation(&self) -> f64 {
+		self.rotation
+	}
+}
+
+/// Either a plain list of tile ids for csv, or base64 encoded and maybe compressed bytes.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum LayerData {
+	Csv(Vec<u32>),
+	Encoded(String),
+}
+
+impl Default for LayerData {
+	fn default() -> Self {
+		LayerData::Csv(Vec::new())
+	}
+}
+
+impl LayerData {
+	fn decode(
+		&self,
+		encoding: &str,
+		compression: &str,
+		width: u32,
+		height: u32,
+	) -> anyhow::Result<TileMap> {
+		let count = (width * height) as usize;
+		let mut tiles = TileMap::new(width, height);
+		match self {
+			LayerData::Csv(ids) => {
+				if ids.len() != count {
+					anyhow::bail!("Expected {} tiles in layer data, got {}", count, ids.len());
+				}
+				for t in ids {
+					tiles.push(*t);
+				}
+			},
+			LayerData::Encoded(data) => {
+				if encoding != "base64" {
+					anyhow::bail!("Layer encoding {:?} not supported!", encoding);
+				}
+				let data = base64::decode(data.trim())?;
+				let bytes = decompress(&data, compression)?;
+				if bytes.len() != count * 4 {
+					anyhow::bail!(
+						"Expected {} bytes in layer data, got {}",
+						count * 4,
+						bytes.len()
+					);
+				}
+				for b in bytes.chunks_exact(4) {
+					tiles.push(u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+				}
+			},
+		}
+		Ok(tiles)
+	}
+}
+
+fn decompress(data: &[u8], compression: &str) -> anyhow::Result<Vec<u8>> {
+	let mut bytes = Vec::new();
+	match compression {
+		"" => bytes.extend_from_slice(data),
+		"zlib" => {
+			flate2::read::ZlibDecoder::new(data).read_to_end(&mut bytes)?;
+		},
+		"gzip" => {
+			flate2::read::GzDecoder::new(data).read_to_end(&mut bytes)?;
+		},
+		"zstd" => bytes = zstd::stream::decode_all(data)?,
+		c => anyhow::bail!("Layer compression {:?} not supported!", c),
+	}
+	Ok(bytes)
 }
 
 #[derive(Debug, Default, Getters, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Chunk {
-	data:   String,
+	data:   LayerData,
 	x:      i32,
 	y:      i32,
 	height: u32,
@@ -80,6 +246,9 @@ pub struct Layer {
 	id:          u32,
 	chunks:      Option<Vec<Chunk>>,
 	objects:     Option<Vec<Object>>,
+	// only for finite maps
+	#[serde(default)]
+	data:        Option<LayerData>,
 	#[serde(default)]
 	compression: String,
 	#[serde(default)]
@@ -102,6 +271,30 @@ pub struct Layer {
 	draworder:   String,
 	#[serde(default = "fixed_f32_1")]
 	parallaxx:   Option<f32>,
+	#[serde(default = "fixed_f32_1")]
+	parallaxy:   Option<f32>,
+	#[serde(default)]
+	offsetx:     f32,
+	#[serde(default)]
+	offsety:     f32,
+	#[serde(default)]
+	properties:  Vec<ObjectProperty>,
+	// only for image layers
+	#[serde(default)]
+	image:       String,
+	#[serde(default)]
+	imagewidth:  u32,
+	#[serde(default)]
+	imageheight: u32,
+	#[serde(default)]
+	repeatx:     bool,
+	#[serde(default)]
+	repeaty:     bool,
+	#[serde(default)]
+	tintcolor:   String,
+	// only for group layers
+	#[serde(default)]
+	layers:      Vec<Layer>,
 }
 
 impl Layer {
@@ -117,6 +310,42 @@ impl Layer {
 	pub fn visible(&self) -> bool {
 		self.visible
 	}
+	pub fn opacity(&self) -> f64 {
+		self.opacity
+	}
+	/// "#AARRGGBB" or "#RRGGBB", empty if not tinted.
+	pub fn tintcolor(&self) -> &str {
+		&self.tintcolor
+	}
+	/// The children of a group layer.
+	pub fn layers(&self) -> &Vec<Layer> {
+		&self.layers
+	}
+
+	fn decode_chunks(&mut self) -> anyhow::Result<()> {
+		if let Some(data) = self.data.take() {
+			let tiles = data.decode(&self.encoding, &self.compression, self.width, self.height)?;
+			let chunk = Chunk {
+				x: self.x as i32,
+				y: self.y as i32,
+				height: self.height,
+				width: self.width,
+				tiles,
+				..Default::default()
+			};
+			self.chunks.get_or_insert_with(Vec::new).push(chunk);
+		} else if let Some(chunks) = &mut self.chunks {
+			for c in chunks.iter_mut() {
+				c.tiles = c
+					.data
+					.decode(&self.encoding, &self.compression, c.width, c.height)?;
+			}
+		}
+		for l in self.layers.iter_mut() {
+			l.decode_chunks()?;
+		}
+		Ok(())
+	}
 	pub fn x(&self) -> u32 {
 		self.x
 	}
@@ -135,6 +364,26 @@ impl Layer {
 	pub fn objects(&self) -> &Option<Vec<Object>> {
 		&self.objects
 	}
+	pub fn properties(&self) -> &Vec<ObjectProperty> {
+		&self.properties
+	}
+	/// How fast the layer scrolls compared to the camera, 1.0 for normal layers.
+	pub fn parallax(&self) -> (f32, f32) {
+		(self.parallaxx.unwrap_or(1.0), self.parallaxy.unwrap_or(1.0))
+	}
+	/// In pixels, y down.
+	pub fn offset(&self) -> (f32, f32) {
+		(self.offsetx, self.offsety)
+	}
+	pub fn image(&self) -> &str {
+		&self.image
+	}
+	pub fn image_size(&self) -> (u32, u32) {
+		(self.imagewidth, self.imageheight)
+	}
+	pub fn repeat(&self) -> (bool, bool) {
+		(self.repeatx, self.repeaty)
+	}
 }
 #[derive(Debug, Default, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -197,6 +446,7 @@ impl MapTmj {
 		&self.tilesets
 	}
 
+	/// Decodes the tiles of all chunks, finite layers become a single chunk.
 	fn decode_chunks(&mut self) -> anyhow::Result<()> {
 		/*
 		The base64-encoded and optionally compressed layer data is somewhat more complicated to parse. First you need to base64-decode it, then you may need to decompress it. Now you have an array of bytes, which should be interpreted as an array of unsigned 32-bit integers using little-endian byte ordering.
@@ -204,33 +454,7 @@ impl MapTmj {
 		Whatever format you choose for your layer data, you will always end up with so called “Global Tile IDs” (gids). They are called “global”, since they may refer to a tile from any of the tilesets used by the map. The IDs also contain flipping flags. The tilesets are always stored with increasing firstgids.
 		*/
 		for l in self.layers.iter_mut() {
-			if let Some(chunks) = &mut l.chunks {
-				for c in chunks.iter_mut() {
-					if l.encoding != "base64" {
-						anyhow::bail!("Non base64 layer encoding not supported!");
-					}
-					if l.compression != "" {
-						anyhow::bail!("Compressed layers not supported!");
-					}
-					//let l = c.data.len() / 4;
-					let mut v = TileMap::new(c.width, c.height); //Vec::with_capacity(l);
-
-					let data = base64::decode(&c.data)?;
-					let mut i = 0;
-					for bytes in data.chunks(4) {
-						let t = u32::from_le_bytes(bytes[0..4].try_into()?);
-						print!("{:08X?} ", &t);
-						i += 1;
-						if i % c.width == 0 {
-							println!("");
-						}
-						v.push(t);
-					}
-					c.tiles = v;
-
-					//					dbg!(&c.tiles);
-				}
-			}
+			l.decode_chunks()?;
 		}
 		Ok(())
 	}
@@ -242,6 +466,10 @@ impl MapTmj {
 		//let v: Value = serde_json::from_str(&tmj_string)?;
 		//		dbg!(&v);
 
+		self.load_from_str(&tmj_string)
+	}
+
+	pub fn load_from_str(&mut self, tmj_string: &str) -> anyhow::Result<()> {
 		let tmj: MapTmj = serde_json::from_str(&tmj_string)?;
 		//		dbg!(&tmj);
 
@@ -249,4 +477,74 @@ impl MapTmj {
 		self.decode_chunks()?;
 		Ok(())
 	}
+
+	/// Only the tileset sources, without decoding any of the layers.
+	pub fn tileset_sources_from_str(tmj_string: &str) -> anyhow::Result<Vec<String>> {
+		let tilesets: MapTmjTilesets = serde_json::from_str(&tmj_string)?;
+		Ok(tilesets
+			.tilesets
+			.iter()
+			.filter(|ts| !ts.source().is_empty())
+			.map(|ts| ts.source().to_owned())
+			.collect())
+	}
+}
+
+// everything else is ignored
+#[derive(Debug, Deserialize)]
+struct MapTmjTilesets {
+	tilesets: Vec<Tileset>,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn tiles_of_first_layer(tmj: &str) -> anyhow::Result<Vec<u32>> {
+		let mut map = MapTmj::new();
+		map.load_from_str(tmj)?;
+		let chunks = map.layers()[0].chunks().as_ref().expect("no chunks");
+		assert_eq!(chunks.len(), 1);
+		let tm = chunks[0].tiles();
+		Ok((0..tm.height())
+			.flat_map(|y| (0..tm.width()).map(move |x| (x, y)))
+			.map(|(x, y)| tm.get_xy(x, y))
+			.collect())
+	}
+
+	#[test]
+	fn all_layer_encodings_decode_to_the_same_tiles() -> anyhow::Result<()> {
+		let fixtures = [
+			(
+				"csv",
+				include_str!("../../fixtures/layer_encodings/csv.tmj"),
+			),
+			(
+				"zlib",
+				include_str!("../../fixtures/layer_encodings/zlib.tmj"),
+			),
+			(
+				"gzip",
+				include_str!("../../fixtures/layer_encodings/gzip.tmj"),
+			),
+			(
+				"zstd",
+				include_str!("../../fixtures/layer_encodings/zstd.tmj"),
+			),
+			(
+				"finite",
+				include_str!("../../fixtures/layer_encodings/finite.tmj"),
+			),
+		];
+		for (name, tmj) in fixtures {
+			assert_eq!(tiles_of_first_layer(tmj)?, vec![1, 2, 0, 3], "{}", name);
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn unknown_compression_is_an_error() {
+		let tmj = include_str!("../../fixtures/layer_encodings/zlib.tmj").replace("zlib", "lzma");
+		assert!(MapTmj::new().load_from_str(&tmj).is_err());
+	}
 }
@@ -0,0 +1,307 @@
+use roxmltree::Node;
+use serde_json::{json, Value};
+use tracing::*;
+
+use crate::rar::tiled_xml;
+use crate::rar::tiled_xml::{attr_bool, attr_num, attr_str, child, children, properties};
+
+/// Converts a TMX map into the TMJ layout, so it can be loaded via `MapTmj`.
+pub fn tmx_to_tmj(tmx_string: &str) -> anyhow::Result<String> {
+	let doc = roxmltree::Document::parse(tmx_string)?;
+	let map = doc.root_element();
+	if !map.has_tag_name("map") {
+		anyhow::bail!("Expected <map> got <{}>", map.tag_name().name());
+	}
+
+	let mut layers = Vec::new();
+	let mut tilesets = Vec::new();
+	let mut editorsettings = None;
+	for n in map.children().filter(|n| n.is_element()) {
+		match n.tag_name().name() {
+			"tileset" => {
+				let firstgid = attr_num(&n, "firstgid", 1u32)?;
+				let ts = match n.attribute("source") {
+					Some(source) => json!({ "firstgid": firstgid, "source": source }),
+					None => {
+						let mut ts = tiled_xml::tileset(&n)?;
+						ts["firstgid"] = firstgid.into();
+						ts
+					},
+				};
+				tilesets.push(ts);
+			},
+			"layer" => layers.push(tile_layer(&n)?),
+			"objectgroup" => layers.push(object_group(&n)?),
+			"imagelayer" => layers.push(image_layer(&n)?),
+			"group" => layers.push(group_layer(&n)?),
+			"editorsettings" => {
+				if let Some(chunksize) = child(&n, "chunksize") {
+					editorsettings = Some(json!({
+						"chunksize": {
+							"width": attr_num(&chunksize, "width", 16u32)?,
+							"height": attr_num(&chunksize, "height", 16u32)?,
+						}
+					}));
+				}
+			},
+			"properties" => {},
+			name => {
+				warn!("Skipping unsupported <{}> in map", name);
+			},
+		}
+	}
+
+	let mut tmj = json!({
+		"compressionlevel": attr_num(&map, "compressionlevel", -1i32)?,
+		"width": attr_num(&map, "width", 0u32)?,
+		"height": attr_num(&map, "height", 0u32)?,
+		"infinite": attr_bool(&map, "infinite", false)?,
+		"layers": layers,
+		"nextlayerid": attr_num(&map, "nextlayerid", 1u32)?,
+		"nextobjectid": attr_num(&map, "nextobjectid", 1u32)?,
+		"orientation": attr_str(&map, "orientation"),
+		"renderorder": attr_str(&map, "renderorder"),
+		"tiledversion": attr_str(&map, "tiledversion"),
+		"tileheight": attr_num(&map, "tileheight", 0u32)?,
+		"tilewidth": attr_num(&map, "tilewidth", 0u32)?,
+		"tilesets": tilesets,
+		"type": "map",
+		"version": attr_str(&map, "version"),
+	});
+	if let Some(editorsettings) = editorsettings {
+		tmj["editorsettings"] = editorsettings;
+	}
+
+	Ok(serde_json::to_string(&tmj)?)
+}
+
+fn layer_common(n: &Node, layertype: &str) -> anyhow::Result<Value> {
+	let mut l = json!({
+		"id": attr_num(n, "id", 0u32)?,
+		"name": attr_str(n, "name"),
+		"x": 0,
+		"y": 0,
+		"opacity": attr_num(n, "opacity", 1.0f64)?,
+		"visible": attr_bool(n, "visible", true)?,
+		"type": layertype,
+	});
+	for a in ["parallaxx", "parallaxy", "offsetx", "offsety"] {
+		if let Some(v) = n.attribute(a) {
+			l[a] = v.parse::<f32>()?.into();
+		}
+	}
+	if let Some(tintcolor) = n.attribute("tintcolor") {
+		l["tintcolor"] = tintcolor.into();
+	}
+	let properties = properties(n)?;
+	if !properties.is_empty() {
+		l["properties"] = properties.into();
+	}
+	Ok(l)
+}
+
+fn tile_layer(n: &Node) -> anyhow::Result<Value> {
+	let mut l = layer_common(n, "tilelayer")?;
+	l["width"] = attr_num(n, "width", 0u32)?.into();
+	l["height"] = attr_num(n, "height", 0u32)?.into();
+
+	let data = match child(n, "data") {
+		Some(data) => data,
+		None => anyhow::bail!("Tile layer {} without data", attr_str(n, "name")),
+	};
+	let encoding = attr_str(&data, "encoding");
+	if !encoding.is_empty() {
+		l["encoding"] = encoding.into();
+	}
+	let compression = attr_str(&data, "compression");
+	if !compression.is_empty() {
+		l["compression"] = compression.into();
+	}
+
+	let mut chunks = Vec::new();
+	for c in children(&data, "chunk") {
+		chunks.push(json!({
+			"x": attr_num(&c, "x", 0i32)?,
+			"y": attr_num(&c, "y", 0i32)?,
+			"width": attr_num(&c, "width", 0u32)?,
+			"height": attr_num(&c, "height", 0u32)?,
+			"data": layer_data(&c, encoding)?,
+		}));
+	}
+	if chunks.is_empty() {
+		l["data"] = layer_data(&data, encoding)?;
+	} else {
+		l["chunks"] = chunks.into();
+	}
+	Ok(l)
+}
+
+fn layer_data(n: &Node, encoding: &str) -> anyhow::Result<Value> {
+	match encoding {
+		"csv" => {
+			let mut tiles = Vec::new();
+			for t in n.text().unwrap_or("").split(',') {
+				let t = t.trim();
+				if !t.is_empty() {
+					tiles.push(t.parse::<u32>()?);
+				}
+			}
+			Ok(tiles.into())
+		},
+		"base64" => Ok(n.text().unwrap_or("").trim().into()),
+		// the deprecated plain xml format
+		"" => {
+			let mut tiles = Vec::new();
+			for t in children(n, "tile") {
+				tiles.push(attr_num(&t, "gid", 0u32)?);
+			}
+			Ok(tiles.into())
+		},
+		e => anyhow::bail!("Layer encoding {:?} not supported!", e),
+	}
+}
+
+fn group_layer(n: &Node) -> anyhow::Result<Value> {
+	let mut l = layer_common(n, "group")?;
+	let mut layers = Vec::new();
+	for c in n.children().filter(|c| c.is_element()) {
+		match c.tag_name().name() {
+			"layer" => layers.push(tile_layer(&c)?),
+			"objectgroup" => layers.push(object_group(&c)?),
+			"imagelayer" => layers.push(image_layer(&c)?),
+			"group" => layers.push(group_layer(&c)?),
+			"properties" => {},
+			name => warn!("Skipping unsupported <{}> in group", name),
+		}
+	}
+	l["layers"] = layers.into();
+	Ok(l)
+}
+
+fn image_layer(n: &Node) -> anyhow::Result<Value> {
+	let mut l = layer_common(n, "imagelayer")?;
+	l["repeatx"] = attr_bool(n, "repeatx", false)?.into();
+	l["repeaty"] = attr_bool(n, "repeaty", false)?.into();
+	if let Some(image) = child(n, "image") {
+		l["image"] = attr_str(&image, "source").into();
+		l["imagewidth"] = attr_num(&image, "width", 0u32)?.into();
+		l["imageheight"] = attr_num(&image, "height", 0u32)?.into();
+	}
+	Ok(l)
+}
+
+fn object_group(n: &Node) -> anyhow::Result<Value> {
+	let mut l = layer_common(n, "objectgroup")?;
+	l["draworder"] = match attr_str(n, "draworder") {
+		"" => "topdown",
+		d => d,
+	}
+	.into();
+	let mut objects = Vec::new();
+	for o in children(n, "object") {
+		objects.push(object(&o)?);
+	}
+	l["objects"] = objects.into();
+	Ok(l)
+}
+
+fn object(n: &Node) -> anyhow::Result<Value> {
+	// before 1.9 the class was called type
+	let class = n.attribute("class").or_else(|| n.attribute("type"));
+	let mut o = json!({
+		"id": attr_num(n, "id", 0u32)?,
+		"name": attr_str(n, "name"),
+		"class": class.unwrap_or(""),
+		"x": attr_num(n, "x", 0.0f64)?,
+		"y": attr_num(n, "y", 0.0f64)?,
+		"width": attr_num(n, "width", 0.0f64)?,
+		"height": attr_num(n, "height", 0.0f64)?,
+		"rotation": attr_num(n, "rotation", 0.0f64)?,
+		"visible": attr_bool(n, "visible", true)?,
+	});
+	if let Some(gid) = n.attribute("gid") {
+		o["gid"] = gid.parse::<u32>()?.into();
+	}
+	let properties = properties(n)?;
+	if !properties.is_empty() {
+		o["properties"] = properties.into();
+	}
+	for c in n.children().filter(|c| c.is_element()) {
+		match c.tag_name().name() {
+			"point" => o["point"] = true.into(),
+			"ellipse" => o["ellipse"] = true.into(),
+			"polygon" => o["polygon"] = points(&c)?,
+			"polyline" => o["polyline"] = points(&c)?,
+			"text" => o["text"] = text(&c)?,
+			_ => {},
+		}
+	}
+	Ok(o)
+}
+
+// "0,0 10,0 0,20"
+fn points(n: &Node) -> anyhow::Result<Value> {
+	let mut r = Vec::new();
+	for p in attr_str(n, "points").split_whitespace() {
+		let (x, y) = match p.split_once(',') {
+			Some(xy) => xy,
+			None => anyhow::bail!("Invalid point {:?}", p),
+		};
+		r.push(json!({ "x": x.parse::<f64>()?, "y": y.parse::<f64>()? }));
+	}
+	Ok(r.into())
+}
+
+fn text(n: &Node) -> anyhow::Result<Value> {
+	Ok(json!({
+		"text": n.text().unwrap_or(""),
+		"wrap": attr_bool(n, "wrap", false)?,
+		"pixelsize": attr_num(n, "pixelsize", 16u32)?,
+		"halign": attr_str(n, "halign"),
+		"valign": attr_str(n, "valign"),
+	}))
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::rar::map::{Map, MapSources};
+
+	fn load(tmj: String, tileset: &str) -> anyhow::Result<Map> {
+		let sources = MapSources::load_from_str("mixed", tmj, &mut |_| Ok(tileset.to_owned()))?;
+		let mut map = Map::new();
+		map.load_from_sources(&sources)?;
+		Ok(map)
+	}
+
+	// the debug output skips the tiles
+	fn describe(map: &Map) -> String {
+		let mut d = format!("{:#?}", map);
+		for l in map.layers() {
+			for c in l.chunks() {
+				let tm = c.tile_map();
+				for y in 0..tm.height() {
+					for x in 0..tm.width() {
+						d += &format!("{} ", tm.get_xy(x, y));
+					}
+				}
+			}
+		}
+		d
+	}
+
+	#[test]
+	fn tmx_and_tmj_produce_the_same_map() -> anyhow::Result<()> {
+		let tmj = load(
+			include_str!("../../fixtures/tiled_xml/mixed.tmj").to_owned(),
+			include_str!("../../fixtures/tiled_xml/blocks.tsj"),
+		)?;
+		let tmx = load(
+			super::tmx_to_tmj(include_str!("../../fixtures/tiled_xml/mixed.tmx"))?,
+			&crate::rar::tileset::tsx_to_tsj(include_str!("../../fixtures/tiled_xml/blocks.tsx"))?,
+		)?;
+
+		assert_eq!(tmj.layers().len(), 4);
+		assert_eq!(describe(&tmx), describe(&tmj));
+		Ok(())
+	}
+}
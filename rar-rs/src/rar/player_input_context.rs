@@ -1,9 +1,242 @@
-#[derive(Debug, Default)]
+use oml_game::math::Vector2;
 
+use crate::rar::input_actions::{InputActionSet, InputActionState};
+
+// how far the stick has to be pushed up or down to count as pressed
+const STICK_VERTICAL_THRESHOLD: f32 = 0.5;
+
+/// The state of a single button, including edges and how long it has been held.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct InputButton {
+	pub is_down:      bool,
+	/// only true in the update the button went down
+	pub was_pressed:  bool,
+	/// only true in the update the button went up
+	pub was_released: bool,
+	/// seconds the button has been held, zero while it is up
+	pub hold_time:    f64,
+}
+
+impl InputButton {
+	fn update(&mut self, is_down: bool, time_step: f64) {
+		self.was_pressed = is_down && !self.is_down;
+		self.was_released = !is_down && self.is_down;
+		self.hold_time = if !is_down {
+			0.0
+		} else if self.was_pressed {
+			// count the frame it went down, so a single tap has a non zero hold time
+			time_step
+		} else {
+			self.hold_time + time_step
+		};
+		self.is_down = is_down;
+	}
+}
+
+#[derive(Debug, Default, Copy, Clone)]
 pub struct PlayerInputContext {
-	pub is_left_pressed:  bool,
-	pub is_right_pressed: bool,
-	pub is_up_pressed:    bool,
-	pub is_down_pressed:  bool,
-	pub is_jump_pressed:  bool,
+	pub left:       InputButton,
+	pub right:      InputButton,
+	pub up:         InputButton,
+	pub down:       InputButton,
+	pub jump:       InputButton,
+	/// -1.0 (left) .. 1.0 (right), with the dead zone already applied
+	pub horizontal: f32,
+}
+
+/// Raw input for one player for one frame, e.g. from the keyboard, a gamepad, or touch controls.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct PlayerInputSample {
+	pub left:       bool,
+	pub right:      bool,
+	pub up:         bool,
+	pub down:       bool,
+	pub jump:       bool,
+	/// -1.0 .. 1.0 before the dead zone
+	pub horizontal: f32,
+}
+
+impl PlayerInputSample {
+	pub fn from_input_state(input_state: &InputActionState, player_index: u8) -> Self {
+		let set = InputActionSet::Player(player_index);
+		Self {
+			left:       input_state.is_down(set, "move_left"),
+			right:      input_state.is_down(set, "move_right"),
+			up:         input_state.is_down(set, "move_up"),
+			down:       input_state.is_down(set, "move_down"),
+			jump:       input_state.is_down(set, "jump"),
+			horizontal: 0.0,
+		}
+	}
+
+	/// Applies an on screen control, `stick` drives the axis, the others are plain buttons.
+	/// Returns false for unknown controls.
+	pub fn apply_virtual_input(&mut self, control: &str, axis: &Vector2, is_down: bool) -> bool {
+		match control {
+			"stick" => {
+				self.horizontal = axis.x;
+				self.up = axis.y > STICK_VERTICAL_THRESHOLD;
+				self.down = axis.y < -STICK_VERTICAL_THRESHOLD;
+			},
+			"left" => self.left = is_down,
+			"right" => self.right = is_down,
+			"up" => self.up = is_down,
+			"down" => self.down = is_down,
+			"jump" => self.jump = is_down,
+			_ => return false,
+		}
+		true
+	}
+
+	/// Combines two sources, e.g. keyboard and gamepad, the stronger axis wins.
+	pub fn merge(&self, other: &Self) -> Self {
+		let horizontal = if other.horizontal.abs() > self.horizontal.abs() {
+			other.horizontal
+		} else {
+			self.horizontal
+		};
+		Self {
+			left: self.left || other.left,
+			right: self.right || other.right,
+			up: self.up || other.up,
+			down: self.down || other.down,
+			jump: self.jump || other.jump,
+			horizontal,
+		}
+	}
+}
+
+/// Turns per frame samples into a `PlayerInputContext`, one per player.
+#[derive(Debug, Copy, Clone)]
+pub struct PlayerInputTracker {
+	dead_zone: f32,
+	context:   PlayerInputContext,
+}
+
+impl Default for PlayerInputTracker {
+	fn default() -> Self {
+		Self {
+			dead_zone: 0.2,
+			context:   PlayerInputContext::default(),
+		}
+	}
+}
+
+impl PlayerInputTracker {
+	pub fn with_dead_zone(mut self, dead_zone: f32) -> Self {
+		self.dead_zone = dead_zone.clamp(0.0, 0.99);
+		self
+	}
+
+	pub fn context(&self) -> &PlayerInputContext {
+		&self.context
+	}
+
+	pub fn reset(&mut self) {
+		self.context = PlayerInputContext::default();
+	}
+
+	pub fn update(&mut self, sample: &PlayerInputSample, time_step: f64) -> PlayerInputContext {
+		let digital = match (sample.left, sample.right) {
+			(true, false) => -1.0,
+			(false, true) => 1.0,
+			_ => 0.0,
+		};
+		let analog = apply_dead_zone(sample.horizontal, self.dead_zone);
+		let horizontal = (digital + analog).clamp(-1.0, 1.0);
+
+		let c = &mut self.context;
+		c.horizontal = horizontal;
+		c.left.update(horizontal < 0.0, time_step);
+		c.right.update(horizontal > 0.0, time_step);
+		c.up.update(sample.up, time_step);
+		c.down.update(sample.down, time_step);
+		c.jump.update(sample.jump, time_step);
+
+		self.context
+	}
+}
+
+// rescales the remaining range, so we still get the full -1..1
+fn apply_dead_zone(v: f32, dead_zone: f32) -> f32 {
+	let v = v.clamp(-1.0, 1.0);
+	if v.abs() <= dead_zone {
+		0.0
+	} else {
+		v.signum() * (v.abs() - dead_zone) / (1.0 - dead_zone)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn edges_and_hold_time_work() {
+		let mut tracker = PlayerInputTracker::default();
+		let pressed = PlayerInputSample {
+			jump: true,
+			..Default::default()
+		};
+		let released = PlayerInputSample::default();
+
+		let c = tracker.update(&pressed, 0.5);
+		assert!(c.jump.is_down && c.jump.was_pressed && !c.jump.was_released);
+		assert_eq!(c.jump.hold_time, 0.5);
+
+		let c = tracker.update(&pressed, 0.5);
+		assert!(c.jump.is_down && !c.jump.was_pressed);
+		assert_eq!(c.jump.hold_time, 1.0);
+
+		let c = tracker.update(&released, 0.5);
+		assert!(!c.jump.is_down && c.jump.was_released);
+		assert_eq!(c.jump.hold_time, 0.0);
+
+		let c = tracker.update(&released, 0.5);
+		assert!(!c.jump.was_released);
+	}
+
+	#[test]
+	fn horizontal_axis_uses_dead_zone() {
+		let mut tracker = PlayerInputTracker::default().with_dead_zone(0.5);
+		let sample = |horizontal| PlayerInputSample {
+			horizontal,
+			..Default::default()
+		};
+
+		let c = tracker.update(&sample(0.4), 0.1);
+		assert_eq!(c.horizontal, 0.0);
+		assert!(!c.right.is_down);
+
+		let c = tracker.update(&sample(0.75), 0.1);
+		assert_eq!(c.horizontal, 0.5);
+		assert!(c.right.was_pressed);
+
+		let c = tracker.update(&sample(-2.0), 0.1);
+		assert_eq!(c.horizontal, -1.0);
+		assert!(c.left.was_pressed && c.right.was_released);
+
+		// digital input still gives the full range
+		let keys = PlayerInputSample {
+			right: true,
+			..Default::default()
+		};
+		let c = tracker.update(&keys.merge(&sample(0.1)), 0.1);
+		assert_eq!(c.horizontal, 1.0);
+	}
+
+	#[test]
+	fn virtual_input_drives_the_sample() {
+		let mut sample = PlayerInputSample::default();
+		assert!(sample.apply_virtual_input("stick", &Vector2::new(-0.6, 0.7), true));
+		assert!(sample.apply_virtual_input("jump", &Vector2::zero(), true));
+		assert!(!sample.apply_virtual_input("fly", &Vector2::zero(), true));
+		assert_eq!(sample.horizontal, -0.6);
+		assert!(sample.up && !sample.down && sample.jump);
+
+		// releasing the stick centers it
+		sample.apply_virtual_input("stick", &Vector2::zero(), false);
+		assert_eq!(sample.horizontal, 0.0);
+		assert!(!sample.up);
+	}
 }
@@ -45,6 +45,7 @@ use crate::rar::game_state_debug_collisions::GameStateDebugCollisions;
 use crate::rar::game_state_game::GameStateGame;
 use crate::rar::game_state_menu::GameStateMenu;
 use crate::rar::game_state_settings::GameStateSettings;
+use crate::rar::input_actions::{InputActionSet, InputActionState, InputConfig};
 use crate::rar::layer_ids::LayerId;
 use crate::rar::AppUpdateContext;
 use crate::rar::AudioMessage;
@@ -104,6 +105,8 @@ pub struct RarApp<'a> {
 	pause_update:        bool,
 	slow_motion_divider: u32,
 
+	input_state: InputActionState,
+
 	egui: RarAppEgui,
 }
 
@@ -149,6 +152,8 @@ impl Default for RarApp<'_> {
 			pause_update: false,
 			slow_motion_divider: 1,
 
+			input_state: InputActionState::default(),
+
 			egui: RarAppEgui::default(),
 		}
 	}
@@ -209,6 +214,44 @@ impl RarApp<'_> {
 		}
 	}
 
+	fn load_input_config(&mut self) {
+		let config = match InputConfig::from_config_asset(&mut self.system, "input") {
+			Some(config) => config,
+			None => {
+				// :TODO: create fallback?
+				warn!("No input config found, nothing will be bound!");
+				InputConfig::default()
+			},
+		};
+		if let Some(data) = self.system.data() {
+			if let Some(data) = data.as_any().downcast_ref::<RarData>() {
+				data.input
+					.write()
+					.and_then(|mut input| {
+						input.config = config;
+						Ok(())
+					})
+					.unwrap();
+			}
+		}
+	}
+
+	fn evaluate_input(&self, wuc: &WindowUpdateContext) -> InputActionState {
+		if let Some(data) = self.system.data() {
+			if let Some(data) = data.as_any().downcast_ref::<RarData>() {
+				if let Ok(input) = data.input.read() {
+					let state = input.config.evaluate(wuc, &self.input_state);
+					// don't trigger anything with the key that is being bound
+					if input.rebinding.is_some() {
+						return state.without_presses();
+					}
+					return state;
+				}
+			}
+		}
+		InputActionState::default()
+	}
+
 	fn game_state(&mut self) -> &mut Box<dyn GameState> {
 		match self.game_states.get_mut(&self.active_game_state) {
 			Some(gs) => return gs,
@@ -326,6 +369,13 @@ impl RarApp<'_> {
 			&Color::pal_next(),
 			|y| *y * 10.0,
 		);
+		Self::render_trace::<f32, _>(
+			debug_renderer,
+			"world.tiles.rendered",
+			1.5,
+			&Color::pal_next(),
+			|y| *y * 0.1,
+		);
 
 		Self::render_trace_pairs::<f64, _>(
 			debug_renderer,
@@ -493,6 +543,8 @@ impl App for RarApp<'_> {
 
 		println!("Something: {}", &something);
 
+		self.load_input_config();
+
 		let mut lfs = FilesystemLayered::new();
 		let doc_dir = System::get_document_dir("rar-rs");
 		self.add_filesystem_disk(&mut lfs, &doc_dir, true);
@@ -667,6 +719,10 @@ impl App for RarApp<'_> {
 		}
 		self.total_time += wuc.time_step;
 
+		self.input_state = self.evaluate_input(wuc);
+		let input = self.input_state.clone();
+		let debug_action = |action: &str| input.was_pressed(InputActionSet::Debug, action);
+
 		if wuc.is_escape_pressed {
 			self.is_done = true;
 		}
@@ -677,30 +733,30 @@ impl App for RarApp<'_> {
 		*/
 
 		// the generic DebugRenderer
-		if wuc.was_key_pressed('\\' as u8) {
+		if debug_action("toggle_global_debug_renderer") {
 			debug_renderer::debug_renderer_toggle(
 				LayerId::DebugRenderer as u8,
 				EffectId::Colored as u16,
 			);
 		}
 
-		if wuc.was_key_pressed('=' as u8) {
+		if debug_action("cycle_ui_debug_mode") {
 			UiDebugConfig::write_then(&mut |ui_debug_config| {
 				ui_debug_config.cycle_mode();
 			});
 		}
 
-		if wuc.was_key_pressed('i' as u8) {
+		if debug_action("slow_motion_increase") {
 			self.slow_motion_divider *= 2;
 		}
 
-		if wuc.was_key_pressed('u' as u8) {
+		if debug_action("slow_motion_decrease") {
 			self.slow_motion_divider /= 2;
 		}
 
 		self.slow_motion_divider = self.slow_motion_divider.clamp(1, 16);
 
-		if wuc.was_function_key_pressed(2) {
+		if debug_action("dump_ui_config") {
 			if let Some(game_state) = self.game_states.get_mut(&self.active_game_state) {
 				let yaml = game_state.ui_to_yaml_config_string();
 				debug!("{}", &yaml);
@@ -708,17 +764,17 @@ impl App for RarApp<'_> {
 			}
 		}
 
-		if wuc.was_function_key_pressed(5) {
+		if debug_action("reload") {
 			if let Some(game_state) = self.game_states.get_mut(&self.active_game_state) {
 				game_state.reload(&mut self.system)?;
 			}
 		}
 
-		if wuc.was_function_key_pressed(11) {
+		if debug_action("screenshot_sequence") {
 			self.screenshot_sequence_requested = true;
 		}
 
-		if wuc.was_function_key_pressed(12) {
+		if debug_action("screenshot") {
 			tracing::debug!("F12 -> Screenshot");
 			self.screenshot_requested = true;
 		}
@@ -726,7 +782,7 @@ impl App for RarApp<'_> {
 		debug_renderer::debug_renderer_begin_frame();
 
 		// the specific DebugRenderer
-		if wuc.was_key_pressed(']' as u8) {
+		if debug_action("toggle_debug_renderer") {
 			if self.debug_renderer.is_none() {
 				self.debug_renderer = Rc::new(Some(RefCell::new(DebugRenderer::new(
 					LayerId::DebugRenderer as u8,
@@ -739,10 +795,7 @@ impl App for RarApp<'_> {
 			}
 		}
 
-		if wuc.was_key_pressed('^' as u8) {
-			self.debug_zoomed_out = !self.debug_zoomed_out;
-		}
-		if wuc.was_key_pressed('/' as u8) {
+		if debug_action("toggle_debug_zoom") {
 			self.debug_zoomed_out = !self.debug_zoomed_out;
 		}
 
@@ -820,7 +873,7 @@ impl App for RarApp<'_> {
 			wuc.fake_mouse_button_press(0);
 		}
 
-		if wuc.was_key_pressed('f' as u8) {
+		if debug_action("add_fun_point") {
 			self.fun.push(self.cursor_pos.clone());
 		}
 
@@ -847,6 +900,7 @@ impl App for RarApp<'_> {
 			.set_sound_tx(self.sound_tx.clone())
 			.with_is_music_playing(self.audio.is_music_playing())
 			.with_is_sound_enabled(self.is_sound_enabled)
+			.with_input_state(self.input_state.clone())
 			.with_ui_update_context(Box::new(ruuc));
 
 		if let Some(data) = self.system.data() {
@@ -888,6 +942,16 @@ impl App for RarApp<'_> {
 					debug!("StartGame");
 					self.next_game_states.push_back(GameStates::Game);
 				},
+				"RestartGame" => {
+					// tears down and sets up the game again, keeping the selected world
+					debug!("RestartGame");
+					self.next_game_states.push_back(GameStates::Game);
+				},
+				"GotoWorldSelection" => {
+					// :TODO: the world selection lives in the main menu for now
+					debug!("GotoWorldSelection");
+					self.next_game_states.push_back(GameStates::Menu);
+				},
 				"SelectWorld" => {
 					debug!("SelectWorld");
 					debug!("{:?}", &r);
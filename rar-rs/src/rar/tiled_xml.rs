@@ -0,0 +1,147 @@
+use std::str::FromStr;
+
+use roxmltree::Node;
+use serde_json::{json, Value};
+
+// Helpers shared by the TMX and TSX loaders.
+// Both turn Tiled's XML into the JSON layout, so everything after parsing is shared.
+
+pub fn attr_str<'a>(node: &Node<'a, '_>, name: &str) -> &'a str {
+	node.attribute(name).unwrap_or("")
+}
+
+pub fn attr_num<T>(node: &Node, name: &str, default: T) -> anyhow::Result<T>
+where
+	T: FromStr,
+	<T as FromStr>::Err: std::error::Error + Send + Sync + 'static,
+{
+	match node.attribute(name) {
+		Some(v) => Ok(v.trim().parse::<T>()?),
+		None => Ok(default),
+	}
+}
+
+/// Tiled writes flags as "0" and "1".
+pub fn attr_bool(node: &Node, name: &str, default: bool) -> anyhow::Result<bool> {
+	match node.attribute(name) {
+		Some("1") | Some("true") => Ok(true),
+		Some("0") | Some("false") => Ok(false),
+		Some(v) => anyhow::bail!("Invalid flag {}={:?}", name, v),
+		None => Ok(default),
+	}
+}
+
+pub fn child<'a, 'i>(node: &Node<'a, 'i>, name: &str) -> Option<Node<'a, 'i>> {
+	node.children().find(|c| c.has_tag_name(name))
+}
+
+pub fn children<'a, 'i>(node: &Node<'a, 'i>, name: &'a str) -> impl Iterator<Item = Node<'a, 'i>> {
+	node.children().filter(move |c| c.has_tag_name(name))
+}
+
+/// The `<properties>` of the node, in the same layout as the JSON formats.
+pub fn properties(node: &Node) -> anyhow::Result<Vec<Value>> {
+	let mut r = Vec::new();
+	if let Some(properties) = child(node, "properties") {
+		for p in children(&properties, "property") {
+			let ptype = property_type(&p);
+			let mut v = json!({
+				"name": attr_str(&p, "name"),
+				"type": ptype,
+				"value": property_value(&p)?,
+			});
+			if ptype == "class" {
+				v["propertytype"] = attr_str(&p, "propertytype").into();
+			}
+			r.push(v);
+		}
+	}
+	Ok(r)
+}
+
+fn property_type<'a>(p: &Node<'a, '_>) -> &'a str {
+	match attr_str(p, "type") {
+		"" => "string",
+		t => t,
+	}
+}
+
+fn property_value(p: &Node) -> anyhow::Result<Value> {
+	let ptype = property_type(p);
+	if ptype == "class" {
+		// members are nested properties, without their types
+		let mut members = serde_json::Map::new();
+		if let Some(properties) = child(p, "properties") {
+			for m in children(&properties, "property") {
+				members.insert(attr_str(&m, "name").to_owned(), property_value(&m)?);
+			}
+		}
+		return Ok(Value::Object(members));
+	}
+	// multi line strings are stored as text
+	let raw = p.attribute("value").or_else(|| p.text()).unwrap_or("");
+	Ok(match ptype {
+		"int" | "object" => raw.parse::<i64>()?.into(),
+		"float" => raw.parse::<f64>()?.into(),
+		"bool" => (raw == "true").into(),
+		_ => raw.into(),
+	})
+}
+
+/// A `<tileset>`, either from a TSX, or embedded in a TMX.
+pub fn tileset(ts: &Node) -> anyhow::Result<Value> {
+	let mut tiles = Vec::new();
+	for t in children(ts, "tile") {
+		let mut tile = json!({
+			"id": attr_num(&t, "id", 0u32)?,
+		});
+		// tiles of image based tilesets have no image of their own
+		if let Some(image) = child(&t, "image") {
+			tile["image"] = attr_str(&image, "source").into();
+			tile["imagewidth"] = attr_num(&image, "width", 0u32)?.into();
+			tile["imageheight"] = attr_num(&image, "height", 0u32)?.into();
+		}
+		let properties = properties(&t)?;
+		if !properties.is_empty() {
+			tile["properties"] = properties.into();
+		}
+		if let Some(animation) = child(&t, "animation") {
+			let mut frames = Vec::new();
+			for f in children(&animation, "frame") {
+				frames.push(json!({
+					"tileid": attr_num(&f, "tileid", 0u32)?,
+					"duration": attr_num(&f, "duration", 0u32)?,
+				}));
+			}
+			tile["animation"] = frames.into();
+		}
+		tiles.push(tile);
+	}
+
+	let mut tsj = json!({
+		"columns": attr_num(ts, "columns", 0u32)?,
+		"margin": attr_num(ts, "margin", 0u32)?,
+		"name": attr_str(ts, "name"),
+		"spacing": attr_num(ts, "spacing", 0u32)?,
+		"tilecount": attr_num(ts, "tilecount", 0u32)?,
+		"tiledversion": attr_str(ts, "tiledversion"),
+		"tilewidth": attr_num(ts, "tilewidth", 0u32)?,
+		"tileheight": attr_num(ts, "tileheight", 0u32)?,
+		"type": "tileset",
+		"version": attr_str(ts, "version"),
+		"tiles": tiles,
+	});
+	if let Some(grid) = child(ts, "grid") {
+		tsj["grid"] = json!({
+			"width": attr_num(&grid, "width", 0u32)?,
+			"height": attr_num(&grid, "height", 0u32)?,
+			"orientation": attr_str(&grid, "orientation"),
+		});
+	}
+	if let Some(image) = child(ts, "image") {
+		tsj["image"] = attr_str(&image, "source").into();
+		tsj["imagewidth"] = attr_num(&image, "width", 0u32)?.into();
+		tsj["imageheight"] = attr_num(&image, "height", 0u32)?.into();
+	}
+	Ok(tsj)
+}
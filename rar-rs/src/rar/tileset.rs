@@ -1,37 +1,88 @@
+use std::collections::HashMap;
+
 use derive_getters::Getters;
+use oml_game::math::Vector2;
 use oml_game::system::System;
 use tracing::*;
 
+/// Where to find a tile, either a whole texture, or a part of a tileset image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileImage {
+	pub texture: String,
+	/// 0..1, y down
+	pub uv_pos:  Vector2,
+	pub uv_size: Vector2,
+}
+
+impl TileImage {
+	pub fn whole(texture: &str) -> Self {
+		Self {
+			texture: texture.to_owned(),
+			uv_pos:  Vector2::zero(),
+			uv_size: Vector2::new(1.0, 1.0),
+		}
+	}
+
+	pub fn is_whole(&self) -> bool {
+		let (p, s) = (&self.uv_pos, &self.uv_size);
+		p.x == 0.0 && p.y == 0.0 && s.x == 1.0 && s.y == 1.0
+	}
+}
+
+impl Default for TileImage {
+	fn default() -> Self {
+		Self::whole("")
+	}
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TileAnimationFrame {
+	/// in the same tileset
+	pub tile_id:  u32,
+	/// seconds
+	pub duration: f64,
+}
+
 #[derive(Debug, Default, Getters)]
 pub struct Tile {
 	id:          u32,
-	image:       String,
+	image:       TileImage,
 	imagewidth:  u32,
 	imageheight: u32,
+	hazard:      bool,
+	animation:   Vec<TileAnimationFrame>,
 }
 
 #[derive(Debug, Default, Getters)]
 pub struct Tileset {
-	columns:     u32,
-	name:        String,
-	tilecount:   u32,
-	tilewidth:   u32,
-	tileheight:  u32,
-	tiles:       Vec<Tile>, // :TODO: make hashmap based on id?
-	remove_path: bool,
+	columns:      u32,
+	name:         String,
+	tilecount:    u32,
+	tilewidth:    u32,
+	tileheight:   u32,
+	tiles:        Vec<Tile>,
+	// tile id -> index into tiles
+	#[getter(skip)]
+	tile_indices: HashMap<u32, usize>,
+	remove_path:  bool,
+}
+
+// "../tiles/grass.png" -> "grass", textures are referenced without extension
+fn texture_name(image: &str) -> &str {
+	image.split(".").nth(0).unwrap_or(image)
 }
 
 impl Tile {
 	pub fn without_path(mut self) -> Self {
-		let s = if let Some(idx) = self.image.rfind("/") {
+		let s = if let Some(idx) = self.image.texture.rfind("/") {
 			idx + 1
 		} else {
 			0
 		};
 
-		let image = self.image[s..].to_string();
+		let image = self.image.texture[s..].to_string();
 		debug!("Tile: {}", &image);
-		self.image = image;
+		self.image.texture = image;
 		todo!();
 		self
 	}
@@ -57,52 +108,116 @@ impl Tileset {
 			tile
 		};
 
+		self.tile_indices.insert(tile.id, self.tiles.len());
 		self.tiles.push(tile);
 	}
 
+	pub fn get_tile(&self, tid: u32) -> Option<&Tile> {
+		self.tile_indices.get(&tid).map(|&i| &self.tiles[i])
+	}
+
 	pub fn load(&mut self, system: &mut System, name: &str) -> anyhow::Result<()> {
-		//		return anyhow::bail!("Just testing...");
+		let tsj_string = Self::read_as_tsj(system, name)?;
+		self.load_from_str(&tsj_string)
+	}
 
+	/// Probes for `.tsj`, then `.tsx`, and returns the tileset in the TSJ layout.
+	pub fn read_as_tsj(system: &mut System, name: &str) -> anyhow::Result<String> {
 		let tsj_name = format!("{}.tsj", &name);
-		dbg!(&tsj_name);
 		if system.default_filesystem().exists(&tsj_name) {
-			println!("Trying to load tileset from {}", &tsj_name);
-			let mut tileset_tsj = TilesetTsj::new();
-			tileset_tsj.load(system, &tsj_name)?;
-
-			dbg!(&tileset_tsj);
-			if self.remove_path {
-				tileset_tsj.remove_paths();
-			}
-
-			*self = tileset_tsj.into();
-			Ok(())
-		} else {
-			anyhow::bail!("No remaining loader for tileset: {}", &name);
+			debug!("Loading tileset from {}", &tsj_name);
+			let mut tsj_file = system.default_filesystem_mut().open(&tsj_name);
+			return Ok(tsj_file.read_as_string());
+		}
+		let tsx_name = format!("{}.tsx", &name);
+		if system.default_filesystem().exists(&tsx_name) {
+			debug!("Loading tileset from {}", &tsx_name);
+			let mut tsx_file = system.default_filesystem_mut().open(&tsx_name);
+			return tsx_to_tsj(&tsx_file.read_as_string());
 		}
+		anyhow::bail!("No remaining loader for tileset: {}", &name);
 	}
 
-	pub fn get_tile_image(&self, tid: u32) -> &str {
-		if let Some(tile) = self.tiles.iter().find(|&t| t.id == tid) {
-			&tile.image
-		} else {
-			""
+	pub fn load_from_str(&mut self, tsj_string: &str) -> anyhow::Result<()> {
+		let mut tileset_tsj = TilesetTsj::new();
+		tileset_tsj.load_from_str(tsj_string)?;
+		if self.remove_path {
+			tileset_tsj.remove_paths();
 		}
+
+		*self = tileset_tsj.into();
+		Ok(())
+	}
+
+	pub fn is_tile_hazard(&self, tid: u32) -> bool {
+		self.get_tile(tid).map_or(false, |t| t.hazard)
+	}
+
+	pub fn get_tile_image(&self, tid: u32) -> Option<&TileImage> {
+		self.get_tile(tid)
+			.map(|t| &t.image)
+			.filter(|i| !i.texture.is_empty())
 	}
 }
 
 impl From<&tileset_tsj::Tile> for Tile {
 	fn from(ttsj: &tileset_tsj::Tile) -> Self {
-		let image = ttsj.image();
 		Self {
 			id:          *ttsj.id(),
-			image:       image.split(".").nth(0).unwrap_or(image).to_owned(),
+			image:       TileImage::whole(texture_name(ttsj.image())),
 			imagewidth:  *ttsj.imagewidth(),
 			imageheight: *ttsj.imageheight(),
+			hazard:      ttsj.property("hazard").map_or(false, |p| p.as_bool()),
+			animation:   animation_frames(ttsj),
 		}
 	}
 }
 
+fn animation_frames(ttsj: &tileset_tsj::Tile) -> Vec<TileAnimationFrame> {
+	ttsj.animation()
+		.iter()
+		.map(|f| TileAnimationFrame {
+			tile_id:  *f.tileid(),
+			duration: *f.duration() as f64 / 1000.0,
+		})
+		.collect()
+}
+
+/// Slices an image based tileset into one tile per id.
+/// `margin` is around the whole image, `spacing` between the tiles.
+fn slice_tileset_image(ttsj: &tileset_tsj::TilesetTsj) -> Vec<Tile> {
+	let (tw, th) = (*ttsj.tilewidth(), *ttsj.tileheight());
+	let (iw, ih) = (*ttsj.imagewidth() as f32, *ttsj.imageheight() as f32);
+	let columns = (*ttsj.columns()).max(1);
+	let uv_size = Vector2::new(tw as f32 / iw, th as f32 / ih);
+	let texture = texture_name(ttsj.image());
+
+	(0..*ttsj.tilecount())
+		.map(|id| {
+			let x = ttsj.margin() + (id % columns) * (tw + ttsj.spacing());
+			let y = ttsj.margin() + (id / columns) * (th + ttsj.spacing());
+			// only tiles with properties, or animations, are listed
+			let listed = ttsj.tiles().iter().find(|t| *t.id() == id);
+			let hazard = listed
+				.and_then(|t| t.property("hazard"))
+				.map_or(false, |p| p.as_bool());
+			let animation = listed.map(animation_frames).unwrap_or_default();
+			Tile {
+				id,
+				image: TileImage {
+					texture: texture.to_owned(),
+					uv_pos: Vector2::new(x as f32 / iw, y as f32 / ih),
+					uv_size,
+				},
+				imagewidth: tw,
+				imageheight: th,
+				hazard,
+				animation,
+			}
+		})
+		.collect()
+}
+
 impl From<tileset_tsj::TilesetTsj> for Tileset {
 	fn from(ttsj: tileset_tsj::TilesetTsj) -> Self {
 		let mut ts = Tileset::new();
@@ -112,8 +227,14 @@ impl From<tileset_tsj::TilesetTsj> for Tileset {
 		ts.tilewidth = *ttsj.tilewidth();
 		ts.tileheight = *ttsj.tileheight();
 
-		for t in ttsj.tiles() {
-			ts.add_tile(t.into());
+		if ttsj.is_image_based() {
+			for t in slice_tileset_image(&ttsj) {
+				ts.add_tile(t);
+			}
+		} else {
+			for t in ttsj.tiles() {
+				ts.add_tile(t.into());
+			}
 		}
 
 		ts
@@ -123,6 +244,9 @@ impl From<tileset_tsj::TilesetTsj> for Tileset {
 #[path = "./tileset_tsj.rs"]
 mod tileset_tsj;
 use tileset_tsj::TilesetTsj;
+#[path = "./tileset_tsx.rs"]
+mod tileset_tsx;
+pub(crate) use tileset_tsx::tsx_to_tsj;
 
 #[cfg(test)]
 mod tests {
@@ -165,4 +289,23 @@ mod tests {
 
 		Ok(())
 	}
+
+	#[test]
+	fn tiles_are_found_by_id_not_position() {
+		let mut tileset = Tileset::new();
+		for (id, hazard) in [(7, false), (3, true)] {
+			tileset.add_tile(Tile {
+				id,
+				image: TileImage::whole(&format!("tile-{}", id)),
+				hazard,
+				..Default::default()
+			});
+		}
+
+		assert_eq!(tileset.get_tile_image(3), Some(&TileImage::whole("tile-3")));
+		assert_eq!(tileset.get_tile_image(7), Some(&TileImage::whole("tile-7")));
+		assert_eq!(tileset.get_tile_image(0), None);
+		assert!(tileset.is_tile_hazard(3));
+		assert!(!tileset.is_tile_hazard(7));
+	}
 }
@@ -19,18 +19,41 @@ pub struct TileProperty {
 	name:             String,
 	#[serde(rename = "type")]
 	tilepropertytype: String,
-	value:            String,
+	value:            serde_json::Value,
+}
+
+impl TileProperty {
+	pub fn as_bool(&self) -> bool {
+		match &self.value {
+			serde_json::Value::Bool(b) => *b,
+			serde_json::Value::String(s) => s == "true",
+			_ => false,
+		}
+	}
+}
+
+#[derive(Debug, Default, Getters, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Frame {
+	tileid:   u32,
+	duration: u32, // ms
 }
 
 #[derive(Debug, Default, Getters, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Tile {
 	id:          u32,
+	// tiles in image based tilesets only have properties
+	#[serde(default)]
 	image:       String,
+	#[serde(default)]
 	imagewidth:  u32,
+	#[serde(default)]
 	imageheight: u32,
 	#[serde(default)]
 	properties:  Vec<TileProperty>,
+	#[serde(default)]
+	animation:   Vec<Frame>,
 }
 
 #[derive(Debug, Default, Getters, Serialize, Deserialize)]
@@ -42,24 +65,43 @@ pub struct TilesetTsj {
 	name:         String,
 	spacing:      u32,
 	tilecount:    u32,
+	// embedded tilesets have no versions, and no type
+	#[serde(default)]
 	tiledversion: String,
 	tilewidth:    u32,
 	tileheight:   u32,
-	#[serde(rename = "type")]
+	#[serde(rename = "type", default)]
 	tilesettype:  String,
+	#[serde(default)]
 	version:      String,
+	#[serde(default)]
 	tiles:        Vec<Tile>,
+	// only for image based tilesets
+	#[serde(default)]
+	image:        String,
+	#[serde(default)]
+	imagewidth:   u32,
+	#[serde(default)]
+	imageheight:  u32,
+}
+
+fn without_path(image: &str) -> String {
+	let s = if let Some(idx) = image.rfind("/") {
+		idx + 1
+	} else {
+		0
+	};
+
+	image[s..].to_string()
 }
 
 impl Tile {
-	pub fn remove_path(&mut self) {
-		let s = if let Some(idx) = self.image.rfind("/") {
-			idx + 1
-		} else {
-			0
-		};
+	pub fn property(&self, name: &str) -> Option<&TileProperty> {
+		self.properties.iter().find(|p| p.name == name)
+	}
 
-		let image = self.image[s..].to_string();
+	pub fn remove_path(&mut self) {
+		let image = without_path(&self.image);
 		debug!("Tile: {}", &image);
 		self.image = image;
 	}
@@ -77,6 +119,10 @@ impl TilesetTsj {
 		// let v: Value = serde_json::from_str(&tsj_string)?;
 		//		dbg!(&v);
 
+		self.load_from_str(&tsj_string)
+	}
+
+	pub fn load_from_str(&mut self, tsj_string: &str) -> anyhow::Result<()> {
 		let tsj: TilesetTsj = serde_json::from_str(&tsj_string)?;
 		//		dbg!(&tmj);
 
@@ -88,8 +134,13 @@ impl TilesetTsj {
 	}
 
 	pub fn remove_paths(&mut self) {
+		self.image = without_path(&self.image);
 		for t in self.tiles.iter_mut() {
 			t.remove_path();
 		}
 	}
+
+	pub fn is_image_based(&self) -> bool {
+		!self.image.is_empty()
+	}
 }
@@ -0,0 +1,12 @@
+use crate::rar::tiled_xml;
+
+/// Converts a TSX tileset into the TSJ layout, so it can be loaded via `TilesetTsj`.
+pub fn tsx_to_tsj(tsx_string: &str) -> anyhow::Result<String> {
+	let doc = roxmltree::Document::parse(tsx_string)?;
+	let ts = doc.root_element();
+	if !ts.has_tag_name("tileset") {
+		anyhow::bail!("Expected <tileset> got <{}>", ts.tag_name().name());
+	}
+
+	Ok(serde_json::to_string(&tiled_xml::tileset(&ts)?)?)
+}
@@ -1,11 +1,15 @@
+use std::sync::Arc;
+
 use derive_getters::Getters;
+use oml_game::math::{Rectangle, Vector2};
 use oml_game::system::System;
+use tracing::*;
 
 use crate::rar::map;
+use crate::rar::map::MapSources;
+use crate::rar::map_streamer::{MapBuilder, MapStreamer};
 use crate::rar::Map;
 
-pub const UPSIDEUP: bool = true;
-
 #[derive(Debug, Default, Getters)]
 pub struct WorldMap {
 	filename: String, // prefix!
@@ -14,74 +18,238 @@ pub struct WorldMap {
 	height:   u32,
 	width:    u32,
 	map:      Option<Map>,
+	#[getter(skip)]
+	sources:  Option<Arc<MapSources>>,
 }
 
 impl WorldMap {
 	pub fn map_mut(&mut self) -> &mut Option<Map> {
 		&mut self.map
 	}
+
+	pub fn is_loaded(&self) -> bool {
+		self.map.is_some()
+	}
+
+	/// World position of the top left corner, the .world file uses Tiled's y down.
+	pub fn origin(&self) -> Vector2 {
+		Vector2::new(self.x as f32, -(self.y as f32))
+	}
+
+	/// In world coordinates.
+	pub fn bounds(&self) -> Rectangle {
+		let size = Vector2::new(self.width as f32, self.height as f32);
+		let center = self
+			.origin()
+			.add(&Vector2::new(0.5 * size.x, -0.5 * size.y));
+		Rectangle::default().with_size(&size).with_center(&center)
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GeneratedLayerKind {
+	Collider,
+	Hazard,
 }
+
+/// A layer generated from tile layers, remembered so maps streamed in later get it too.
+#[derive(Debug, Clone)]
+struct GeneratedLayer {
+	kind:          GeneratedLayerKind,
+	name:          String,
+	source_layers: Vec<String>,
+}
+
+impl GeneratedLayer {
+	fn apply(&self, map: &mut Map) -> anyhow::Result<()> {
+		let layers: Vec<&str> = self.source_layers.iter().map(|l| l.as_str()).collect();
+		match self.kind {
+			GeneratedLayerKind::Collider => map.generate_collider_layers(&self.name, &layers),
+			GeneratedLayerKind::Hazard => map.generate_hazard_layers(&self.name, &layers),
+		}
+	}
+}
+
 #[derive(Debug, Default, Getters)]
 pub struct World {
-	maps: Vec<WorldMap>,
+	maps:                    Vec<WorldMap>,
+	only_show_adjacent_maps: bool,
+	#[getter(skip)]
+	generated_layers:        Vec<GeneratedLayer>,
 }
 
 impl World {
 	pub const fn new() -> Self {
-		Self { maps: Vec::new() }
+		Self {
+			maps:                    Vec::new(),
+			only_show_adjacent_maps: false,
+			generated_layers:        Vec::new(),
+		}
+	}
+
+	/// Only streamed worlds load and unload maps while playing, all others load everything.
+	pub fn is_streamed(&self) -> bool {
+		self.only_show_adjacent_maps
+	}
+
+	pub fn loaded_maps(&self) -> impl Iterator<Item = &Map> {
+		self.maps.iter().filter_map(|wm| wm.map.as_ref())
 	}
 
 	pub fn list_objects_in_layer_for_class(&self, layer: &str, class: &str) -> Vec<&map::Object> {
 		let mut r = Vec::new();
 
-		for wm in self.maps.iter() {
-			if let Some(m) = &wm.map {
-				let mut rm = m.list_objects_in_layer_for_class(layer, class);
-				r.append(&mut rm);
-			}
+		for m in self.loaded_maps() {
+			let mut rm = m.list_objects_in_layer_for_class(layer, class);
+			r.append(&mut rm);
 		}
 		r
 	}
 
+	/// Only looks at the given maps, indexed like `maps()`, and skips the ones not loaded.
+	pub fn list_objects_in_maps_layer_for_class(
+		&self,
+		indices: &[usize],
+		layer: &str,
+		class: &str,
+	) -> Vec<&map::Object> {
+		let mut r = Vec::new();
+
+		for m in indices
+			.iter()
+			.filter_map(|i| self.maps.get(*i).and_then(|wm| wm.map.as_ref()))
+		{
+			let mut rm = m.list_objects_in_layer_for_class(layer, class);
+			r.append(&mut rm);
+		}
+		r
+	}
+
+	pub fn loaded_map_indices(&self) -> Vec<usize> {
+		self.maps
+			.iter()
+			.enumerate()
+			.filter(|(_, wm)| wm.is_loaded())
+			.map(|(i, _)| i)
+			.collect()
+	}
+
 	pub fn list_objects_in_layer(&self, layer: &str) -> Vec<&map::Object> {
 		let mut r = Vec::new();
 
-		for wm in self.maps.iter() {
-			if let Some(m) = &wm.map {
-				let mut rm = m.list_objects_in_layer(layer);
-				r.append(&mut rm);
-			}
+		for m in self.loaded_maps() {
+			let mut rm = m.list_objects_in_layer(layer);
+			r.append(&mut rm);
 		}
 		r
 	}
 
+	/// Covers all maps, loaded or not.
+	pub fn bounds(&self) -> Option<Rectangle> {
+		let mut bounds: Option<Rectangle> = None;
+		for wm in self.maps.iter() {
+			let b = wm.bounds();
+			bounds = match bounds {
+				Some(bounds) => Some(bounds.combine_with(&b)),
+				None => Some(b),
+			};
+		}
+		bounds
+	}
+
 	fn add_map(&mut self, map: WorldMap) {
 		self.maps.push(map);
 	}
 
-	pub fn load_all_maps(&mut self, system: &mut System) -> anyhow::Result<()> {
-		for m in self.maps.iter_mut() {
+	fn builder_for(generated_layers: Vec<GeneratedLayer>) -> Arc<MapBuilder> {
+		Arc::new(move |sources: &MapSources, origin: &Vector2| {
 			let mut map = Map::new();
-			map.load(system, &m.filename)?;
+			map.load_from_sources(sources)?;
+			map.place_in_world(origin);
 
-			if *map.upsideup() != UPSIDEUP {
-				map.hflip(512.0);
+			for gl in generated_layers.iter() {
+				gl.apply(&mut map)?;
 			}
+			Ok(map)
+		})
+	}
 
-			m.map = Some(map);
+	fn load_map_now(&mut self, index: usize) -> anyhow::Result<()> {
+		let builder = Self::builder_for(self.generated_layers.clone());
+		let wm = &mut self.maps[index];
+		if let Some(sources) = &wm.sources {
+			wm.map = Some(builder(sources, &wm.origin())?);
 		}
 		Ok(())
 	}
-	pub fn load_all_tilesets(&mut self, system: &mut System) -> anyhow::Result<()> {
-		for wm in self.maps.iter_mut() {
-			if let Some(m) = wm.map_mut() {
-				m.load_all_tilesets(system)?;
-			}
+
+	/// Blocks until all maps are loaded.
+	pub fn load_all_maps(&mut self) -> anyhow::Result<()> {
+		for i in 0..self.maps.len() {
+			self.load_map_now(i)?;
 		}
+		Ok(())
+	}
 
+	/// Blocks until all maps near the given area are loaded, e.g. for the start of a level.
+	pub fn load_maps_near(
+		&mut self,
+		streamer: &MapStreamer,
+		area: &Rectangle,
+	) -> anyhow::Result<()> {
+		for i in 0..self.maps.len() {
+			if !self.maps[i].is_loaded() && streamer.should_load(area, &self.maps[i].bounds()) {
+				self.load_map_now(i)?;
+			}
+		}
 		Ok(())
 	}
 
+	/// Requests maps near the frame, and unloads far away ones, never blocks.
+	/// Returns the indices of the maps that got loaded, and of the ones that got unloaded.
+	pub fn update_streaming(
+		&mut self,
+		streamer: &mut MapStreamer,
+		frame: &Rectangle,
+	) -> (Vec<usize>, Vec<usize>) {
+		let mut loaded = Vec::new();
+		let mut unloaded = Vec::new();
+		for r in streamer.receive() {
+			match r.map {
+				Ok(map) => {
+					debug!("Map {} streamed in", &self.maps[r.index].filename);
+					self.maps[r.index].map = Some(map);
+					loaded.push(r.index);
+				},
+				Err(e) => {
+					warn!(
+						"Failed streaming map {}: {:?}",
+						&self.maps[r.index].filename, e
+					);
+				},
+			}
+		}
+
+		let mut builder = None;
+		for (i, wm) in self.maps.iter_mut().enumerate() {
+			let bounds = wm.bounds();
+			if wm.map.is_some() {
+				if streamer.should_unload(frame, &bounds) {
+					debug!("Map {} streamed out", &wm.filename);
+					wm.map = None;
+					unloaded.push(i);
+				}
+			} else if !streamer.is_in_flight(i) && streamer.should_load(frame, &bounds) {
+				if let Some(sources) = &wm.sources {
+					let builder = builder
+						.get_or_insert_with(|| Self::builder_for(self.generated_layers.clone()));
+					streamer.request(i, sources, &wm.origin(), Arc::clone(builder));
+				}
+			}
+		}
+		(loaded, unloaded)
+	}
+
 	pub fn load(&mut self, system: &mut System, name: &str) -> anyhow::Result<()> {
 		//		return anyhow::bail!("Just testing...");
 
@@ -89,28 +257,66 @@ impl World {
 		dbg!(&world_name);
 		if system.default_filesystem().exists(&world_name) {
 			println!("Trying to load world from {}", &world_name);
-			let mut world_world = WorldWorld::new();
-			world_world.load(system, &world_name)?;
-
-			dbg!(&world_world);
+			let world_string = system
+				.default_filesystem_mut()
+				.open(&world_name)
+				.read_as_string();
 
-			*self = world_world.into();
-			Ok(())
+			self.load_from_str(&world_string, &mut |name| MapSources::load(system, name))
 		} else {
 			anyhow::bail!("No remaining loader for world: {}", &name);
 		}
 	}
 
+	/// `load_sources` reads the files for the map with the given name.
+	pub fn load_from_str(
+		&mut self,
+		world_string: &str,
+		load_sources: &mut dyn FnMut(&str) -> anyhow::Result<MapSources>,
+	) -> anyhow::Result<()> {
+		let mut world_world = WorldWorld::new();
+		world_world.load_from_str(world_string)?;
+
+		dbg!(&world_world);
+
+		*self = world_world.into();
+
+		// only read the files here, building the maps is done when they are needed
+		for wm in self.maps.iter_mut() {
+			wm.sources = Some(Arc::new(load_sources(&wm.filename)?));
+		}
+		Ok(())
+	}
+
 	pub fn generate_collider_layers(
 		&mut self,
 		name: &str,
 		layers: &Vec<&str>,
 	) -> anyhow::Result<()> {
+		self.add_generated_layer(GeneratedLayerKind::Collider, name, layers)
+	}
+
+	pub fn generate_hazard_layers(&mut self, name: &str, layers: &Vec<&str>) -> anyhow::Result<()> {
+		self.add_generated_layer(GeneratedLayerKind::Hazard, name, layers)
+	}
+
+	fn add_generated_layer(
+		&mut self,
+		kind: GeneratedLayerKind,
+		name: &str,
+		layers: &Vec<&str>,
+	) -> anyhow::Result<()> {
+		let gl = GeneratedLayer {
+			kind,
+			name: name.to_owned(),
+			source_layers: layers.iter().map(|l| l.to_string()).collect(),
+		};
 		for wm in self.maps.iter_mut() {
 			if let Some(m) = wm.map_mut() {
-				m.generate_collider_layers(name, layers)?;
+				gl.apply(m)?;
 			}
 		}
+		self.generated_layers.push(gl);
 
 		Ok(())
 	}
@@ -118,7 +324,10 @@ impl World {
 
 impl From<WorldWorld> for World {
 	fn from(ww: WorldWorld) -> Self {
-		let mut w = World::default();
+		let mut w = World {
+			only_show_adjacent_maps: ww.only_show_adjacent_maps(),
+			..Default::default()
+		};
 		for wwm in ww.maps().iter() {
 			let m = wwm.into();
 
@@ -130,13 +339,20 @@ impl From<WorldWorld> for World {
 
 impl From<&world_world::Map> for WorldMap {
 	fn from(wwm: &world_world::Map) -> Self {
+		// the map loader probes for the format
+		let filename = wwm.filename();
+		let filename = filename
+			.strip_suffix(".tmj")
+			.or_else(|| filename.strip_suffix(".tmx"))
+			.unwrap_or("");
 		Self {
 			x:        wwm.x(),
 			y:        wwm.y(),
 			width:    wwm.width(),
 			height:   wwm.height(),
-			filename: wwm.filename().strip_suffix(".tmj").unwrap_or("").to_owned(), // :TODO: strip suffix
+			filename: filename.to_owned(),
 			map:      None,
+			sources:  None,
 		}
 	}
 }
@@ -169,7 +385,7 @@ mod tests {
 
 			let mut world = World::new();
 			world.load(&mut system, "dev")?;
-			world.load_all_maps(&mut system)?;
+			world.load_all_maps()?;
 			dbg!(&world);
 			Ok(())
 		};
@@ -186,4 +402,92 @@ mod tests {
 
 		Ok(())
 	}
+
+	fn load_two_maps() -> anyhow::Result<World> {
+		let mut world = World::new();
+		world.load_from_str(
+			include_str!("../../fixtures/two_maps/two_maps.world"),
+			&mut |name| {
+				let tmj = match name {
+					"left" => include_str!("../../fixtures/two_maps/left.tmj"),
+					"right" => include_str!("../../fixtures/two_maps/right.tmj"),
+					_ => anyhow::bail!("Unknown map {}", name),
+				};
+				MapSources::load_from_str(name, tmj.to_string(), &mut |ts_name| {
+					assert_eq!(ts_name, "blocks");
+					Ok(include_str!("../../fixtures/two_maps/blocks.tsj").to_string())
+				})
+			},
+		)?;
+		world.generate_collider_layers("Collider", &["terrain"].to_vec())?;
+		world.load_all_maps()?;
+		Ok(world)
+	}
+
+	fn close_to(a: &Vector2, x: f32, y: f32) -> bool {
+		(a.x - x).abs() < 0.001 && (a.y - y).abs() < 0.001
+	}
+
+	#[test]
+	fn maps_are_placed_at_their_world_offset() -> anyhow::Result<()> {
+		let world = load_two_maps()?;
+		assert!(world.is_streamed());
+		assert_eq!(world.loaded_maps().count(), 2);
+
+		let right = &world.maps()[1];
+		assert!(close_to(&right.origin(), 128.0, -64.0));
+		assert!(close_to(right.bounds().center(), 192.0, -128.0));
+		let bounds = world.bounds().unwrap();
+		assert!(close_to(bounds.size(), 256.0, 192.0));
+
+		// tiles stay map local, but know where they are in the world
+		let map = right.map().as_ref().unwrap();
+		let center = map.tile_center(1, 1);
+		assert!(close_to(&center, 224.0, -160.0));
+		assert_eq!(map.world_to_tile(&center), (1, 1));
+		Ok(())
+	}
+
+	#[test]
+	fn objects_and_colliders_are_in_world_space() -> anyhow::Result<()> {
+		let world = load_two_maps()?;
+
+		let spawns = world.list_objects_in_layer_for_class("Player", "PlayerSpawn");
+		assert_eq!(spawns.len(), 1);
+		match spawns[0].data() {
+			map::ObjectData::Point { pos } => assert!(close_to(pos, 32.0, -32.0)),
+			d => panic!("Unexpected {:?}", d),
+		}
+
+		let goals = world.list_objects_in_layer_for_class("Player", "Goal");
+		match goals[0].data() {
+			map::ObjectData::Rectangle { rect, .. } => {
+				assert!(close_to(rect.center(), 160.0, -96.0))
+			},
+			d => panic!("Unexpected {:?}", d),
+		}
+
+		// one collider per solid tile, in both maps
+		let colliders = world.list_objects_in_layer("Collider");
+		assert_eq!(colliders.len(), 4);
+		let centers: Vec<Vector2> = colliders
+			.iter()
+			.filter_map(|o| match o.data() {
+				map::ObjectData::Rectangle { rect, .. } => Some(*rect.center()),
+				_ => None,
+			})
+			.collect();
+		for (x, y) in [
+			(32.0, -32.0),
+			(96.0, -96.0),
+			(160.0, -96.0),
+			(224.0, -160.0),
+		] {
+			assert!(
+				centers.iter().any(|c| close_to(c, x, y)),
+				"No collider at {x}, {y}"
+			);
+		}
+		Ok(())
+	}
 }
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use oml_game::math::Vector2;
+use oml_game::math::{Matrix32, Vector2};
 use oml_game::renderer::debug_renderer::DebugRenderer;
 //use oml_game::renderer::debug_renderer::DebugRenderer;
 use oml_game::renderer::Color;
@@ -9,24 +9,195 @@ use tracing::*;
 
 use crate::rar::{
 	camera::Camera,
+	effect_ids::EffectId,
+	layer_config::{LayerConfig, WorldLayerConfig},
+	layer_ids::LayerId,
 	map,
-	map::{Layer, LayerType},
-	World,
+	map::{Gid, Layer, LayerImage, LayerType, TileFlip, TileMap},
+	TileImage, World,
 };
 
 const MAP_TEXT_SCALE: f32 = 20.0;
 const MAP_TEXT_WIDTH: f32 = 2.0;
 
-#[derive(Debug, Default)]
+// picks the part of the tileset image, and flips it,
+// via the texture matrix which maps the uvs of the quad into the texture
+fn tile_tex_matrix(image: &TileImage, flip: &TileFlip) -> Matrix32 {
+	let (x, y, offset) = flip.uv_transform();
+	let uv_size = &image.uv_size;
+	let mut mtx = Matrix32::identity();
+	mtx.rot.x = x.scaled_vector2(uv_size);
+	mtx.rot.y = y.scaled_vector2(uv_size);
+	mtx.pos = image.uv_pos.add(&offset.scaled_vector2(uv_size));
+	mtx
+}
+
+// expects the texture of the image to be in use already
+fn render_tile(
+	renderer: &mut Renderer,
+	image: &TileImage,
+	flip: &TileFlip,
+	pos: &Vector2,
+	size: &Vector2,
+) {
+	if flip.is_none() && image.is_whole() {
+		renderer.render_textured_quad(pos, size);
+	} else {
+		renderer.set_tex_matrix(&tile_tex_matrix(image, flip));
+		renderer.render_textured_quad(pos, size);
+		renderer.set_tex_matrix(&Matrix32::identity());
+	}
+}
+
+// image layers start at the top left of the map, and can repeat in both directions
+fn render_layer_image(
+	renderer: &mut Renderer,
+	image: &LayerImage,
+	top_left: &Vector2,
+	render_offset: &Vector2,
+	scale: f32,
+	view_min: &Vector2,
+	view_max: &Vector2,
+) {
+	let size = &image.size;
+	if size.x <= 0.0 || size.y <= 0.0 {
+		return;
+	}
+	let (sx, ex) = if image.repeat_x {
+		(
+			((view_min.x - top_left.x) / size.x).floor() as i32,
+			((view_max.x - top_left.x) / size.x).ceil() as i32,
+		)
+	} else {
+		(0, 1)
+	};
+	// rows go down
+	let (sy, ey) = if image.repeat_y {
+		(
+			((top_left.y - view_max.y) / size.y).floor() as i32,
+			((top_left.y - view_min.y) / size.y).ceil() as i32,
+		)
+	} else {
+		(0, 1)
+	};
+
+	renderer.use_texture(&image.texture);
+	let screen_size = size.scaled(scale);
+	for y in sy..ey {
+		for x in sx..ex {
+			let pos = top_left
+				.add(&Vector2::new(
+					(x as f32 + 0.5) * size.x,
+					-(y as f32 + 0.5) * size.y,
+				))
+				.add(render_offset)
+				.scaled(scale);
+			renderer.render_textured_quad(&pos, &screen_size);
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
 struct EnabledLayer {
 	layer_id:  u8,
 	effect_id: u16,
+	order:     i32,
+	color:     Color,
+}
+
+impl Default for EnabledLayer {
+	fn default() -> Self {
+		Self {
+			layer_id:  LayerId::TileMap1 as u8,
+			effect_id: EffectId::Textured as u16,
+			order:     0,
+			color:     Color::white(),
+		}
+	}
+}
+
+impl From<&LayerConfig> for EnabledLayer {
+	fn from(lc: &LayerConfig) -> Self {
+		Self {
+			layer_id:  lc.layer as u8,
+			effect_id: lc.effect as u16,
+			order:     lc.order,
+			color:     lc.color(),
+		}
+	}
+}
+
+impl EnabledLayer {
+	// e.g. render_layer: "TileMap2", render_effect: "TexturedDesaturated"
+	fn with_layer_properties(mut self, layer: &Layer) -> Self {
+		if let Some(name) = layer.property("render_layer") {
+			match serde_yaml::from_str::<LayerId>(name) {
+				Ok(id) => self.layer_id = id as u8,
+				Err(_) => warn!("Unknown render_layer {:?} for {}", name, layer.name()),
+			}
+		}
+		if let Some(name) = layer.property("render_effect") {
+			match serde_yaml::from_str::<EffectId>(name) {
+				Ok(id) => self.effect_id = id as u16,
+				Err(_) => warn!("Unknown render_effect {:?} for {}", name, layer.name()),
+			}
+		}
+		if let Some(order) = layer.property_as_i64("render_order") {
+			self.order = order as i32;
+		}
+		if let Some(tint) = layer.property_as_color("tint") {
+			let a = self.color.a;
+			self.color = *tint;
+			self.color.a *= a;
+		}
+		if let Some(opacity) = layer.property_as_f32("render_opacity") {
+			self.color.a *= opacity;
+		}
+		self
+	}
+}
+
+// the non empty tiles of a chunk as (x, y, gid), so empty space costs nothing per frame
+#[derive(Debug, Default)]
+struct ChunkCache {
+	revision: Option<u64>,
+	tiles:    Vec<(u32, u32, u32)>,
+}
+
+impl ChunkCache {
+	fn update(&mut self, tile_map: &TileMap) {
+		if self.revision == Some(tile_map.revision()) {
+			return;
+		}
+		self.tiles.clear();
+		for y in 0..tile_map.height() {
+			for x in 0..tile_map.width() {
+				let gid = tile_map.get_xy(x, y);
+				if gid != 0 {
+					self.tiles.push((x, y, gid));
+				}
+			}
+		}
+		self.revision = Some(tile_map.revision());
+	}
+}
+
+#[derive(Debug, Default)]
+struct MapCache {
+	// by layer and chunk index
+	chunks:     HashMap<(usize, usize), ChunkCache>,
+	last_frame: u64,
 }
 
 #[derive(Debug, Default)]
 pub struct WorldRenderer {
-	enabled_layers: HashMap<String, EnabledLayer>,
-	total_time:     f64,
+	enabled_layers:         HashMap<String, EnabledLayer>,
+	map_caches:             HashMap<String, MapCache>,
+	frame_count:            u64,
+	total_time:             f64,
+	// drives animated tiles, stops while paused
+	tile_animation_time:    f64,
+	tile_animations_paused: bool,
 }
 
 impl WorldRenderer {
@@ -46,19 +217,53 @@ impl WorldRenderer {
 		layer.effect_id = effect_id;
 		//		layer.enabled = true;
 	}
+	/// Replaces all enabled layers.
+	pub fn configure_layers(&mut self, config: &WorldLayerConfig) {
+		self.enabled_layers.clear();
+		for lc in config.layers.iter() {
+			self.enabled_layers.insert(lc.name.clone(), lc.into());
+		}
+	}
+	// layers not in the world config can still opt in via their properties
+	fn enabled_layer(&self, layer: &Layer) -> Option<EnabledLayer> {
+		if !*layer.visible() {
+			return None;
+		}
+		let enabled_layer = match self.enabled_layers.get(layer.path()) {
+			Some(enabled_layer) => enabled_layer.clone(),
+			None if layer.property("render_layer").is_some() => EnabledLayer::default(),
+			None => return None,
+		};
+		let mut enabled_layer = enabled_layer.with_layer_properties(layer);
+		enabled_layer.color = map::multiply_colors(&enabled_layer.color, layer.tint());
+		enabled_layer.color.a *= *layer.opacity();
+		Some(enabled_layer)
+	}
+	pub fn set_tile_animations_paused(&mut self, paused: bool) {
+		self.tile_animations_paused = paused;
+	}
 	pub fn update(&mut self, time_step: f64) {
 		self.total_time += time_step;
+		if !self.tile_animations_paused {
+			self.tile_animation_time += time_step;
+		}
 	}
 	pub fn render(&mut self, renderer: &mut Renderer, camera: &Camera, world: &World) {
 		//		dbg!(&self);
 		let frame = camera.frame();
-		let left = frame.left();
-		let right = frame.right();
-		let _top = frame.top();
-		let _bottom = frame.bottom();
+		let camera_offset = camera.offset();
+		// positions relative to the camera get zoomed around it
+		let scale = camera.scale();
+		let view_min = Vector2::new(frame.left(), frame.top().min(frame.bottom()));
+		let view_max = Vector2::new(frame.right(), frame.top().max(frame.bottom()));
 
+		self.frame_count += 1;
+		// for animated tiles
+		let t = self.tile_animation_time;
 		let mut tiles_rendered = 0;
 		let mut chunks_with_tiles_rendered = 0;
+		let mut texture_switches = 0;
+		let mut batch = Vec::new();
 		for m in world.maps() {
 			//			dbg!(&m.filename());
 			if let Some(map) = m.map() {
@@ -66,90 +271,126 @@ impl WorldRenderer {
 				let th = *map.tileheight();
 				let tw = *map.tilewidth();
 
-				let tile_left = (left / tw as f32).floor() as i32;
-				let tile_right = (right / tw as f32).ceil() as i32;
-
-				for l in map.layers() {
-					if let Some(enabled_layer) = self.enabled_layers.get(l.name()) {
-						//						dbg!( &l.name() );
-						//						dbg!( &l );
-						//						println!("Layer ->");
-						renderer.use_layer(enabled_layer.layer_id);
-						renderer.use_effect(enabled_layer.effect_id);
-
-						match l.layertype() {
-							LayerType::Objects => {},
-							LayerType::Tile => {
-								for c in l.chunks() {
-									let tm = c.tile_map();
-									let w = tm.width();
-									let h = tm.height();
-									let ox = *c.x();
-									let oy = *c.y();
-
-									// :TODO: optimise the following two boundaries
-									let sy = 0;
-									let ey = h;
-									let sx = (tile_left - ox).clamp(0, w as i32) as u32;
-									let ex = (tile_right - ox).clamp(0, w as i32) as u32;
-
-									let size = Vector2::new(tw as f32, th as f32);
-									let pos =
-										Vector2::new(0.5 * (tw as f32), 512.0 - 0.5 * (th as f32)); // :TODO: world offset etc
-
-									let pos = pos.add(&Vector2::new(
-										(sx as f32) * tw as f32,
-										-(sy as f32) * th as f32,
-									));
-
-									let mut pos = pos.add(&camera.offset());
-
-									// :HACK: apply chunk offset
-									pos = pos.add(&Vector2::new(
-										(ox as f32) * (tw as f32),
-										-1.0 * (oy as f32) * (th as f32),
-									));
-									let inc_x = Vector2::new(tw as f32, 0.0);
-									// including undo row aka carriage return ;)
-									let inc_y =
-										Vector2::new(0.0 - ((tw * (ex - sx)) as f32), -(th as f32));
-
-									let mut tiles_rendered_in_chunk = 0;
-									for y in sy..ey {
-										for x in sx..ex {
-											tiles_rendered_in_chunk += 1;
-											let tid = tm.get_xy(x, y);
-											if tid > 0 {
-												let image = map.get_tile_image(tid);
-												if !image.is_empty() {
-													renderer.use_texture(image);
-													renderer.render_textured_quad(&pos, &size);
-												} else {
-													println!("Warning: No tile for TID: {}", tid);
-												}
-											}
-											pos = pos.add(&inc_x);
-										}
-										pos = pos.add(&inc_y);
+				let origin = map.origin();
+
+				let mut layers: Vec<(usize, &Layer, EnabledLayer)> = map
+					.layers()
+					.iter()
+					.enumerate()
+					.filter_map(|(li, l)| self.enabled_layer(l).map(|el| (li, l, el)))
+					.collect();
+				// stable, so the map order is kept for the same order
+				layers.sort_by_key(|(_, _, el)| el.order);
+
+				let map_cache = self.map_caches.entry(m.filename().to_string()).or_default();
+				map_cache.last_frame = self.frame_count;
+
+				for (li, l, enabled_layer) in layers {
+					renderer.use_layer(enabled_layer.layer_id);
+					renderer.use_effect(enabled_layer.effect_id);
+					renderer.set_color(&enabled_layer.color);
+
+					// the part of the world this layer shows, shifted by parallax and offset
+					let render_offset = l.render_offset(&camera_offset, &origin);
+					let shift = camera_offset.sub(&render_offset);
+					let view_min = view_min.add(&shift);
+					let view_max = view_max.add(&shift);
+
+					match l.layertype() {
+						LayerType::Objects => {},
+						LayerType::Image => {
+							if let Some(image) = l.image() {
+								render_layer_image(
+									renderer,
+									image,
+									&origin,
+									&render_offset,
+									scale,
+									&view_min,
+									&view_max,
+								);
+							}
+						},
+						LayerType::Tile => {
+							// in map local tiles, rows go down
+							let tile_left = ((view_min.x - origin.x) / tw as f32).floor() as i32;
+							let tile_right = ((view_max.x - origin.x) / tw as f32).ceil() as i32;
+							let tile_top = ((origin.y - view_max.y) / th as f32).floor() as i32;
+							let tile_bottom = ((origin.y - view_min.y) / th as f32).ceil() as i32;
+
+							let size = Vector2::new(tw as f32, th as f32).scaled(scale);
+							for (ci, c) in l.chunks().iter().enumerate() {
+								let tm = c.tile_map();
+								let w = tm.width() as i32;
+								let h = tm.height() as i32;
+								let ox = *c.x();
+								let oy = *c.y();
+
+								let sx = (tile_left - ox).clamp(0, w);
+								let ex = (tile_right - ox).clamp(0, w);
+								let sy = (tile_top - oy).clamp(0, h);
+								let ey = (tile_bottom - oy).clamp(0, h);
+								if sx >= ex || sy >= ey {
+									continue;
+								}
+
+								let cache = map_cache.chunks.entry((li, ci)).or_default();
+								cache.update(tm);
+
+								let batch_len = batch.len();
+								for &(x, y, gid) in cache.tiles.iter() {
+									let (x, y) = (x as i32, y as i32);
+									if x < sx || x >= ex || y < sy || y >= ey {
+										continue;
 									}
-									tiles_rendered += tiles_rendered_in_chunk;
-									if tiles_rendered_in_chunk > 0 {
-										chunks_with_tiles_rendered += 1;
+									let gid = Gid::from(gid);
+									if let Some(image) = map.get_tile_image_at(gid.id(), t) {
+										let pos = map
+											.tile_center(ox + x, oy + y)
+											.add(&render_offset)
+											.scaled(scale);
+										batch.push((image, *gid.flip(), pos));
+									} else {
+										warn!("No tile for GID: {}", gid.id());
 									}
 								}
-							},
-							LayerType::None => {},
-						};
-					}
+								if batch.len() > batch_len {
+									chunks_with_tiles_rendered += 1;
+								}
+							}
+
+							// stable, so tiles sharing a texture keep their order
+							batch.sort_by(|a, b| a.0.texture.cmp(&b.0.texture));
+							let mut texture: Option<&str> = None;
+							for (image, flip, pos) in batch.drain(..) {
+								if texture != Some(image.texture.as_str()) {
+									renderer.use_texture(&image.texture);
+									texture = Some(image.texture.as_str());
+									texture_switches += 1;
+								}
+								render_tile(renderer, image, &flip, &pos, &size);
+								tiles_rendered += 1;
+							}
+						},
+						LayerType::None => {},
+					};
 				}
 			}
 		}
-		if false {
-			println!(
-				"Tiles rendered {}, Chunks with tiles {}",
-				tiles_rendered, chunks_with_tiles_rendered
-			);
-		}
+
+		// don't leak the last layer's tint into whatever is rendered next
+		renderer.set_color(&Color::white());
+
+		// forget the caches of maps that were streamed out
+		let frame_count = self.frame_count;
+		self.map_caches.retain(|_, mc| mc.last_frame == frame_count);
+
+		oml_game::DefaultTelemetry::trace::<f32>("world.tiles.rendered", tiles_rendered as f32);
+		oml_game::DefaultTelemetry::trace::<f32>(
+			"world.chunks.rendered",
+			chunks_with_tiles_rendered as f32,
+		);
+		oml_game::DefaultTelemetry::trace::<f32>("world.texture.switches", texture_switches as f32);
 	}
 
 	pub fn render_debug_layer_objects(
@@ -160,8 +401,6 @@ impl WorldRenderer {
 	) {
 		let l = layer;
 
-		let offset = camera.offset();
-
 		//if l.name() == "CameraControl" {
 		for o in l.objects() {
 			//						dbg!(&o);
@@ -173,6 +412,14 @@ impl WorldRenderer {
 					//color = Color::red();
 					width = 9.0;
 				},
+				("Player", "Checkpoint") => {
+					color = Color::green();
+					width = 9.0;
+				},
+				("Player", "Goal") => {
+					color = Color::from_rgba(0.9, 0.8, 0.2, 1.0);
+					width = 9.0;
+				},
 				("Player", "PlayerKill") => {
 					color = Color::red();
 					width = 9.0;
@@ -193,6 +440,30 @@ impl WorldRenderer {
 					color = Color::green();
 					width = 3.0;
 				},
+				("Hazard", _) => {
+					color = Color::red();
+					width = 3.0;
+				},
+				("Enemies", "Enemy") => {
+					color = Color::from_rgba(0.9, 0.3, 0.3, 1.0);
+					width = 5.0;
+				},
+				("Platforms", "Platform") => {
+					color = Color::from_rgba(0.6, 0.4, 0.9, 1.0);
+					width = 5.0;
+				},
+				("CameraControl", "CameraZone") => {
+					color = Color::from_rgba(0.3, 0.9, 0.9, 1.0);
+					width = 3.0;
+				},
+				("CameraControl", "CameraRail") => {
+					color = Color::from_rgba(0.3, 0.9, 0.9, 1.0);
+					width = 5.0;
+				},
+				("Obstacles", "Obstacle") => {
+					color = Color::red();
+					width = 9.0;
+				},
 				(_, "Test") => {
 					do_debug_render = false;
 				},
@@ -212,9 +483,7 @@ impl WorldRenderer {
 						rect,
 						bounding_circle: _,
 					} => {
-						let mut rect = rect.clone();
-						//								let offset = self.camera.scaled_vector2( &Vector2::new( -1.0, 1.0 ) );
-						rect.offset(&offset);
+						let rect = camera.world_rect_to_screen(rect);
 
 						debug_renderer.add_rectangle(&rect, width, &color);
 						debug_renderer.add_text(
@@ -235,8 +504,7 @@ impl WorldRenderer {
 						//								debug_renderer.add_text(rect.pos(), o.class(), 50.0, 5.0, &Color::from_rgba( 0.75, 0.75, 0.95, 1.0 ));
 					},
 					map::ObjectData::Point { pos } => {
-						let pos = offset.add(pos);
-						//let pos = pos.add( &Vector2::new( 0.0, 0.0 ) );
+						let pos = camera.world_to_screen(pos);
 						debug_renderer.add_circle(&pos, 50.0, 5.0, &color);
 						debug_renderer.add_text(
 							&pos,
@@ -246,6 +514,63 @@ impl WorldRenderer {
 							&color,
 						);
 					},
+					map::ObjectData::Polyline { points } => {
+						for (a, b) in points.iter().zip(points.iter().skip(1)) {
+							let a = camera.world_to_screen(a);
+							let b = camera.world_to_screen(b);
+							debug_renderer.add_line(&a, &b, width, &color);
+						}
+						if let Some(first) = points.first() {
+							debug_renderer.add_text(
+								&camera.world_to_screen(first),
+								o.class(),
+								MAP_TEXT_SCALE,
+								MAP_TEXT_WIDTH,
+								&color,
+							);
+						}
+					},
+					map::ObjectData::Polygon { points } => {
+						let next = points.iter().cycle().skip(1);
+						for (a, b) in points.iter().zip(next) {
+							let a = camera.world_to_screen(a);
+							let b = camera.world_to_screen(b);
+							debug_renderer.add_line(&a, &b, width, &color);
+						}
+						if let Some(first) = points.first() {
+							debug_renderer.add_text(
+								&camera.world_to_screen(first),
+								o.class(),
+								MAP_TEXT_SCALE,
+								MAP_TEXT_WIDTH,
+								&color,
+							);
+						}
+					},
+					map::ObjectData::Ellipse { rect } => {
+						// rotation only moves the center, we render a circle anyway
+						let center = o.rotated_bounds().map_or(*rect.center(), |b| *b.center());
+						let pos = camera.world_to_screen(&center);
+						let radius = 0.25 * (rect.size().x + rect.size().y) * camera.scale();
+						debug_renderer.add_circle(&pos, radius, width, &color);
+						debug_renderer.add_text(
+							&pos,
+							o.class(),
+							MAP_TEXT_SCALE,
+							MAP_TEXT_WIDTH,
+							&color,
+						);
+					},
+					map::ObjectData::Tile { .. } | map::ObjectData::Text { .. } => {
+						if let Some(corners) = o.rotated_corners() {
+							let next = corners.iter().cycle().skip(1);
+							for (a, b) in corners.iter().zip(next) {
+								let a = camera.world_to_screen(a);
+								let b = camera.world_to_screen(b);
+								debug_renderer.add_line(&a, &b, width, &color);
+							}
+						}
+					},
 					map::ObjectData::Unknown => {},
 					/*
 					d => {
@@ -56,10 +56,18 @@ impl WorldWorld {
 		&self.maps
 	}
 
+	pub fn only_show_adjacent_maps(&self) -> bool {
+		self.onlyShowAdjacentMaps
+	}
+
 	pub fn load(&mut self, system: &mut System, name: &str) -> anyhow::Result<()> {
 		let mut world_file = system.default_filesystem_mut().open(&name);
 		let world_string = world_file.read_as_string();
 
+		self.load_from_str(&world_string)
+	}
+
+	pub fn load_from_str(&mut self, world_string: &str) -> anyhow::Result<()> {
 		let v: Value = serde_json::from_str(&world_string)?;
 		dbg!(&v);
 
@@ -15,6 +15,7 @@ pub use ui_event::UiEvent;
 pub use ui_event::UiEventResponse;
 pub use ui_event::UiEventResponseButtonClicked;
 pub use ui_event::UiEventResponseGenericMessage;
+pub use ui_event::UiEventResponseVirtualInput;
 
 mod ui_3x3_image;
 pub use ui_3x3_image::Ui3x3Image;
@@ -40,6 +41,10 @@ mod ui_toggle_button;
 pub use ui_toggle_button::UiToggleButton;
 mod ui_vbox;
 pub use ui_vbox::UiVbox;
+mod ui_virtual_button;
+pub use ui_virtual_button::UiVirtualButton;
+mod ui_virtual_joystick;
+pub use ui_virtual_joystick::UiVirtualJoystick;
 
 mod ui_element_factory;
 pub use ui_element_factory::UiElementFactory;
@@ -66,13 +66,15 @@ impl UiElement for UiButton {
 	fn handle_ui_event(
 		&mut self,
 		container: &mut UiElementContainerData,
-		_event: &UiEvent,
+		event: &UiEvent,
 		_event_sender: &Sender<Box<dyn UiEventResponse>>,
 	) -> Option<Box<dyn UiEventResponse>> {
 		if !container.is_visible() {
 			return None;
 		}
-		// :TODO: check actual event type
+		if !matches!(event, UiEvent::MouseClick { .. }) {
+			return None;
+		}
 		debug!("Button clicked");
 		Some(Box::new(UiEventResponseButtonClicked::new(&container.name)))
 	}
@@ -694,10 +694,9 @@ children:
 		&mut self.data
 	}
 
-	fn handle_mouse_click(
+	fn handle_hit_event(
 		&mut self,
 		pos: &Vector2,
-		button: u8,
 		event: &UiEvent,
 		event_sender: &Sender<Box<dyn UiEventResponse>>,
 	) -> Option<Box<dyn UiEventResponse>> {
@@ -714,10 +713,7 @@ children:
 				//						println!("New pos: {},{} (child @ {}, {} -> {}, {})", pos.x, pos.y , c.pos().x, c.pos().y, cpos.x, cpos.y );
 				if c.is_hit_by(&cpos) {
 					println!("Child is hit");
-					let ev = UiEvent::MouseClick {
-						pos,
-						button: button,
-					};
+					let ev = event.with_pos(&pos);
 					if let Some(r) = c.handle_ui_event(&ev, event_sender) {
 						//return self.element.handle_ui_event_response(r);
 						return Some(r);
@@ -741,19 +737,46 @@ children:
 		event_sender: &Sender<Box<dyn UiEventResponse>>,
 	) -> Option<Box<dyn UiEventResponse>> {
 		match event {
-			UiEvent::MouseClick { pos, button } if self.data.is_visible() => {
-				if let Some(r) = self.handle_mouse_click(pos, *button, event, event_sender) {
+			UiEvent::MouseClick { pos, .. } | UiEvent::PointerPressed { pos, .. }
+				if self.data.is_visible() =>
+			{
+				if let Some(r) = self.handle_hit_event(pos, event, event_sender) {
 					// self.element.handle_ui_event_response(r)
 					self.handle_ui_event_response(r)
 				} else {
 					None
 				}
 			},
-			#[allow(unreachable_patterns)]
+			// not hit tested, and also sent to invisible elements, so a pressed element always
+			// sees the release
+			UiEvent::PointerHeld { pos, .. } | UiEvent::PointerReleased { pos, .. } => {
+				if let Some(r) = self.handle_broadcast_event(pos, event, event_sender) {
+					self.handle_ui_event_response(r)
+				} else {
+					None
+				}
+			},
 			_ => None,
 		}
 	}
 
+	fn handle_broadcast_event(
+		&mut self,
+		pos: &Vector2,
+		event: &UiEvent,
+		event_sender: &Sender<Box<dyn UiEventResponse>>,
+	) -> Option<Box<dyn UiEventResponse>> {
+		let pos = pos.sub(self.pos());
+		let ev = event.with_pos(&pos);
+		for c in self.data.borrow_children_mut().iter_mut() {
+			if let Some(r) = c.borrow_mut().handle_ui_event(&ev, event_sender) {
+				return Some(r);
+			}
+		}
+		self.element
+			.handle_ui_event(&mut self.data, &event, event_sender)
+	}
+
 	fn handle_ui_event_response(
 		&mut self,
 		response: Box<dyn UiEventResponse>,
@@ -19,6 +19,8 @@ impl UiElementFactory {
 		self.register_producer_via_info(&crate::ui::UiLabel::info());
 		self.register_producer_via_info(&crate::ui::UiImage::info());
 		self.register_producer_via_info(&crate::ui::Ui3x3Image::info());
+		self.register_producer_via_info(&crate::ui::UiVirtualButton::info());
+		self.register_producer_via_info(&crate::ui::UiVirtualJoystick::info());
 		self
 	}
 
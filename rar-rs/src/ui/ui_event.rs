@@ -2,7 +2,47 @@ use oml_game::math::Vector2;
 
 #[derive(Debug)]
 pub enum UiEvent {
-	MouseClick { pos: Vector2, button: u8 },
+	MouseClick {
+		pos:    Vector2,
+		button: u8,
+	},
+	/// a pointer (mouse button, or touch) went down, only sent to elements that are hit
+	PointerPressed {
+		pos:     Vector2,
+		pointer: u8,
+	},
+	/// sent every update while the pointer is down, to all elements
+	PointerHeld {
+		pos:     Vector2,
+		pointer: u8,
+	},
+	/// the pointer went up, sent to all elements, since it might have moved off the pressed one
+	PointerReleased {
+		pos:     Vector2,
+		pointer: u8,
+	},
+}
+
+impl UiEvent {
+	pub fn pos(&self) -> &Vector2 {
+		match self {
+			UiEvent::MouseClick { pos, .. }
+			| UiEvent::PointerPressed { pos, .. }
+			| UiEvent::PointerHeld { pos, .. }
+			| UiEvent::PointerReleased { pos, .. } => pos,
+		}
+	}
+
+	/// The same event, at a different position, e.g. in the coordinates of a child.
+	pub fn with_pos(&self, pos: &Vector2) -> Self {
+		let pos = *pos;
+		match *self {
+			UiEvent::MouseClick { button, .. } => UiEvent::MouseClick { pos, button },
+			UiEvent::PointerPressed { pointer, .. } => UiEvent::PointerPressed { pos, pointer },
+			UiEvent::PointerHeld { pointer, .. } => UiEvent::PointerHeld { pos, pointer },
+			UiEvent::PointerReleased { pointer, .. } => UiEvent::PointerReleased { pos, pointer },
+		}
+	}
 }
 
 // :TODO: move
@@ -28,6 +68,38 @@ impl UiEventResponse for UiEventResponseButtonClicked {
 	}
 }
 
+/// Sent by virtual (on screen) controls whenever their state changes.
+#[derive(Debug)]
+pub struct UiEventResponseVirtualInput {
+	pub name:    String,
+	/// -1.0 .. 1.0 on both axis, zero for buttons
+	pub axis:    Vector2,
+	pub is_down: bool,
+}
+
+impl UiEventResponseVirtualInput {
+	pub fn new(name: &str, axis: &Vector2, is_down: bool) -> Self {
+		Self {
+			name: name.to_owned(),
+			axis: *axis,
+			is_down,
+		}
+	}
+}
+
+impl UiEventResponse for UiEventResponseVirtualInput {
+	fn as_any(&self) -> &dyn std::any::Any {
+		self
+	}
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+		writeln!(
+			f,
+			"UiEventResponseVirtualInput -> {} {:?} {}",
+			self.name, self.axis, self.is_down
+		)
+	}
+}
+
 #[derive(Debug)]
 pub struct UiEventResponseGenericMessage {
 	// :TODO: nooooooo....
@@ -22,6 +22,8 @@ pub struct UiSystem {
 	name:                  String,
 	root:                  Option<UiElementContainer>,
 	event_response_sender: Option<Sender<Box<dyn UiEventResponse>>>,
+	// indexed like the mouse buttons
+	were_pointers_down:    Vec<bool>,
 }
 
 impl UiSystem {
@@ -148,23 +150,33 @@ impl UiSystem {
 	pub fn update(&mut self, auc: &mut AppUpdateContext) {
 		if let Some(root) = &mut self.root {
 			if let Some(wuc) = auc.wuc() {
+				let cp = *auc.cursor_pos();
 				if wuc.was_mouse_button_pressed(0) {
-					let cp = auc.cursor_pos();
 					debug!("Left Mouse Button was pressed @ {}, {}", cp.x, cp.y);
 					//debug!("{:?}", &root);
 					let ev = UiEvent::MouseClick {
-						pos:    *cp,
+						pos:    cp,
 						button: 0,
 					};
-					if let Some(event_response_sender) = &mut self.event_response_sender {
-						debug!("{:?}", &root);
-						if let Some(ev) = root.handle_ui_event(&ev, &event_response_sender) {
-							debug!("Click handled");
-							//let _ =
-							event_response_sender.send(ev).unwrap();
-						} else {
-							//root.dump_info( "", &Vector2::zero() );
-						}
+					Self::send_event(root, &self.event_response_sender, &ev);
+				}
+				// every mouse button is its own pointer,
+				// so e.g. the left one can hold the joystick while the right one presses jump
+				// :TODO: add touch pointers once we get touch input
+				self.were_pointers_down
+					.resize(wuc.mouse_buttons.len(), false);
+				for (i, is_pointer_down) in wuc.mouse_buttons.iter().enumerate() {
+					let was_pointer_down = self.were_pointers_down[i];
+					self.were_pointers_down[i] = *is_pointer_down;
+					let pointer = i as u8;
+					let ev = match (was_pointer_down, *is_pointer_down) {
+						(false, true) => Some(UiEvent::PointerPressed { pos: cp, pointer }),
+						(true, true) => Some(UiEvent::PointerHeld { pos: cp, pointer }),
+						(true, false) => Some(UiEvent::PointerReleased { pos: cp, pointer }),
+						(false, false) => None,
+					};
+					if let Some(ev) = ev {
+						Self::send_event(root, &self.event_response_sender, &ev);
 					}
 				}
 
@@ -172,6 +184,22 @@ impl UiSystem {
 			}
 		}
 	}
+
+	fn send_event(
+		root: &mut UiElementContainer,
+		event_response_sender: &Option<Sender<Box<dyn UiEventResponse>>>,
+		ev: &UiEvent,
+	) {
+		if let Some(event_response_sender) = event_response_sender {
+			if let Some(ev) = root.handle_ui_event(ev, &event_response_sender) {
+				debug!("Event handled");
+				//let _ =
+				event_response_sender.send(ev).unwrap();
+			} else {
+				//root.dump_info( "", &Vector2::zero() );
+			}
+		}
+	}
 	pub fn render(&mut self, renderer: &mut Renderer) {
 		if let Some(root) = &mut self.root {
 			// :CHEAT: ???
@@ -96,9 +96,12 @@ impl UiElement for UiToggleButton {
 	fn handle_ui_event(
 		&mut self,
 		container: &mut UiElementContainerData,
-		_event: &UiEvent,
+		event: &UiEvent,
 		_event_sender: &Sender<Box<dyn UiEventResponse>>,
 	) -> Option<Box<dyn UiEventResponse>> {
+		if !matches!(event, UiEvent::MouseClick { .. }) {
+			return None;
+		}
 		debug!("Button toggled");
 		Some(Box::new(UiEventResponseButtonClicked::new(&container.name)))
 	}
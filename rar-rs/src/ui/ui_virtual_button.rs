@@ -0,0 +1,170 @@
+use std::sync::mpsc::Sender;
+
+use oml_game::math::Vector2;
+use serde::{Deserialize, Serialize};
+
+use crate::ui::UiElementInfo;
+use crate::ui::{
+	UiElement, UiElementContainerData, UiElementFadeState, UiEvent, UiEventResponse,
+	UiEventResponseVirtualInput, UiRenderer,
+};
+
+// opacity while not pressed
+const RELEASED_OPACITY: f32 = 0.5;
+
+/// An on screen button that reports press and release, unlike `UiButton` which only reports clicks.
+#[derive(Debug, Default)]
+pub struct UiVirtualButton {
+	imagesize: Vector2,
+	imagename: String,
+	pointer:   Option<u8>,
+}
+
+impl UiVirtualButton {
+	pub fn new(imagename: &str, size: &Vector2) -> Self {
+		Self {
+			imagesize: *size,
+			imagename: imagename.to_owned(),
+			pointer:   None,
+		}
+	}
+
+	pub fn is_down(&self) -> bool {
+		self.pointer.is_some()
+	}
+
+	pub fn info() -> &'static UiElementInfo {
+		&UiElementInfo {
+			type_name:   "UiVirtualButton",
+			producer_fn: &Self::produce,
+		}
+	}
+
+	pub fn produce() -> Box<dyn UiElement> {
+		Box::new(Self::default())
+	}
+
+	fn response(&self, container: &UiElementContainerData) -> Option<Box<dyn UiEventResponse>> {
+		Some(Box::new(UiEventResponseVirtualInput::new(
+			&container.name,
+			&Vector2::zero(),
+			self.is_down(),
+		)))
+	}
+}
+
+impl UiElement for UiVirtualButton {
+	fn type_name(&self) -> &str {
+		Self::info().type_name
+	}
+	fn as_any(&self) -> &dyn std::any::Any {
+		self
+	}
+	fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+		self
+	}
+	fn preferred_size(&self) -> Option<&Vector2> {
+		Some(&self.imagesize)
+	}
+	fn render(&self, container: &UiElementContainerData, ui_renderer: &mut UiRenderer) {
+		if *container.fade_state() != UiElementFadeState::FadedOut {
+			let mut l = container.get_fade_level();
+			if !self.is_down() {
+				l *= RELEASED_OPACITY;
+			}
+			ui_renderer.push_opacity(l);
+			ui_renderer.use_texture(&self.imagename);
+			ui_renderer.render_textured_quad(&container.pos, &self.imagesize);
+			ui_renderer.pop_opacity();
+		}
+	}
+	fn handle_ui_event(
+		&mut self,
+		container: &mut UiElementContainerData,
+		event: &UiEvent,
+		_event_sender: &Sender<Box<dyn UiEventResponse>>,
+	) -> Option<Box<dyn UiEventResponse>> {
+		match *event {
+			UiEvent::PointerPressed { pointer, .. } if self.pointer.is_none() => {
+				self.pointer = Some(pointer);
+				self.response(container)
+			},
+			UiEvent::PointerReleased { pointer, .. } if self.pointer == Some(pointer) => {
+				self.pointer = None;
+				self.response(container)
+			},
+			_ => None,
+		}
+	}
+	fn configure_from_yaml_value(&mut self, yaml_value: serde_yaml::Value) {
+		let config: UiVirtualButtonConfig = serde_yaml::from_value(yaml_value).unwrap();
+
+		self.imagesize = Vector2::from_x_str(&config.size);
+		self.imagename = config.image;
+	}
+	fn to_yaml_config(&self) -> serde_yaml::Value {
+		serde_yaml::to_value(UiVirtualButtonConfig {
+			image: self.imagename.clone(),
+			size:  format!("{}x{}", self.imagesize.x, self.imagesize.y),
+		})
+		.unwrap_or(serde_yaml::Value::Null)
+	}
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct UiVirtualButtonConfig {
+	image: String,
+	size:  String,
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::mpsc::channel;
+
+	use super::*;
+
+	fn is_down(response: Option<Box<dyn UiEventResponse>>) -> Option<bool> {
+		response.map(|r| {
+			r.as_any()
+				.downcast_ref::<UiEventResponseVirtualInput>()
+				.unwrap()
+				.is_down
+		})
+	}
+
+	#[test]
+	fn button_follows_the_pointer_that_pressed_it() {
+		let (tx, _rx) = channel();
+		let mut container = UiElementContainerData::new();
+		container.name = "jump".to_string();
+		let mut button = UiVirtualButton::new("button", &Vector2::new(64.0, 64.0));
+		let pos = Vector2::zero();
+
+		let ev = UiEvent::PointerPressed { pos, pointer: 1 };
+		assert_eq!(
+			is_down(button.handle_ui_event(&mut container, &ev, &tx)),
+			Some(true)
+		);
+		assert!(button.is_down());
+
+		// other pointers can neither press, nor release it
+		let ev = UiEvent::PointerPressed { pos, pointer: 0 };
+		assert_eq!(
+			is_down(button.handle_ui_event(&mut container, &ev, &tx)),
+			None
+		);
+		let ev = UiEvent::PointerReleased { pos, pointer: 0 };
+		assert_eq!(
+			is_down(button.handle_ui_event(&mut container, &ev, &tx)),
+			None
+		);
+		assert!(button.is_down());
+
+		let ev = UiEvent::PointerReleased { pos, pointer: 1 };
+		assert_eq!(
+			is_down(button.handle_ui_event(&mut container, &ev, &tx)),
+			Some(false)
+		);
+		assert!(!button.is_down());
+	}
+}
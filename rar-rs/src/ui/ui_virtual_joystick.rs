@@ -0,0 +1,219 @@
+use std::sync::mpsc::Sender;
+
+use oml_game::math::Vector2;
+use serde::{Deserialize, Serialize};
+
+use crate::ui::UiElementInfo;
+use crate::ui::{
+	UiElement, UiElementContainerData, UiElementFadeState, UiEvent, UiEventResponse,
+	UiEventResponseVirtualInput, UiRenderer,
+};
+
+/// An on screen stick, the knob follows the pointer that pressed it until it is released.
+#[derive(Debug, Default)]
+pub struct UiVirtualJoystick {
+	imagesize:      Vector2,
+	base_imagename: String,
+	knob_imagename: String,
+	knob_size:      Vector2,
+	pointer:        Option<u8>,
+	axis:           Vector2,
+}
+
+impl UiVirtualJoystick {
+	pub fn new(base_imagename: &str, knob_imagename: &str, size: &Vector2) -> Self {
+		Self {
+			imagesize: *size,
+			base_imagename: base_imagename.to_owned(),
+			knob_imagename: knob_imagename.to_owned(),
+			knob_size: size.scaled(0.5),
+			..Default::default()
+		}
+	}
+
+	pub fn axis(&self) -> &Vector2 {
+		&self.axis
+	}
+
+	pub fn is_active(&self) -> bool {
+		self.pointer.is_some()
+	}
+
+	pub fn info() -> &'static UiElementInfo {
+		&UiElementInfo {
+			type_name:   "UiVirtualJoystick",
+			producer_fn: &Self::produce,
+		}
+	}
+
+	pub fn produce() -> Box<dyn UiElement> {
+		Box::new(Self::default())
+	}
+
+	// how far the knob can travel from the center
+	fn radius(&self) -> f32 {
+		0.5 * (self.imagesize.x.min(self.imagesize.y) - self.knob_size.x.min(self.knob_size.y))
+	}
+
+	fn update_axis(&mut self, container: &UiElementContainerData, pos: &Vector2) {
+		let radius = self.radius();
+		if radius <= 0.0 {
+			self.axis = Vector2::zero();
+			return;
+		}
+		let delta = pos.sub(&container.pos).scaled(1.0 / radius);
+		let l = delta.length();
+		self.axis = if l > 1.0 {
+			delta.scaled(1.0 / l)
+		} else {
+			delta
+		};
+	}
+
+	fn response(&self, container: &UiElementContainerData) -> Option<Box<dyn UiEventResponse>> {
+		Some(Box::new(UiEventResponseVirtualInput::new(
+			&container.name,
+			&self.axis,
+			self.is_active(),
+		)))
+	}
+}
+
+impl UiElement for UiVirtualJoystick {
+	fn type_name(&self) -> &str {
+		Self::info().type_name
+	}
+	fn as_any(&self) -> &dyn std::any::Any {
+		self
+	}
+	fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+		self
+	}
+	fn preferred_size(&self) -> Option<&Vector2> {
+		Some(&self.imagesize)
+	}
+	fn render(&self, container: &UiElementContainerData, ui_renderer: &mut UiRenderer) {
+		if *container.fade_state() != UiElementFadeState::FadedOut {
+			let l = container.get_fade_level();
+			ui_renderer.push_opacity(l);
+			ui_renderer.use_texture(&self.base_imagename);
+			ui_renderer.render_textured_quad(&container.pos, &self.imagesize);
+			let knob_pos = container.pos.add(&self.axis.scaled(self.radius()));
+			ui_renderer.use_texture(&self.knob_imagename);
+			ui_renderer.render_textured_quad(&knob_pos, &self.knob_size);
+			ui_renderer.pop_opacity();
+		}
+	}
+	fn handle_ui_event(
+		&mut self,
+		container: &mut UiElementContainerData,
+		event: &UiEvent,
+		_event_sender: &Sender<Box<dyn UiEventResponse>>,
+	) -> Option<Box<dyn UiEventResponse>> {
+		match *event {
+			UiEvent::PointerPressed { pos, pointer } if self.pointer.is_none() => {
+				self.pointer = Some(pointer);
+				self.update_axis(container, &pos);
+				self.response(container)
+			},
+			UiEvent::PointerHeld { pos, pointer } if self.pointer == Some(pointer) => {
+				self.update_axis(container, &pos);
+				self.response(container)
+			},
+			UiEvent::PointerReleased { pointer, .. } if self.pointer == Some(pointer) => {
+				self.pointer = None;
+				self.axis = Vector2::zero();
+				self.response(container)
+			},
+			_ => None,
+		}
+	}
+	fn configure_from_yaml_value(&mut self, yaml_value: serde_yaml::Value) {
+		let config: UiVirtualJoystickConfig = serde_yaml::from_value(yaml_value).unwrap();
+
+		self.imagesize = Vector2::from_x_str(&config.size);
+		self.knob_size = Vector2::from_x_str(&config.knob_size);
+		self.base_imagename = config.images[0].clone();
+		self.knob_imagename = config.images[1].clone();
+	}
+	fn to_yaml_config(&self) -> serde_yaml::Value {
+		serde_yaml::to_value(UiVirtualJoystickConfig {
+			images:    [self.base_imagename.clone(), self.knob_imagename.clone()],
+			size:      format!("{}x{}", self.imagesize.x, self.imagesize.y),
+			knob_size: format!("{}x{}", self.knob_size.x, self.knob_size.y),
+		})
+		.unwrap_or(serde_yaml::Value::Null)
+	}
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct UiVirtualJoystickConfig {
+	/// base, and knob
+	images:    [String; 2],
+	size:      String,
+	knob_size: String,
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::mpsc::channel;
+
+	use super::*;
+
+	fn axis(response: Option<Box<dyn UiEventResponse>>) -> Option<(Vector2, bool)> {
+		response.map(|r| {
+			let vi = r
+				.as_any()
+				.downcast_ref::<UiEventResponseVirtualInput>()
+				.unwrap();
+			(vi.axis, vi.is_down)
+		})
+	}
+
+	fn close_to(a: &Vector2, x: f32, y: f32) -> bool {
+		(a.x - x).abs() < 0.001 && (a.y - y).abs() < 0.001
+	}
+
+	#[test]
+	fn joystick_follows_the_pointer_that_pressed_it() {
+		let (tx, _rx) = channel();
+		let mut container = UiElementContainerData::new();
+		container.name = "stick".to_string();
+		container.pos = Vector2::new(100.0, 100.0);
+		// the knob can travel 50 from the center
+		let mut joystick = UiVirtualJoystick::new("base", "knob", &Vector2::new(200.0, 200.0));
+
+		let ev = UiEvent::PointerPressed {
+			pos:     Vector2::new(125.0, 100.0),
+			pointer: 0,
+		};
+		let (a, is_down) = axis(joystick.handle_ui_event(&mut container, &ev, &tx)).unwrap();
+		assert!(is_down);
+		assert!(close_to(&a, 0.5, 0.0));
+
+		// a second pointer is ignored, so it can press a button meanwhile
+		let ev = UiEvent::PointerHeld {
+			pos:     Vector2::new(100.0, 0.0),
+			pointer: 1,
+		};
+		assert!(axis(joystick.handle_ui_event(&mut container, &ev, &tx)).is_none());
+		assert!(close_to(joystick.axis(), 0.5, 0.0));
+
+		// the axis is clamped to the knob range
+		let ev = UiEvent::PointerHeld {
+			pos:     Vector2::new(100.0, 300.0),
+			pointer: 0,
+		};
+		let (a, _) = axis(joystick.handle_ui_event(&mut container, &ev, &tx)).unwrap();
+		assert!(close_to(&a, 0.0, 1.0));
+
+		let ev = UiEvent::PointerReleased {
+			pos:     Vector2::new(100.0, 300.0),
+			pointer: 0,
+		};
+		let (a, is_down) = axis(joystick.handle_ui_event(&mut container, &ev, &tx)).unwrap();
+		assert!(!is_down);
+		assert!(close_to(&a, 0.0, 0.0));
+		assert!(!joystick.is_active());
+	}
+}